@@ -1,39 +1,709 @@
 use std::collections::HashMap;
+use std::fmt;
 
 use crate::ctx::{TyCtxt, TyCtxtView};
+use crate::kindck;
+use crate::span::Span;
 
 use crate::trace;
 #[cfg(feature = "trace")]
 use tracing::instrument;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub enum Term {
+pub struct Term {
+    pub kind: TermKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TermKind {
     Unit,
     Appl(Box<Term>, Box<Term>),
-    Lambda(Box<Term>),
+    /// `\x. e`, with an optional annotation on `x`.
+    Lambda(Option<Ty>, Box<Term>),
     Bool(bool),
     Var(usize),
     Ann(Box<Term>, Ty),
     TypeAppl(Box<Term>, Ty),
+    /// `let x = e1 in e2`, with an optional annotation on `x`. `x` is bound
+    /// at index 0 in `e2` only.
+    Let(Option<Ty>, Box<Term>, Box<Term>),
+    /// `/\'a. e`. `'a` is bound at index 0 in the types in `e`, counting
+    /// outwards from the type abstraction.
+    TyAbs(Kind, Box<Term>),
+    /// `if c then t else e`
+    If(Box<Term>, Box<Term>, Box<Term>),
+    /// `fix f. e`, with an optional annotation on `f`. `f` is bound at index
+    /// 0 in `e`.
+    Fix(Option<Ty>, Box<Term>),
+    /// `(e1, e2, ...)`, with at least two components.
+    Tuple(Vec<Term>),
+    /// `e.i`
+    Proj(Box<Term>, usize),
+    /// `let (x1, ..., xn) = e1 in e2`, where the usize is `n`. The components
+    /// are bound in `e2` in order, so `xn` is at index 0.
+    LetTuple(usize, Box<Term>, Box<Term>),
+    /// `inl e`
+    Inl(Box<Term>),
+    /// `inr e`
+    Inr(Box<Term>),
+    /// `case e of inl x -> e1 | inr y -> e2`. `x` is bound at index 0 in
+    /// `e1`, and `y` at index 0 in `e2`.
+    Case(Box<Term>, Box<Term>, Box<Term>),
+    /// `{ x = e1, y = e2, ... }`, with the fields in the order they were
+    /// written, which is the order they are evaluated in.
+    Record(Vec<(String, Term)>),
+    /// `e.x`
+    Field(Box<Term>, String),
+    /// `<x = e>`
+    Variant(String, Box<Term>),
+    /// `case e of { <x = y> -> e1 | ... }`. Each arm binds its variable at
+    /// index 0 in its body.
+    VariantCase(Box<Term>, Vec<(String, Term)>),
+    /// `fold e`, which wraps `e` in a recursive type.
+    Fold(Box<Term>),
+    /// `unfold e`, which unwraps `e` from its recursive type.
+    Unfold(Box<Term>),
+    /// `pack [T, e] as U`, where `T` is the hidden witness type and `U` the
+    /// existential type.
+    Pack(Ty, Box<Term>, Ty),
+    /// `unpack e1 as ['a, x] in e2`. `'a` is bound at index 0 in the types in
+    /// `e2`, as with a type abstraction, and `x` at index 0 in `e2`.
+    Unpack(Box<Term>, Box<Term>),
+    /// A constructor of a data type, along with the number of fields it
+    /// takes.
+    Ctor(String, usize),
+    /// `absurd`, which turns a term of type `never` into one of any type.
+    Absurd,
+    /// `match e with { p1 -> e1 | ... }`. The variables bound by each pattern
+    /// are bound in its arm's body in order, so the last is at index 0.
+    Match(Box<Term>, Vec<(Pattern, Term)>),
+}
+
+/// A pattern in an arm of a `match`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pattern {
+    pub kind: PatternKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PatternKind {
+    /// `_`, which matches anything and binds nothing.
+    Wildcard,
+    /// A variable, which matches anything and binds it.
+    Var,
+    /// A constructor applied to a pattern for each of its fields.
+    Ctor(String, Vec<Pattern>),
 }
 
+/// A whole program: a sequence of top-level declarations, each of which may
+/// refer to the ones before it.
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub enum Ty {
+pub struct Program {
+    pub decls: Vec<Decl>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Decl {
+    pub kind: DeclKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeclKind {
+    /// `def name : ty = body;`. Earlier definitions are bound as term
+    /// variables in `body`, with the most recent one at index 0.
+    Def(String, Ty, Term),
+    /// `data Name 'a ... = C1 T ... | ...;`. Later declarations can refer to
+    /// the type and its constructors by name.
+    Data(DataDecl),
+    /// `type Name 'a ... = T;`. Uses of the synonym have already been
+    /// expanded, so this only matters for displaying types.
+    Type(Synonym),
+}
+
+/// A declared data type: a nominal type constructor and the constructors of
+/// its values.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DataDecl {
+    pub name: String,
+    /// The kind of each type parameter.
+    pub params: Vec<Kind>,
+    /// The name and field types of each constructor, in the order they were
+    /// declared. The parameters are bound in the field types as if by
+    /// enclosing foralls, so the first is at index `params.len() - 1`.
+    pub ctors: Vec<(String, Vec<Ty>)>,
+}
+
+impl DataDecl {
+    /// The type of the constructor at `idx` when used as a term: a function
+    /// from its fields to the data type, quantified over the parameters.
+    pub fn ctor_ty(&self, idx: usize, span: Span) -> Ty {
+        let result = Ty::new(
+            TyKind::Data(
+                self.name.clone(),
+                (0..self.params.len())
+                    .rev()
+                    .map(|idx| Ty::new(TyKind::Var(idx), span))
+                    .collect(),
+            ),
+            span,
+        );
+        let ty = self.ctors[idx].1.iter().rev().fold(result, |out, field| {
+            Ty::new(TyKind::Arrow(Box::new(field.clone()), Box::new(out)), span)
+        });
+        self.params.iter().rev().fold(ty, |body, kind| {
+            Ty::new(TyKind::Forall(kind.clone(), None, Box::new(body)), span)
+        })
+    }
+}
+
+/// A declared type synonym, which stands for its body with its arguments
+/// substituted for its parameters.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Synonym {
+    pub name: String,
+    /// The kind of each type parameter.
+    pub params: Vec<Kind>,
+    /// The type the synonym stands for, with the parameters bound as in the
+    /// fields of a [`DataDecl`].
+    pub body: Ty,
+}
+
+impl Synonym {
+    /// If `ty` is an expansion of this synonym, return the arguments it was
+    /// expanded with, so that it can be displayed as a use of the synonym
+    /// instead.
+    pub fn fold(&self, ty: &Ty) -> Option<Vec<Ty>> {
+        // Every type would be an expansion of a synonym for one of its
+        // parameters, including the argument it was expanded with.
+        if let TyKind::Var(_) = self.body.kind {
+            return None;
+        }
+        let mut args = vec![None; self.params.len()];
+        if fold_(&self.body, ty, 0, &mut args) {
+            args.into_iter().collect()
+        } else {
+            None
+        }
+    }
+}
+
+/// Whether `ty` matches `pat`, which is `depth` binders into the body of a
+/// synonym, filling in `args` with what its parameters matched.
+fn fold_(pat: &Ty, ty: &Ty, depth: usize, args: &mut [Option<Ty>]) -> bool {
+    let rows = |row1: &Row, row2: &Row, args: &mut [Option<Ty>]| {
+        row1.fields.len() == row2.fields.len()
+            && row1
+                .fields
+                .iter()
+                .zip(&row2.fields)
+                .all(|((l1, ty1), (l2, ty2))| l1 == l2 && fold_(ty1, ty2, depth, args))
+            && match (&row1.tail, &row2.tail) {
+                (Some(tail1), Some(tail2)) => fold_(tail1, tail2, depth, args),
+                (None, None) => true,
+                _ => false,
+            }
+    };
+    match (&pat.kind, &ty.kind) {
+        (TyKind::Var(idx), _) if *idx >= depth => {
+            // The argument can't mention the variables bound inside the body.
+            let mut escapes = false;
+            let arg = ty.clone().map_vars(0, &mut |idx, inner, span| {
+                if idx >= inner && idx - inner < depth {
+                    escapes = true;
+                }
+                if idx >= inner + depth {
+                    Ty::new(TyKind::Var(idx - depth), span)
+                } else {
+                    Ty::new(TyKind::Var(idx), span)
+                }
+            });
+            if escapes {
+                return false;
+            }
+            let slot = &mut args[args.len() - 1 - (idx - depth)];
+            match slot {
+                Some(prev) => *prev == arg,
+                None => {
+                    *slot = Some(arg);
+                    true
+                }
+            }
+        }
+        (TyKind::Bool, TyKind::Bool)
+        | (TyKind::Unit, TyKind::Unit)
+        | (TyKind::Top, TyKind::Top)
+        | (TyKind::Never, TyKind::Never) => true,
+        (TyKind::Var(idx1), TyKind::Var(idx2))
+        | (TyKind::UnivVar(idx1), TyKind::UnivVar(idx2))
+        | (TyKind::ExstVar(idx1), TyKind::ExstVar(idx2)) => idx1 == idx2,
+        (TyKind::Arrow(inp1, out1), TyKind::Arrow(inp2, out2))
+        | (TyKind::Sum(inp1, out1), TyKind::Sum(inp2, out2))
+        | (TyKind::Intersection(inp1, out1), TyKind::Intersection(inp2, out2))
+        | (TyKind::Union(inp1, out1), TyKind::Union(inp2, out2)) => {
+            fold_(inp1, inp2, depth, args) && fold_(out1, out2, depth, args)
+        }
+        (TyKind::Product(tys1), TyKind::Product(tys2)) => {
+            tys1.len() == tys2.len()
+                && tys1
+                    .iter()
+                    .zip(tys2)
+                    .all(|(ty1, ty2)| fold_(ty1, ty2, depth, args))
+        }
+        (TyKind::Record(row1), TyKind::Record(row2))
+        | (TyKind::Variant(row1), TyKind::Variant(row2))
+        | (TyKind::Row(row1), TyKind::Row(row2)) => rows(row1, row2, args),
+        (TyKind::Forall(kind1, bound1, body1), TyKind::Forall(kind2, bound2, body2)) => {
+            kind1 == kind2
+                && match (bound1, bound2) {
+                    (Some(bound1), Some(bound2)) => fold_(bound1, bound2, depth, args),
+                    (None, None) => true,
+                    _ => false,
+                }
+                && fold_(body1, body2, depth + 1, args)
+        }
+        (TyKind::Lam(kind1, body1), TyKind::Lam(kind2, body2)) => {
+            kind1 == kind2 && fold_(body1, body2, depth + 1, args)
+        }
+        (TyKind::Mu(body1), TyKind::Mu(body2)) | (TyKind::Exists(body1), TyKind::Exists(body2)) => {
+            fold_(body1, body2, depth + 1, args)
+        }
+        (TyKind::App(func1, arg1), TyKind::App(func2, arg2)) => {
+            fold_(func1, func2, depth, args) && fold_(arg1, arg2, depth, args)
+        }
+        (TyKind::Data(name1, tys1), TyKind::Data(name2, tys2)) => {
+            name1 == name2
+                && tys1.len() == tys2.len()
+                && tys1
+                    .iter()
+                    .zip(tys2)
+                    .all(|(ty1, ty2)| fold_(ty1, ty2, depth, args))
+        }
+        _ => false,
+    }
+}
+
+impl Pattern {
+    pub fn new(kind: PatternKind, span: Span) -> Self {
+        Self { kind, span }
+    }
+
+    /// The number of variables this pattern binds.
+    pub fn binders(&self) -> usize {
+        match &self.kind {
+            PatternKind::Wildcard => 0,
+            PatternKind::Var => 1,
+            PatternKind::Ctor(_, pats) => pats.iter().map(Pattern::binders).sum(),
+        }
+    }
+}
+
+/// A type, along with the span of source it originated from.
+///
+/// Types synthesised by the typechecker carry the span of the term or type
+/// that caused them to be created. Spans are not semantically meaningful, so
+/// they are ignored when comparing types for equality.
+#[derive(Debug, Clone)]
+pub struct Ty {
+    pub kind: TyKind,
+    pub span: Span,
+}
+
+impl PartialEq for Ty {
+    fn eq(&self, other: &Self) -> bool {
+        self.kind == other.kind
+    }
+}
+
+impl Eq for Ty {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TyKind {
     Bool,
     Unit,
+    /// `top`, the type that every type is a subtype of.
+    Top,
+    /// `never`, the type that is a subtype of every type, and has no terms.
+    Never,
     Arrow(Box<Ty>, Box<Ty>),
-    Forall(Box<Ty>),
+    /// `A * B * ...`, with at least two components.
+    Product(Vec<Ty>),
+    /// `A + B`
+    Sum(Box<Ty>, Box<Ty>),
+    /// `A & B`, the type of terms that are both `A`s and `B`s.
+    Intersection(Box<Ty>, Box<Ty>),
+    /// `A | B`, the type of terms that are either `A`s or `B`s.
+    Union(Box<Ty>, Box<Ty>),
+    /// `{ x : A, y : B, ... }` or `{ x : A, ... | 'r }`
+    Record(Row),
+    /// `< x : A, y : B, ... >` or `< x : A, ... | 'r >`
+    Variant(Row),
+    /// The solution of a row existential. When it is substituted into the
+    /// tail of a row, its fields are added to that row's.
+    Row(Row),
+    /// `'a => T`, or `'a <: B => T` if `'a` is bounded above by `B`. `'a` is
+    /// bound at index 0 in `T`, but not in `B`, which is outside its scope.
+    Forall(Kind, Option<Box<Ty>>, Box<Ty>),
+    /// `mu 'a. T`, the iso-recursive type whose values are `fold`ed `T`s in
+    /// which `'a` stands for the whole type again. `'a` is bound at index 0.
+    Mu(Box<Ty>),
+    /// `exists 'a. T`. `'a` is bound at index 0.
+    Exists(Box<Ty>),
+    /// A declared data type applied to a type for each of its parameters.
+    /// Data types are nominal: two are only equal if their names are.
+    Data(String, Vec<Ty>),
+    /// `\'a. T`, a type operator. `'a` is bound at index 0.
+    Lam(Kind, Box<Ty>),
+    /// A type operator applied to an argument. Types are compared up to
+    /// beta-reduction (see [`Ty::normalize`]).
+    App(Box<Ty>, Box<Ty>),
+    /// A variable bound by an enclosing `Forall`, `Mu`, `Exists` or `Lam`, by
+    /// De Bruijn index.
     Var(usize),
+    /// A universal variable in the typing context, introduced when a
+    /// `Forall` (or a type abstraction) is opened.
+    UnivVar(usize),
     ExstVar(usize),
 }
 
+/// The kind of a type variable.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Kind {
+    /// An ordinary type.
+    Type,
+    /// A row of labelled types, which can only be used as the tail of a
+    /// record or variant type.
+    Row,
+    /// `k1 -> k2`, a type operator taking a type of kind `k1` to one of kind
+    /// `k2`.
+    Arrow(Box<Kind>, Box<Kind>),
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Kind::Type => write!(f, "*"),
+            Kind::Row => write!(f, "row"),
+            Kind::Arrow(inp, out) => match **inp {
+                Kind::Arrow(..) => write!(f, "({}) -> {}", inp, out),
+                _ => write!(f, "{} -> {}", inp, out),
+            },
+        }
+    }
+}
+
+/// The labelled fields of a record or variant type, sorted by label with no
+/// label appearing twice, and optionally a tail: a row variable (bound,
+/// universal or existential) standing for any further fields.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Row {
+    pub fields: Vec<(String, Ty)>,
+    pub tail: Option<Box<Ty>>,
+}
+
+impl Row {
+    pub fn new(mut fields: Vec<(String, Ty)>, tail: Option<Ty>) -> Self {
+        fields.sort_by(|(a, _), (b, _)| a.cmp(b));
+        Self {
+            fields,
+            tail: tail.map(Box::new),
+        }
+    }
+
+    /// A row with no tail.
+    pub fn closed(fields: Vec<(String, Ty)>) -> Self {
+        Self::new(fields, None)
+    }
+
+    pub fn get(&self, label: &str) -> Option<&Ty> {
+        self.fields
+            .iter()
+            .find(|(l, _)| l == label)
+            .map(|(_, ty)| ty)
+    }
+
+    /// The field types, followed by the tail.
+    pub fn tys(&self) -> impl Iterator<Item = &Ty> {
+        self.fields
+            .iter()
+            .map(|(_, ty)| ty)
+            .chain(self.tail.as_deref())
+    }
+
+    /// Apply `f` to each field type and the tail. If the tail becomes a
+    /// [`TyKind::Row`], its fields are added to this row's, except for labels
+    /// this row already has.
+    #[must_use]
+    pub fn map(self, mut f: impl FnMut(Ty) -> Ty) -> Row {
+        let mut fields = self
+            .fields
+            .into_iter()
+            .map(|(label, ty)| (label, f(ty)))
+            .collect::<Vec<_>>();
+        let mut tail = self.tail.map(|tail| f(*tail));
+        while let Some(Ty {
+            kind: TyKind::Row(row),
+            ..
+        }) = tail
+        {
+            for (label, ty) in row.fields {
+                if !fields.iter().any(|(l, _)| l == &label) {
+                    fields.push((label, ty));
+                }
+            }
+            tail = row.tail.map(|tail| *tail);
+        }
+        Row::new(fields, tail)
+    }
+}
+
+impl Term {
+    pub fn new(kind: TermKind, span: Span) -> Self {
+        Self { kind, span }
+    }
+
+    /// Open the body of a type abstraction, substituting `with` for the type
+    /// variable it bound in every type inside it.
+    pub fn open_ty(self, with: &Ty) -> Term {
+        self.open_ty_(with, 0)
+    }
+
+    fn open_ty_(self, with: &Ty, depth: usize) -> Term {
+        let open = |ty: Ty| ty.open_(with, depth);
+        let kind = match self.kind {
+            TermKind::Unit | TermKind::Bool(_) | TermKind::Absurd | TermKind::Var(_) => self.kind,
+            TermKind::Appl(func, arg) => TermKind::Appl(
+                Box::new(func.open_ty_(with, depth)),
+                Box::new(arg.open_ty_(with, depth)),
+            ),
+            TermKind::Lambda(ty, body) => {
+                TermKind::Lambda(ty.map(open), Box::new(body.open_ty_(with, depth)))
+            }
+            TermKind::Ann(term, ty) => {
+                TermKind::Ann(Box::new(term.open_ty_(with, depth)), open(ty))
+            }
+            TermKind::TypeAppl(term, ty) => {
+                TermKind::TypeAppl(Box::new(term.open_ty_(with, depth)), open(ty))
+            }
+            TermKind::Let(ty, val, body) => TermKind::Let(
+                ty.map(open),
+                Box::new(val.open_ty_(with, depth)),
+                Box::new(body.open_ty_(with, depth)),
+            ),
+            TermKind::TyAbs(kind, body) => {
+                TermKind::TyAbs(kind, Box::new(body.open_ty_(with, depth + 1)))
+            }
+            TermKind::If(cond, then, els) => TermKind::If(
+                Box::new(cond.open_ty_(with, depth)),
+                Box::new(then.open_ty_(with, depth)),
+                Box::new(els.open_ty_(with, depth)),
+            ),
+            TermKind::Fix(ty, body) => {
+                TermKind::Fix(ty.map(open), Box::new(body.open_ty_(with, depth)))
+            }
+            TermKind::Tuple(terms) => TermKind::Tuple(
+                terms
+                    .into_iter()
+                    .map(|term| term.open_ty_(with, depth))
+                    .collect(),
+            ),
+            TermKind::Proj(term, idx) => TermKind::Proj(Box::new(term.open_ty_(with, depth)), idx),
+            TermKind::LetTuple(len, val, body) => TermKind::LetTuple(
+                len,
+                Box::new(val.open_ty_(with, depth)),
+                Box::new(body.open_ty_(with, depth)),
+            ),
+            TermKind::Inl(term) => TermKind::Inl(Box::new(term.open_ty_(with, depth))),
+            TermKind::Inr(term) => TermKind::Inr(Box::new(term.open_ty_(with, depth))),
+            TermKind::Case(scrut, left, right) => TermKind::Case(
+                Box::new(scrut.open_ty_(with, depth)),
+                Box::new(left.open_ty_(with, depth)),
+                Box::new(right.open_ty_(with, depth)),
+            ),
+            TermKind::Record(fields) => TermKind::Record(
+                fields
+                    .into_iter()
+                    .map(|(label, term)| (label, term.open_ty_(with, depth)))
+                    .collect(),
+            ),
+            TermKind::Field(term, label) => {
+                TermKind::Field(Box::new(term.open_ty_(with, depth)), label)
+            }
+            TermKind::Variant(label, term) => {
+                TermKind::Variant(label, Box::new(term.open_ty_(with, depth)))
+            }
+            TermKind::VariantCase(scrut, arms) => TermKind::VariantCase(
+                Box::new(scrut.open_ty_(with, depth)),
+                arms.into_iter()
+                    .map(|(label, term)| (label, term.open_ty_(with, depth)))
+                    .collect(),
+            ),
+            TermKind::Fold(term) => TermKind::Fold(Box::new(term.open_ty_(with, depth))),
+            TermKind::Unfold(term) => TermKind::Unfold(Box::new(term.open_ty_(with, depth))),
+            TermKind::Pack(witness, term, ty) => TermKind::Pack(
+                open(witness),
+                Box::new(term.open_ty_(with, depth)),
+                open(ty),
+            ),
+            TermKind::Unpack(packed, body) => TermKind::Unpack(
+                Box::new(packed.open_ty_(with, depth)),
+                Box::new(body.open_ty_(with, depth + 1)),
+            ),
+            TermKind::Ctor(..) => self.kind,
+            TermKind::Match(scrut, arms) => TermKind::Match(
+                Box::new(scrut.open_ty_(with, depth)),
+                arms.into_iter()
+                    .map(|(pat, term)| (pat, term.open_ty_(with, depth)))
+                    .collect(),
+            ),
+        };
+        Term::new(kind, self.span)
+    }
+
+    /// Whether this term uses `fix`. Terms that don't are strongly
+    /// normalising, as long as the variables they refer to are.
+    pub fn uses_fix(&self) -> bool {
+        match &self.kind {
+            TermKind::Unit
+            | TermKind::Bool(_)
+            | TermKind::Absurd
+            | TermKind::Var(_)
+            | TermKind::Ctor(..) => false,
+            TermKind::Fix(..) => true,
+            TermKind::Lambda(_, term)
+            | TermKind::Ann(term, _)
+            | TermKind::TypeAppl(term, _)
+            | TermKind::TyAbs(_, term)
+            | TermKind::Proj(term, _)
+            | TermKind::Inl(term)
+            | TermKind::Inr(term)
+            | TermKind::Field(term, _)
+            | TermKind::Variant(_, term)
+            | TermKind::Fold(term)
+            | TermKind::Unfold(term)
+            | TermKind::Pack(_, term, _) => term.uses_fix(),
+            TermKind::VariantCase(scrut, arms) => {
+                scrut.uses_fix() || arms.iter().any(|(_, term)| term.uses_fix())
+            }
+            TermKind::Match(scrut, arms) => {
+                scrut.uses_fix() || arms.iter().any(|(_, term)| term.uses_fix())
+            }
+            TermKind::Tuple(terms) => terms.iter().any(Term::uses_fix),
+            TermKind::Record(fields) => fields.iter().any(|(_, term)| term.uses_fix()),
+            TermKind::Appl(term1, term2)
+            | TermKind::Let(_, term1, term2)
+            | TermKind::LetTuple(_, term1, term2)
+            | TermKind::Unpack(term1, term2) => term1.uses_fix() || term2.uses_fix(),
+            TermKind::If(term1, term2, term3) | TermKind::Case(term1, term2, term3) => {
+                term1.uses_fix() || term2.uses_fix() || term3.uses_fix()
+            }
+        }
+    }
+
+    /// The free term variables of this term, by De Bruijn index relative to
+    /// the term itself.
+    pub fn free_vars(&self) -> Vec<usize> {
+        let mut acc = Vec::new();
+        self.free_vars_(0, &mut acc);
+        acc
+    }
+
+    fn free_vars_(&self, depth: usize, acc: &mut Vec<usize>) {
+        match &self.kind {
+            TermKind::Unit | TermKind::Bool(_) | TermKind::Absurd | TermKind::Ctor(..) => (),
+            TermKind::Var(idx) => {
+                if *idx >= depth && !acc.contains(&(idx - depth)) {
+                    acc.push(idx - depth);
+                }
+            }
+            TermKind::Lambda(_, body) | TermKind::Fix(_, body) => body.free_vars_(depth + 1, acc),
+            TermKind::Ann(term, _)
+            | TermKind::TypeAppl(term, _)
+            | TermKind::TyAbs(_, term)
+            | TermKind::Proj(term, _)
+            | TermKind::Inl(term)
+            | TermKind::Inr(term)
+            | TermKind::Field(term, _)
+            | TermKind::Variant(_, term)
+            | TermKind::Fold(term)
+            | TermKind::Unfold(term)
+            | TermKind::Pack(_, term, _) => {
+                term.free_vars_(depth, acc);
+            }
+            TermKind::VariantCase(scrut, arms) => {
+                scrut.free_vars_(depth, acc);
+                for (_, term) in arms {
+                    term.free_vars_(depth + 1, acc);
+                }
+            }
+            TermKind::Match(scrut, arms) => {
+                scrut.free_vars_(depth, acc);
+                for (pat, term) in arms {
+                    term.free_vars_(depth + pat.binders(), acc);
+                }
+            }
+            TermKind::Record(fields) => {
+                for (_, term) in fields {
+                    term.free_vars_(depth, acc);
+                }
+            }
+            TermKind::Tuple(terms) => {
+                for term in terms {
+                    term.free_vars_(depth, acc);
+                }
+            }
+            TermKind::LetTuple(len, val, body) => {
+                val.free_vars_(depth, acc);
+                body.free_vars_(depth + len, acc);
+            }
+            TermKind::Appl(func, arg) => {
+                func.free_vars_(depth, acc);
+                arg.free_vars_(depth, acc);
+            }
+            TermKind::Let(_, val, body) | TermKind::Unpack(val, body) => {
+                val.free_vars_(depth, acc);
+                body.free_vars_(depth + 1, acc);
+            }
+            TermKind::If(cond, then, els) => {
+                cond.free_vars_(depth, acc);
+                then.free_vars_(depth, acc);
+                els.free_vars_(depth, acc);
+            }
+            TermKind::Case(scrut, left, right) => {
+                scrut.free_vars_(depth, acc);
+                left.free_vars_(depth + 1, acc);
+                right.free_vars_(depth + 1, acc);
+            }
+        }
+    }
+}
+
+impl Decl {
+    pub fn new(kind: DeclKind, span: Span) -> Self {
+        Self { kind, span }
+    }
+}
+
 impl Ty {
+    pub fn new(kind: TyKind, span: Span) -> Self {
+        Self { kind, span }
+    }
+
+    /// Create a type with the same span as this one.
+    #[must_use]
+    pub fn with_kind(&self, kind: TyKind) -> Ty {
+        Ty::new(kind, self.span)
+    }
+
     pub fn subst_ctx(self, ctx: &TyCtxt) -> Ty {
         let solved = ctx.get_solved();
         let mut zelf = self;
         loop {
             if !zelf.contains_evars(&solved) {
-                break zelf;
+                // An existential may have been solved with a type operator.
+                break zelf.normalize();
             }
             zelf = zelf.subst_ctx_once(ctx, &solved);
         }
@@ -42,40 +712,310 @@ impl Ty {
     #[cfg_attr(feature = "trace", instrument(level = "trace", skip(ctx)))]
     fn subst_ctx_once(self, ctx: &TyCtxt, solved: &HashMap<usize, &Ty>) -> Ty {
         trace!("ty/subst_ctx_once/enter");
-        let res = match self {
-            Ty::Bool | Ty::Unit | Ty::Var(_) => self,
-            Ty::Forall(body) => Ty::Forall(Box::new(body.subst_ctx_once(ctx, solved))),
-            Ty::Arrow(inp, out) => Ty::Arrow(
-                Box::new(inp.subst_ctx_once(ctx, solved)),
-                Box::new(out.subst_ctx_once(ctx, solved)),
-            ),
-            Ty::ExstVar(evar) => solved.get(&evar).copied().cloned().unwrap_or(self),
+        let span = self.span;
+        let res = match self.kind {
+            TyKind::Bool
+            | TyKind::Unit
+            | TyKind::Top
+            | TyKind::Never
+            | TyKind::Var(_)
+            | TyKind::UnivVar(_) => self,
+            TyKind::Forall(kind, bound, body) => Ty::new(
+                TyKind::Forall(
+                    kind,
+                    bound.map(|bound| Box::new(bound.subst_ctx_once(ctx, solved))),
+                    Box::new(body.subst_ctx_once(ctx, solved)),
+                ),
+                span,
+            ),
+            TyKind::Lam(kind, body) => Ty::new(
+                TyKind::Lam(kind, Box::new(body.subst_ctx_once(ctx, solved))),
+                span,
+            ),
+            TyKind::App(func, arg) => Ty::new(
+                TyKind::App(
+                    Box::new(func.subst_ctx_once(ctx, solved)),
+                    Box::new(arg.subst_ctx_once(ctx, solved)),
+                ),
+                span,
+            ),
+            TyKind::Mu(body) => {
+                Ty::new(TyKind::Mu(Box::new(body.subst_ctx_once(ctx, solved))), span)
+            }
+            TyKind::Exists(body) => Ty::new(
+                TyKind::Exists(Box::new(body.subst_ctx_once(ctx, solved))),
+                span,
+            ),
+            TyKind::Arrow(inp, out) => Ty::new(
+                TyKind::Arrow(
+                    Box::new(inp.subst_ctx_once(ctx, solved)),
+                    Box::new(out.subst_ctx_once(ctx, solved)),
+                ),
+                span,
+            ),
+            TyKind::Product(tys) => Ty::new(
+                TyKind::Product(
+                    tys.into_iter()
+                        .map(|ty| ty.subst_ctx_once(ctx, solved))
+                        .collect(),
+                ),
+                span,
+            ),
+            TyKind::Sum(left, right) => Ty::new(
+                TyKind::Sum(
+                    Box::new(left.subst_ctx_once(ctx, solved)),
+                    Box::new(right.subst_ctx_once(ctx, solved)),
+                ),
+                span,
+            ),
+            TyKind::Intersection(left, right) => Ty::new(
+                TyKind::Intersection(
+                    Box::new(left.subst_ctx_once(ctx, solved)),
+                    Box::new(right.subst_ctx_once(ctx, solved)),
+                ),
+                span,
+            ),
+            TyKind::Union(left, right) => Ty::new(
+                TyKind::Union(
+                    Box::new(left.subst_ctx_once(ctx, solved)),
+                    Box::new(right.subst_ctx_once(ctx, solved)),
+                ),
+                span,
+            ),
+            TyKind::Record(row) => Ty::new(
+                TyKind::Record(row.map(|ty| ty.subst_ctx_once(ctx, solved))),
+                span,
+            ),
+            TyKind::Variant(row) => Ty::new(
+                TyKind::Variant(row.map(|ty| ty.subst_ctx_once(ctx, solved))),
+                span,
+            ),
+            TyKind::Row(row) => Ty::new(
+                TyKind::Row(row.map(|ty| ty.subst_ctx_once(ctx, solved))),
+                span,
+            ),
+            TyKind::Data(name, args) => Ty::new(
+                TyKind::Data(
+                    name,
+                    args.into_iter()
+                        .map(|ty| ty.subst_ctx_once(ctx, solved))
+                        .collect(),
+                ),
+                span,
+            ),
+            TyKind::ExstVar(evar) => match solved.get(&evar) {
+                Some(ty) => Ty::new(ty.kind.clone(), span),
+                None => self,
+            },
         };
         trace!(?res, "ty/subst_ctx_once/leave");
         res
     }
 
-    /// Substitute the bare body of a forall,
-    /// potentially with remaining unbound Var(0)s.
-    pub fn subst_uvar0_bare(self, with: &Ty) -> Ty {
-        self.subst_uvar0_bare_(with, 0)
+    /// Open the body of a forall, mu or exists, substituting `with` for the variable
+    /// it bound.
+    pub fn open(self, with: &Ty) -> Ty {
+        self.open_(with, 0)
+    }
+
+    /// Substitute `args` for the parameters of a data type in one of its
+    /// constructors' field types, or of a synonym in its body. The arguments
+    /// may mention variables bound outside of this type.
+    pub fn instantiate(self, args: &[Ty]) -> Ty {
+        let len = args.len();
+        self.map_vars(0, &mut |idx, depth, span| {
+            if idx < depth {
+                Ty::new(TyKind::Var(idx), span)
+            } else if idx - depth < len {
+                // Shift the argument's own free variables past the binders
+                // it is substituted under.
+                args[len - 1 - (idx - depth)].clone().shift(depth)
+            } else {
+                Ty::new(TyKind::Var(idx - len), span)
+            }
+        })
+    }
+
+    /// Shift the variables bound outside of this type by `by`, so that it
+    /// can be moved under that many more binders.
+    #[must_use]
+    pub fn shift(self, by: usize) -> Ty {
+        self.map_vars(0, &mut |idx, depth, span| {
+            Ty::new(TyKind::Var(if idx >= depth { idx + by } else { idx }), span)
+        })
+    }
+
+    /// Replace each bound variable with `f(idx, depth, span)`, where `depth`
+    /// is the number of binders it is under within this type.
+    fn map_vars(self, depth: usize, f: &mut impl FnMut(usize, usize, Span) -> Ty) -> Ty {
+        let span = self.span;
+        match self.kind {
+            TyKind::Bool
+            | TyKind::Unit
+            | TyKind::Top
+            | TyKind::Never
+            | TyKind::UnivVar(_)
+            | TyKind::ExstVar(_) => self,
+            TyKind::Var(idx) => f(idx, depth, span),
+            TyKind::Forall(kind, bound, body) => Ty::new(
+                TyKind::Forall(
+                    kind,
+                    bound.map(|bound| Box::new(bound.map_vars(depth, f))),
+                    Box::new(body.map_vars(depth + 1, f)),
+                ),
+                span,
+            ),
+            TyKind::Lam(kind, body) => Ty::new(
+                TyKind::Lam(kind, Box::new(body.map_vars(depth + 1, f))),
+                span,
+            ),
+            TyKind::App(func, arg) => Ty::new(
+                TyKind::App(
+                    Box::new(func.map_vars(depth, f)),
+                    Box::new(arg.map_vars(depth, f)),
+                ),
+                span,
+            ),
+            TyKind::Mu(body) => Ty::new(TyKind::Mu(Box::new(body.map_vars(depth + 1, f))), span),
+            TyKind::Exists(body) => {
+                Ty::new(TyKind::Exists(Box::new(body.map_vars(depth + 1, f))), span)
+            }
+            TyKind::Arrow(inp, out) => Ty::new(
+                TyKind::Arrow(
+                    Box::new(inp.map_vars(depth, f)),
+                    Box::new(out.map_vars(depth, f)),
+                ),
+                span,
+            ),
+            TyKind::Product(tys) => Ty::new(
+                TyKind::Product(tys.into_iter().map(|ty| ty.map_vars(depth, f)).collect()),
+                span,
+            ),
+            TyKind::Sum(left, right) => Ty::new(
+                TyKind::Sum(
+                    Box::new(left.map_vars(depth, f)),
+                    Box::new(right.map_vars(depth, f)),
+                ),
+                span,
+            ),
+            TyKind::Intersection(left, right) => Ty::new(
+                TyKind::Intersection(
+                    Box::new(left.map_vars(depth, f)),
+                    Box::new(right.map_vars(depth, f)),
+                ),
+                span,
+            ),
+            TyKind::Union(left, right) => Ty::new(
+                TyKind::Union(
+                    Box::new(left.map_vars(depth, f)),
+                    Box::new(right.map_vars(depth, f)),
+                ),
+                span,
+            ),
+            TyKind::Record(row) => {
+                Ty::new(TyKind::Record(row.map(|ty| ty.map_vars(depth, f))), span)
+            }
+            TyKind::Variant(row) => {
+                Ty::new(TyKind::Variant(row.map(|ty| ty.map_vars(depth, f))), span)
+            }
+            TyKind::Row(row) => Ty::new(TyKind::Row(row.map(|ty| ty.map_vars(depth, f))), span),
+            TyKind::Data(name, args) => Ty::new(
+                TyKind::Data(
+                    name,
+                    args.into_iter().map(|ty| ty.map_vars(depth, f)).collect(),
+                ),
+                span,
+            ),
+        }
     }
 
-    pub fn subst_uvar0(self, with: &Ty) -> Ty {
-        self.subst_uvar0_(with, 0)
+    /// Close over a universal variable, giving the body of a forall that
+    /// binds it.
+    pub fn close(self, uvar: usize) -> Ty {
+        self.close_(uvar, 0)
     }
 
     #[cfg_attr(feature = "trace", instrument(level = "trace"))]
-    fn subst_uvar0_bare_(self, with: &Ty, depth: usize) -> Ty {
-        trace!("ty/subst_uvar0_bare/enter");
-        let res = match self {
-            Ty::Bool | Ty::Unit | Ty::ExstVar(_) => self,
-            Ty::Forall(body) => Ty::Forall(Box::new(body.subst_uvar0_bare_(with, depth + 1))),
-            Ty::Arrow(inp, out) => Ty::Arrow(
-                Box::new(inp.subst_uvar0_bare_(with, depth)),
-                Box::new(out.subst_uvar0_bare_(with, depth)),
-            ),
-            Ty::Var(idx) => {
+    fn open_(self, with: &Ty, depth: usize) -> Ty {
+        trace!("ty/open/enter");
+        let span = self.span;
+        let res = match self.kind {
+            TyKind::Bool
+            | TyKind::Unit
+            | TyKind::Top
+            | TyKind::Never
+            | TyKind::UnivVar(_)
+            | TyKind::ExstVar(_) => self,
+            TyKind::Forall(kind, bound, body) => Ty::new(
+                TyKind::Forall(
+                    kind,
+                    bound.map(|bound| Box::new(bound.open_(with, depth))),
+                    Box::new(body.open_(with, depth + 1)),
+                ),
+                span,
+            ),
+            TyKind::Lam(kind, body) => Ty::new(
+                TyKind::Lam(kind, Box::new(body.open_(with, depth + 1))),
+                span,
+            ),
+            TyKind::App(func, arg) => Ty::new(
+                TyKind::App(
+                    Box::new(func.open_(with, depth)),
+                    Box::new(arg.open_(with, depth)),
+                ),
+                span,
+            ),
+            TyKind::Mu(body) => Ty::new(TyKind::Mu(Box::new(body.open_(with, depth + 1))), span),
+            TyKind::Exists(body) => {
+                Ty::new(TyKind::Exists(Box::new(body.open_(with, depth + 1))), span)
+            }
+            TyKind::Arrow(inp, out) => Ty::new(
+                TyKind::Arrow(
+                    Box::new(inp.open_(with, depth)),
+                    Box::new(out.open_(with, depth)),
+                ),
+                span,
+            ),
+            TyKind::Product(tys) => Ty::new(
+                TyKind::Product(tys.into_iter().map(|ty| ty.open_(with, depth)).collect()),
+                span,
+            ),
+            TyKind::Sum(left, right) => Ty::new(
+                TyKind::Sum(
+                    Box::new(left.open_(with, depth)),
+                    Box::new(right.open_(with, depth)),
+                ),
+                span,
+            ),
+            TyKind::Intersection(left, right) => Ty::new(
+                TyKind::Intersection(
+                    Box::new(left.open_(with, depth)),
+                    Box::new(right.open_(with, depth)),
+                ),
+                span,
+            ),
+            TyKind::Union(left, right) => Ty::new(
+                TyKind::Union(
+                    Box::new(left.open_(with, depth)),
+                    Box::new(right.open_(with, depth)),
+                ),
+                span,
+            ),
+            TyKind::Record(row) => {
+                Ty::new(TyKind::Record(row.map(|ty| ty.open_(with, depth))), span)
+            }
+            TyKind::Variant(row) => {
+                Ty::new(TyKind::Variant(row.map(|ty| ty.open_(with, depth))), span)
+            }
+            TyKind::Row(row) => Ty::new(TyKind::Row(row.map(|ty| ty.open_(with, depth))), span),
+            TyKind::Data(name, args) => Ty::new(
+                TyKind::Data(
+                    name,
+                    args.into_iter().map(|ty| ty.open_(with, depth)).collect(),
+                ),
+                span,
+            ),
+            TyKind::Var(idx) => {
                 if idx == depth {
                     with.clone()
                 } else {
@@ -83,96 +1023,348 @@ impl Ty {
                 }
             }
         };
-        trace!(?res, "ty/subst_uvar0_bare/leave");
+        trace!(?res, "ty/open/leave");
         res
     }
 
     #[cfg_attr(feature = "trace", instrument(level = "trace"))]
-    fn subst_uvar0_(self, with: &Ty, depth: usize) -> Ty {
-        trace!("ty/subst_uvar0/enter");
-        let res = match self {
-            Ty::Bool | Ty::Unit | Ty::ExstVar(_) => self,
-            Ty::Forall(body) => {
-                let subst = body.subst_uvar0_(with, depth + 1);
-                if depth == 0 {
-                    subst
-                } else {
-                    Ty::Forall(Box::new(subst))
-                }
+    fn close_(self, uvar: usize, depth: usize) -> Ty {
+        trace!("ty/close/enter");
+        let span = self.span;
+        let res = match self.kind {
+            TyKind::Bool
+            | TyKind::Unit
+            | TyKind::Top
+            | TyKind::Never
+            | TyKind::Var(_)
+            | TyKind::ExstVar(_) => self,
+            TyKind::Forall(kind, bound, body) => Ty::new(
+                TyKind::Forall(
+                    kind,
+                    bound.map(|bound| Box::new(bound.close_(uvar, depth))),
+                    Box::new(body.close_(uvar, depth + 1)),
+                ),
+                span,
+            ),
+            TyKind::Lam(kind, body) => Ty::new(
+                TyKind::Lam(kind, Box::new(body.close_(uvar, depth + 1))),
+                span,
+            ),
+            TyKind::App(func, arg) => Ty::new(
+                TyKind::App(
+                    Box::new(func.close_(uvar, depth)),
+                    Box::new(arg.close_(uvar, depth)),
+                ),
+                span,
+            ),
+            TyKind::Mu(body) => Ty::new(TyKind::Mu(Box::new(body.close_(uvar, depth + 1))), span),
+            TyKind::Exists(body) => {
+                Ty::new(TyKind::Exists(Box::new(body.close_(uvar, depth + 1))), span)
             }
-            Ty::Arrow(inp, out) => Ty::Arrow(
-                Box::new(inp.subst_uvar0_(with, depth)),
-                Box::new(out.subst_uvar0_(with, depth)),
+            TyKind::Arrow(inp, out) => Ty::new(
+                TyKind::Arrow(
+                    Box::new(inp.close_(uvar, depth)),
+                    Box::new(out.close_(uvar, depth)),
+                ),
+                span,
             ),
-            Ty::Var(idx) => {
-                if idx + 1 == depth {
-                    with.clone()
+            TyKind::Product(tys) => Ty::new(
+                TyKind::Product(tys.into_iter().map(|ty| ty.close_(uvar, depth)).collect()),
+                span,
+            ),
+            TyKind::Sum(left, right) => Ty::new(
+                TyKind::Sum(
+                    Box::new(left.close_(uvar, depth)),
+                    Box::new(right.close_(uvar, depth)),
+                ),
+                span,
+            ),
+            TyKind::Intersection(left, right) => Ty::new(
+                TyKind::Intersection(
+                    Box::new(left.close_(uvar, depth)),
+                    Box::new(right.close_(uvar, depth)),
+                ),
+                span,
+            ),
+            TyKind::Union(left, right) => Ty::new(
+                TyKind::Union(
+                    Box::new(left.close_(uvar, depth)),
+                    Box::new(right.close_(uvar, depth)),
+                ),
+                span,
+            ),
+            TyKind::Record(row) => {
+                Ty::new(TyKind::Record(row.map(|ty| ty.close_(uvar, depth))), span)
+            }
+            TyKind::Variant(row) => {
+                Ty::new(TyKind::Variant(row.map(|ty| ty.close_(uvar, depth))), span)
+            }
+            TyKind::Row(row) => Ty::new(TyKind::Row(row.map(|ty| ty.close_(uvar, depth))), span),
+            TyKind::Data(name, args) => Ty::new(
+                TyKind::Data(
+                    name,
+                    args.into_iter().map(|ty| ty.close_(uvar, depth)).collect(),
+                ),
+                span,
+            ),
+            TyKind::UnivVar(u) => {
+                if u == uvar {
+                    Ty::new(TyKind::Var(depth), span)
                 } else {
                     self
                 }
             }
         };
-        trace!(?res, "ty/subst_uvar0/leave");
+        trace!(?res, "ty/close/leave");
         res
     }
 
     #[cfg_attr(feature = "trace", instrument(level = "trace"))]
     pub fn contains_evar(&self, idx: usize) -> bool {
         trace!("ty/contains_evar/enter");
-        let res = match self {
-            Ty::Bool | Ty::Unit | Ty::Var(_) => false,
-            Ty::ExstVar(eidx) => idx == *eidx,
-            Ty::Arrow(inp, out) => inp.contains_evar(idx) || out.contains_evar(idx),
-            Ty::Forall(body) => body.contains_evar(idx),
+        let res = match &self.kind {
+            TyKind::Bool
+            | TyKind::Unit
+            | TyKind::Top
+            | TyKind::Never
+            | TyKind::Var(_)
+            | TyKind::UnivVar(_) => false,
+            TyKind::ExstVar(eidx) => idx == *eidx,
+            TyKind::Arrow(inp, out)
+            | TyKind::Sum(inp, out)
+            | TyKind::Intersection(inp, out)
+            | TyKind::Union(inp, out)
+            | TyKind::App(inp, out) => inp.contains_evar(idx) || out.contains_evar(idx),
+            TyKind::Product(tys) | TyKind::Data(_, tys) => {
+                tys.iter().any(|ty| ty.contains_evar(idx))
+            }
+            TyKind::Record(row) | TyKind::Variant(row) | TyKind::Row(row) => {
+                row.tys().any(|ty| ty.contains_evar(idx))
+            }
+            TyKind::Forall(_, bound, body) => {
+                bound.as_ref().is_some_and(|bound| bound.contains_evar(idx))
+                    || body.contains_evar(idx)
+            }
+            TyKind::Lam(_, body) | TyKind::Mu(body) | TyKind::Exists(body) => {
+                body.contains_evar(idx)
+            }
         };
         trace!(%res, "ty/contains_evar/leave");
         res
     }
 
+    /// Collect the existentials in this type, in order of first appearance.
+    pub fn evars(&self, acc: &mut Vec<usize>) {
+        match &self.kind {
+            TyKind::Bool
+            | TyKind::Unit
+            | TyKind::Top
+            | TyKind::Never
+            | TyKind::Var(_)
+            | TyKind::UnivVar(_) => (),
+            TyKind::ExstVar(evar) => {
+                if !acc.contains(evar) {
+                    acc.push(*evar);
+                }
+            }
+            TyKind::Arrow(inp, out)
+            | TyKind::Sum(inp, out)
+            | TyKind::Intersection(inp, out)
+            | TyKind::Union(inp, out)
+            | TyKind::App(inp, out) => {
+                inp.evars(acc);
+                out.evars(acc);
+            }
+            TyKind::Product(tys) | TyKind::Data(_, tys) => {
+                for ty in tys {
+                    ty.evars(acc);
+                }
+            }
+            TyKind::Record(row) | TyKind::Variant(row) | TyKind::Row(row) => {
+                for ty in row.tys() {
+                    ty.evars(acc);
+                }
+            }
+            TyKind::Forall(_, bound, body) => {
+                if let Some(bound) = bound {
+                    bound.evars(acc);
+                }
+                body.evars(acc);
+            }
+            TyKind::Lam(_, body) | TyKind::Mu(body) | TyKind::Exists(body) => body.evars(acc),
+        }
+    }
+
     #[cfg_attr(feature = "trace", instrument(level = "trace"))]
     pub fn contains_evars(&self, evars: &HashMap<usize, &Ty>) -> bool {
         trace!("ty/contains_evars/enter");
-        let res = match self {
-            Ty::Bool | Ty::Unit | Ty::Var(_) => false,
-            Ty::ExstVar(eidx) => evars.contains_key(eidx),
-            Ty::Arrow(inp, out) => inp.contains_evars(evars) || out.contains_evars(evars),
-            Ty::Forall(body) => body.contains_evars(evars),
+        let res = match &self.kind {
+            TyKind::Bool
+            | TyKind::Unit
+            | TyKind::Top
+            | TyKind::Never
+            | TyKind::Var(_)
+            | TyKind::UnivVar(_) => false,
+            TyKind::ExstVar(eidx) => evars.contains_key(eidx),
+            TyKind::Arrow(inp, out)
+            | TyKind::Sum(inp, out)
+            | TyKind::Intersection(inp, out)
+            | TyKind::Union(inp, out)
+            | TyKind::App(inp, out) => inp.contains_evars(evars) || out.contains_evars(evars),
+            TyKind::Product(tys) | TyKind::Data(_, tys) => {
+                tys.iter().any(|ty| ty.contains_evars(evars))
+            }
+            TyKind::Record(row) | TyKind::Variant(row) | TyKind::Row(row) => {
+                row.tys().any(|ty| ty.contains_evars(evars))
+            }
+            TyKind::Forall(_, bound, body) => {
+                bound
+                    .as_ref()
+                    .is_some_and(|bound| bound.contains_evars(evars))
+                    || body.contains_evars(evars)
+            }
+            TyKind::Lam(_, body) | TyKind::Mu(body) | TyKind::Exists(body) => {
+                body.contains_evars(evars)
+            }
         };
         trace!(%res, "ty/contains_evars/leave");
         res
     }
 
-    #[cfg_attr(feature = "trace", instrument(level = "trace", skip(ctx)))]
+    /// Whether this is a monotype that is well-formed in `ctx`. Recursive
+    /// types and type operators are monotypes as long as their bodies are.
     pub fn is_mono_wellformed_in(&self, ctx: TyCtxtView) -> bool {
+        self.is_mono_wellformed_in_(ctx, 0)
+    }
+
+    #[cfg_attr(feature = "trace", instrument(level = "trace", skip(ctx)))]
+    fn is_mono_wellformed_in_(&self, ctx: TyCtxtView, depth: usize) -> bool {
         trace!("ty/is_mono_wellformed_in/enter");
-        let res = match self {
-            Ty::Bool | Ty::Unit => true,
-            Ty::Var(idx) => ctx.contains_uvar(*idx),
-            Ty::Forall(_) => false,
-            Ty::ExstVar(eidx) => ctx.contains_evar(*eidx),
-            Ty::Arrow(inp, out) => inp.is_mono_wellformed_in(ctx) && out.is_mono_wellformed_in(ctx),
+        let res = match &self.kind {
+            TyKind::Bool | TyKind::Unit | TyKind::Top | TyKind::Never => true,
+            TyKind::UnivVar(uvar) => ctx.contains_uvar(*uvar),
+            TyKind::Var(idx) => *idx < depth,
+            TyKind::Forall(..) | TyKind::Exists(_) => false,
+            TyKind::Mu(body) | TyKind::Lam(_, body) => body.is_mono_wellformed_in_(ctx, depth + 1),
+            TyKind::ExstVar(eidx) => ctx.contains_evar(*eidx),
+            TyKind::Arrow(inp, out)
+            | TyKind::Sum(inp, out)
+            | TyKind::Intersection(inp, out)
+            | TyKind::Union(inp, out)
+            | TyKind::App(inp, out) => {
+                inp.is_mono_wellformed_in_(ctx, depth) && out.is_mono_wellformed_in_(ctx, depth)
+            }
+            TyKind::Product(tys) | TyKind::Data(_, tys) => {
+                tys.iter().all(|ty| ty.is_mono_wellformed_in_(ctx, depth))
+            }
+            TyKind::Record(row) | TyKind::Variant(row) | TyKind::Row(row) => {
+                row.tys().all(|ty| ty.is_mono_wellformed_in_(ctx, depth))
+            }
         };
         trace!(%res, "ty/is_mono_wellformed_in/leave");
         res
     }
 
-    pub fn is_wellformed_in(&self, ctx: &TyCtxt) -> bool {
-        self.is_wellformed_in_(ctx, 0)
-    }
-
+    /// Whether this is a well-formed type of kind [`Kind::Type`] in `ctx`.
     #[cfg_attr(feature = "trace", instrument(level = "trace", skip(ctx)))]
-    fn is_wellformed_in_(&self, ctx: &TyCtxt, depth: usize) -> bool {
+    pub fn is_wellformed_in(&self, ctx: &TyCtxt) -> bool {
         trace!("ty/is_wellformed_in/enter");
-        let res = match self {
-            Ty::Bool | Ty::Unit => true,
-            Ty::Var(idx) => *idx < depth,
-            Ty::Forall(body) => body.is_wellformed_in_(ctx, depth + 1),
-            Ty::ExstVar(eidx) => ctx.contains_evar(*eidx),
-            Ty::Arrow(inp, out) => {
-                inp.is_wellformed_in_(ctx, depth) && out.is_wellformed_in_(ctx, depth)
-            }
-        };
+        let res =
+            kindck::infer_kind(ctx, &mut Vec::new(), self).is_ok_and(|kind| kind == Kind::Type);
         trace!(%res, "ty/is_wellformed_in/leave");
         res
     }
+
+    /// If this is `\'a. D T.. 'a` where `'a` occurs nowhere else, the data
+    /// type `D T..` it is equivalent to.
+    pub fn eta_contract(&self) -> Option<Ty> {
+        let (name, args) = match &self.kind {
+            TyKind::Lam(_, body) => match &body.kind {
+                TyKind::Data(name, args) => (name, args),
+                _ => return None,
+            },
+            _ => return None,
+        };
+        let (last, init) = args.split_last()?;
+        if !matches!(last.kind, TyKind::Var(0)) {
+            return None;
+        }
+        let mut mentioned = false;
+        let init = init
+            .iter()
+            .map(|arg| {
+                arg.clone().map_vars(0, &mut |idx, depth, span| {
+                    mentioned |= idx == depth;
+                    Ty::new(TyKind::Var(if idx > depth { idx - 1 } else { idx }), span)
+                })
+            })
+            .collect();
+        (!mentioned).then(|| self.with_kind(TyKind::Data(name.clone(), init)))
+    }
+
+    /// Beta-reduce every type operator applied to an argument in this type.
+    /// Kinding rules out self-application, so this terminates on well-formed
+    /// types.
+    #[must_use]
+    pub fn normalize(self) -> Ty {
+        let span = self.span;
+        match self.kind {
+            TyKind::Bool
+            | TyKind::Unit
+            | TyKind::Top
+            | TyKind::Never
+            | TyKind::Var(_)
+            | TyKind::UnivVar(_)
+            | TyKind::ExstVar(_) => self,
+            TyKind::App(func, arg) => {
+                let func = func.normalize();
+                let arg = arg.normalize();
+                match func.kind {
+                    TyKind::Lam(_, body) => body.instantiate(&[arg]).normalize(),
+                    kind => Ty::new(
+                        TyKind::App(Box::new(Ty::new(kind, func.span)), Box::new(arg)),
+                        span,
+                    ),
+                }
+            }
+            TyKind::Lam(kind, body) => Ty::new(TyKind::Lam(kind, Box::new(body.normalize())), span),
+            TyKind::Forall(kind, bound, body) => Ty::new(
+                TyKind::Forall(
+                    kind,
+                    bound.map(|bound| Box::new(bound.normalize())),
+                    Box::new(body.normalize()),
+                ),
+                span,
+            ),
+            TyKind::Mu(body) => Ty::new(TyKind::Mu(Box::new(body.normalize())), span),
+            TyKind::Exists(body) => Ty::new(TyKind::Exists(Box::new(body.normalize())), span),
+            TyKind::Arrow(inp, out) => Ty::new(
+                TyKind::Arrow(Box::new(inp.normalize()), Box::new(out.normalize())),
+                span,
+            ),
+            TyKind::Product(tys) => Ty::new(
+                TyKind::Product(tys.into_iter().map(Ty::normalize).collect()),
+                span,
+            ),
+            TyKind::Sum(left, right) => Ty::new(
+                TyKind::Sum(Box::new(left.normalize()), Box::new(right.normalize())),
+                span,
+            ),
+            TyKind::Intersection(left, right) => Ty::new(
+                TyKind::Intersection(Box::new(left.normalize()), Box::new(right.normalize())),
+                span,
+            ),
+            TyKind::Union(left, right) => Ty::new(
+                TyKind::Union(Box::new(left.normalize()), Box::new(right.normalize())),
+                span,
+            ),
+            TyKind::Record(row) => Ty::new(TyKind::Record(row.map(Ty::normalize)), span),
+            TyKind::Variant(row) => Ty::new(TyKind::Variant(row.map(Ty::normalize)), span),
+            TyKind::Row(row) => Ty::new(TyKind::Row(row.map(Ty::normalize)), span),
+            TyKind::Data(name, args) => Ty::new(
+                TyKind::Data(name, args.into_iter().map(Ty::normalize).collect()),
+                span,
+            ),
+        }
+    }
 }
@@ -1,89 +1,1731 @@
-use std::collections::VecDeque;
+use std::{collections::VecDeque, fmt};
 
 use super::core;
+use crate::span::Span;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub enum Term {
+pub struct Term {
+    pub kind: TermKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TermKind {
     Unit,
     Appl(Box<Term>, Box<Term>),
-    Lambda(String, Box<Term>),
+    /// `\x. e` or `\(x : T). e`
+    Lambda(String, Option<Ty>, Box<Term>),
     Bool(bool),
     Var(String),
     Ann(Box<Term>, Ty),
     TypeAppl(Box<Term>, Ty),
+    /// `let x = e1 in e2` or `let x : T = e1 in e2`
+    Let(String, Option<Ty>, Box<Term>, Box<Term>),
+    /// `/\'a. e` or `/\('a : K). e`
+    TyAbs(String, Option<core::Kind>, Box<Term>),
+    /// `if c then t else e`
+    If(Box<Term>, Box<Term>, Box<Term>),
+    /// `fix f. e` or `fix f : T. e`
+    Fix(String, Option<Ty>, Box<Term>),
+    /// `(e1, e2, ...)`
+    Tuple(Vec<Term>),
+    /// `e.i`
+    Proj(Box<Term>, usize),
+    /// `let (x1, ..., xn) = e1 in e2`
+    LetTuple(Vec<String>, Box<Term>, Box<Term>),
+    /// `inl e`
+    Inl(Box<Term>),
+    /// `inr e`
+    Inr(Box<Term>),
+    /// `case e of inl x -> e1 | inr y -> e2`
+    Case(Box<Term>, String, Box<Term>, String, Box<Term>),
+    /// `{ x = e1, y = e2, ... }`
+    Record(Vec<(String, Term)>),
+    /// `e.x`
+    Field(Box<Term>, String),
+    /// `<x = e>`
+    Variant(String, Box<Term>),
+    /// `case e of { <x = y> -> e1 | ... }`
+    VariantCase(Box<Term>, Vec<(String, String, Term)>),
+    /// `fold e`
+    Fold(Box<Term>),
+    /// `unfold e`
+    Unfold(Box<Term>),
+    /// `pack [T, e] as U`
+    Pack(Ty, Box<Term>, Ty),
+    /// `unpack e1 as ['a, x] in e2`
+    Unpack(Box<Term>, String, String, Box<Term>),
+    /// `absurd`
+    Absurd,
+    /// `match e with { p1 -> e1 | ... }`
+    Match(Box<Term>, Vec<(Pattern, Term)>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pattern {
+    pub kind: PatternKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PatternKind {
+    /// `_`
+    Wildcard,
+    /// A variable, or a constructor with no fields: which one is only known
+    /// once the declared constructors are.
+    Var(String),
+    /// `C p1 ...`, with at least one pattern.
+    Ctor(String, Vec<Pattern>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Program {
+    pub decls: Vec<Decl>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Decl {
+    pub kind: DeclKind,
+    pub span: Span,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub enum Ty {
+pub enum DeclKind {
+    /// `def name : ty = body;`
+    Def(String, Ty, Box<Term>),
+    /// `data Name 'a ... = C1 T ... | ...;`, with the name, the parameters
+    /// (each with an optional kind) and each constructor's name and field
+    /// types.
+    Data(
+        String,
+        Vec<(String, Option<core::Kind>)>,
+        Vec<(String, Vec<Ty>)>,
+    ),
+    /// `type Name 'a ... = T;`, with the name, the parameters and the body.
+    Type(String, Vec<(String, Option<core::Kind>)>, Ty),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ty {
+    pub kind: TyKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TyKind {
     Unit,
     Bool,
+    Top,
+    Never,
     Arrow(Box<Ty>, Box<Ty>),
-    Forall(String, Box<Ty>),
+    Product(Vec<Ty>),
+    Sum(Box<Ty>, Box<Ty>),
+    /// `A & B`
+    Intersection(Box<Ty>, Box<Ty>),
+    /// `A | B`
+    Union(Box<Ty>, Box<Ty>),
+    /// `{ x : A, y : B, ... }` or `{ x : A, ... | 'r }`. The tail is always
+    /// a [`TyKind::Var`].
+    Record(Vec<(String, Ty)>, Option<Box<Ty>>),
+    /// `< x : A, y : B, ... >` or `< x : A, ... | 'r >`
+    Variant(Vec<(String, Ty)>, Option<Box<Ty>>),
+    /// `'a => T` or `('a : K) => T`, optionally with an upper bound, as in
+    /// `'a <: B => T`.
+    Forall(String, Option<core::Kind>, Option<Box<Ty>>, Box<Ty>),
+    /// `mu 'a. T`
+    Mu(String, Box<Ty>),
+    /// `exists 'a. T`
+    Exists(String, Box<Ty>),
+    /// A quantifier inserted by [`Program::quantify_free_ty_vars`] or
+    /// [`Term::quantify_free_ty_vars`] rather than written in the source.
+    ImplicitForall(String, Box<Ty>),
     Var(String),
+    /// `Name T ...`, a data type or synonym applied to its parameters.
+    Con(String, Vec<Ty>),
+    /// `\'a. T` or `\('a : K). T`
+    Lam(String, Option<core::Kind>, Box<Ty>),
+    /// `F T`, where `F` is a type variable or a parenthesised type.
+    App(Box<Ty>, Box<Ty>),
 }
 
-impl Term {
-    pub fn into_core(self) -> Option<core::Term> {
-        self.into_core_(&mut VecDeque::new())
+/// An error encountered while lowering the surface AST to the core AST.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LowerError {
+    pub kind: LowerErrorKind,
+    pub span: Span,
+    /// The names of the same sort that were in scope at the error, innermost
+    /// first and without shadowed duplicates.
+    pub in_scope: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LowerErrorKind {
+    /// A term variable was not bound by any enclosing lambda.
+    UnboundVar(String),
+    /// A type variable was not bound by any enclosing quantifier.
+    UnboundTyVar(String),
+    /// A label appeared more than once in a record, a variant `case` or a
+    /// record or variant type.
+    DuplicateLabel(String),
+    /// A type variable was used both as a type and as a row.
+    KindMismatch(String),
+    /// The variable bound by a recursive type occurred to the left of an
+    /// arrow in its body.
+    NegativeOccurrence(String),
+    /// A type name was not a declared data type or synonym.
+    UnboundType(String),
+    /// A constructor pattern's name was not a declared constructor.
+    UnboundCtor(String),
+    /// A data type, synonym or constructor pattern that takes the first
+    /// number of arguments was given the second.
+    Arity(String, usize, usize),
+    /// A variable was bound more than once by one pattern, or a parameter by
+    /// one data type or synonym.
+    DuplicateBinder(String),
+    /// A data type, synonym or constructor was declared with the name of an
+    /// earlier one.
+    Redeclared(String),
+    /// A data type occurred to the left of an arrow in the fields of its
+    /// constructors.
+    NegativeData(String),
+}
+
+impl LowerError {
+    pub fn new(kind: LowerErrorKind, span: Span, scope: &VecDeque<String>) -> Self {
+        let mut in_scope: Vec<String> = Vec::new();
+        for name in scope {
+            if !in_scope.contains(name) {
+                in_scope.push(name.clone());
+            }
+        }
+        Self {
+            kind,
+            span,
+            in_scope,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match &self.kind {
+            LowerErrorKind::UnboundVar(name)
+            | LowerErrorKind::UnboundTyVar(name)
+            | LowerErrorKind::DuplicateLabel(name)
+            | LowerErrorKind::KindMismatch(name)
+            | LowerErrorKind::NegativeOccurrence(name)
+            | LowerErrorKind::UnboundType(name)
+            | LowerErrorKind::UnboundCtor(name)
+            | LowerErrorKind::Arity(name, _, _)
+            | LowerErrorKind::DuplicateBinder(name)
+            | LowerErrorKind::Redeclared(name)
+            | LowerErrorKind::NegativeData(name) => name,
+        }
     }
 
-    fn into_core_(self, ctx: &mut VecDeque<String>) -> Option<core::Term> {
-        Some(match self {
-            Term::Appl(func, arg) => core::Term::Appl(
-                Box::new(func.into_core_(ctx)?),
-                Box::new(arg.into_core_(ctx)?),
+    /// Names in scope that are close enough to the unbound name that it may
+    /// have been a typo, closest first.
+    pub fn suggestions(&self) -> Vec<&str> {
+        let name = self.name();
+        // Allow roughly one edit for every three characters.
+        let max_distance = (name.chars().count() / 3).max(1);
+        let mut candidates = self
+            .in_scope
+            .iter()
+            .map(|cand| (edit_distance(name, cand), cand.as_str()))
+            .filter(|&(dist, _)| dist <= max_distance)
+            .collect::<Vec<_>>();
+        candidates.sort_by_key(|&(dist, _)| dist);
+        candidates.into_iter().map(|(_, cand)| cand).collect()
+    }
+}
+
+impl fmt::Display for LowerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            LowerErrorKind::UnboundVar(name) => {
+                write!(f, "cannot find variable `{}` in this scope", name)
+            }
+            LowerErrorKind::UnboundTyVar(name) => {
+                write!(f, "cannot find type variable `{}` in this scope", name)
+            }
+            LowerErrorKind::DuplicateLabel(label) => {
+                write!(f, "label `{}` appears more than once", label)
+            }
+            LowerErrorKind::KindMismatch(name) => write!(
+                f,
+                "type variable `{}` is used both as a type and as a row",
+                name
+            ),
+            LowerErrorKind::NegativeOccurrence(name) => write!(
+                f,
+                "type variable `{}` occurs negatively in its recursive type",
+                name
+            ),
+            LowerErrorKind::UnboundType(name) => {
+                write!(f, "cannot find type `{}` in this scope", name)
+            }
+            LowerErrorKind::UnboundCtor(name) => {
+                write!(f, "cannot find constructor `{}` in this scope", name)
+            }
+            LowerErrorKind::Arity(name, expected, found) => write!(
+                f,
+                "`{}` takes {} argument{}, but {} {} given",
+                name,
+                expected,
+                if *expected == 1 { "" } else { "s" },
+                found,
+                if *found == 1 { "was" } else { "were" }
+            ),
+            LowerErrorKind::DuplicateBinder(name) => {
+                write!(f, "`{}` is bound more than once", name)
+            }
+            LowerErrorKind::Redeclared(name) => {
+                write!(f, "`{}` is declared more than once", name)
+            }
+            LowerErrorKind::NegativeData(name) => write!(
+                f,
+                "data type `{}` occurs negatively in its constructors",
+                name
             ),
-            Term::Lambda(name, body) => {
+        }
+    }
+}
+
+impl std::error::Error for LowerError {}
+
+/// The edit distance between two strings, counted in characters, where an
+/// insertion, deletion, substitution or transposition of adjacent characters
+/// each cost one.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a = a.chars().collect::<Vec<_>>();
+    let b = b.chars().collect::<Vec<_>>();
+    let mut dist = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in dist.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in dist[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            dist[i][j] = (dist[i - 1][j] + 1)
+                .min(dist[i][j - 1] + 1)
+                .min(dist[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                dist[i][j] = dist[i][j].min(dist[i - 2][j - 2] + 1);
+            }
+        }
+    }
+    dist[a.len()][b.len()]
+}
+
+impl Program {
+    /// Lower to the core AST, reporting every unbound name. Each definition
+    /// is in scope in the bodies of the ones after it.
+    pub fn into_core(self) -> Result<core::Program, Vec<LowerError>> {
+        let mut errors = Vec::new();
+        let mut scope = VecDeque::new();
+        let mut sig = Signature::default();
+        let mut decls = Vec::new();
+        for decl in self.decls {
+            match decl.kind {
+                DeclKind::Def(name, ty, body) => {
+                    let binders = ty.forall_binders();
+                    let ty = ty.into_core_(&mut VecDeque::new(), &sig, &mut errors);
+                    let body = body.into_core_scoped(
+                        &mut scope,
+                        &mut VecDeque::new(),
+                        &binders,
+                        &sig,
+                        &mut errors,
+                    );
+                    scope.push_front(name.clone());
+                    if let (Some(ty), Some(body)) = (ty, body) {
+                        decls.push(core::Decl::new(
+                            core::DeclKind::Def(name, ty, body),
+                            decl.span,
+                        ));
+                    }
+                }
+                DeclKind::Data(name, params, ctors) => {
+                    let data = Program::data_into_core(
+                        name,
+                        params,
+                        ctors,
+                        decl.span,
+                        &mut sig,
+                        &mut errors,
+                    );
+                    if let Some(data) = data {
+                        decls.push(core::Decl::new(core::DeclKind::Data(data), decl.span));
+                    }
+                }
+                DeclKind::Type(name, params, body) => {
+                    let synonym = Program::synonym_into_core(
+                        name,
+                        params,
+                        body,
+                        decl.span,
+                        &mut sig,
+                        &mut errors,
+                    );
+                    if let Some(synonym) = synonym {
+                        decls.push(core::Decl::new(core::DeclKind::Type(synonym), decl.span));
+                    }
+                }
+            }
+        }
+        if errors.is_empty() {
+            Ok(core::Program { decls })
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+impl Program {
+    /// Lower the declaration `data name params = ctors;` at `span`, adding
+    /// the data type and its constructors to `sig`. The data type is in scope
+    /// in its own constructors, as long as it only occurs positively in them.
+    fn data_into_core(
+        name: String,
+        params: Vec<(String, Option<core::Kind>)>,
+        ctors: Vec<(String, Vec<Ty>)>,
+        span: Span,
+        sig: &mut Signature,
+        errors: &mut Vec<LowerError>,
+    ) -> Option<core::DataDecl> {
+        if sig.declares_ty(&name) {
+            errors.push(LowerError::new(
+                LowerErrorKind::Redeclared(name.clone()),
+                span,
+                &VecDeque::new(),
+            ));
+        }
+        let (params, kinds) = params_into_core(params, span, errors);
+        for (idx, (ctor, _)) in ctors.iter().enumerate() {
+            if sig.ctor(ctor).is_some() || ctors[..idx].iter().any(|(c, _)| c == ctor) {
+                errors.push(LowerError::new(
+                    LowerErrorKind::Redeclared(ctor.clone()),
+                    span,
+                    &VecDeque::new(),
+                ));
+            }
+        }
+        let fields = ctors.iter().flat_map(|(_, fields)| fields);
+        let kinds = params
+            .iter()
+            .zip(kinds)
+            .map(|(param, kind)| {
+                kind.unwrap_or_else(|| {
+                    let mut uses = TyVarUses::default();
+                    for field in fields.clone() {
+                        field.ty_var_uses(param, &mut uses);
+                    }
+                    uses.require_type(param, span, errors);
+                    core::Kind::Type
+                })
+            })
+            .collect::<Vec<_>>();
+        // Until its parameters' polarities are known, assume they are only
+        // used positively by the data type's own constructors.
+        sig.data
+            .push((name.clone(), kinds.clone(), vec![false; params.len()]));
+        for field in fields.clone() {
+            if field.occurs_negatively(&name, sig) {
+                errors.push(LowerError::new(
+                    LowerErrorKind::NegativeData(name.clone()),
+                    field.span,
+                    &VecDeque::new(),
+                ));
+            }
+        }
+        let negative = params
+            .iter()
+            .map(|param| {
+                fields
+                    .clone()
+                    .any(|field| field.occurs_negatively(param, sig))
+            })
+            .collect();
+        sig.data.last_mut().unwrap().2 = negative;
+        for (ctor, fields) in &ctors {
+            sig.ctors.push((ctor.clone(), fields.len()));
+        }
+
+        let mut ty_ctx = params.iter().rev().cloned().collect::<VecDeque<_>>();
+        let ctors = ctors
+            .into_iter()
+            .map(|(ctor, fields)| {
+                let fields = fields
+                    .into_iter()
+                    .map(|field| field.into_core_(&mut ty_ctx, sig, errors))
+                    .collect::<Vec<_>>();
+                Some((ctor, fields.into_iter().collect::<Option<_>>()?))
+            })
+            .collect::<Vec<_>>();
+        Some(core::DataDecl {
+            name,
+            params: kinds,
+            ctors: ctors.into_iter().collect::<Option<_>>()?,
+        })
+    }
+
+    /// Lower the declaration `type name params = body;` at `span`, adding
+    /// the synonym to `sig`. The synonym is only in scope after its body.
+    fn synonym_into_core(
+        name: String,
+        params: Vec<(String, Option<core::Kind>)>,
+        body: Ty,
+        span: Span,
+        sig: &mut Signature,
+        errors: &mut Vec<LowerError>,
+    ) -> Option<core::Synonym> {
+        if sig.declares_ty(&name) {
+            errors.push(LowerError::new(
+                LowerErrorKind::Redeclared(name.clone()),
+                span,
+                &VecDeque::new(),
+            ));
+        }
+        let (params, kinds) = params_into_core(params, span, errors);
+        let kinds = params
+            .iter()
+            .zip(kinds)
+            .map(|(param, kind)| {
+                kind.unwrap_or_else(|| {
+                    let mut uses = TyVarUses::default();
+                    body.ty_var_uses(param, &mut uses);
+                    uses.require_type(param, span, errors);
+                    core::Kind::Type
+                })
+            })
+            .collect();
+        let negative = params
+            .iter()
+            .map(|param| body.occurs_negatively(param, sig))
+            .collect();
+
+        let mut ty_ctx = params.iter().rev().cloned().collect::<VecDeque<_>>();
+        let synonym = core::Synonym {
+            name,
+            params: kinds,
+            body: body.into_core_(&mut ty_ctx, sig, errors)?,
+        };
+        sig.synonyms.push((synonym.clone(), negative));
+        Some(synonym)
+    }
+
+    /// Implicitly quantify the free type variables of every signature and
+    /// annotation. See [`Ty::quantify_free_ty_vars`].
+    pub fn quantify_free_ty_vars(&mut self) {
+        for decl in &mut self.decls {
+            match &mut decl.kind {
+                DeclKind::Def(_, ty, body) => {
+                    ty.quantify_free_ty_vars(&[]);
+                    body.quantify_free_ty_vars_(&mut ty.forall_prefix());
+                }
+                // The parameters are the only type variables that can appear
+                // in the constructors or the body.
+                DeclKind::Data(..) | DeclKind::Type(..) => (),
+            }
+        }
+    }
+}
+
+/// Split the parameters of a data type or synonym declared at `span` into
+/// their names and kinds, reporting any that are bound more than once.
+#[allow(clippy::type_complexity)]
+fn params_into_core(
+    params: Vec<(String, Option<core::Kind>)>,
+    span: Span,
+    errors: &mut Vec<LowerError>,
+) -> (Vec<String>, Vec<Option<core::Kind>>) {
+    let (names, kinds): (Vec<_>, Vec<_>) = params.into_iter().unzip();
+    for (idx, name) in names.iter().enumerate() {
+        if names[..idx].contains(name) {
+            errors.push(LowerError::new(
+                LowerErrorKind::DuplicateBinder(name.clone()),
+                span,
+                &VecDeque::new(),
+            ));
+        }
+    }
+    (names, kinds)
+}
+
+impl Decl {
+    pub fn new(kind: DeclKind, span: Span) -> Self {
+        Self { kind, span }
+    }
+}
+
+impl Term {
+    pub fn new(kind: TermKind, span: Span) -> Self {
+        Self { kind, span }
+    }
+
+    /// Desugar `\x y z. e`, where the lambda starts at `start` and ends at
+    /// `end` and each binder starts at the given offset, into nested lambdas.
+    pub fn lambdas(
+        start: usize,
+        binders: Vec<(usize, String, Option<Ty>)>,
+        body: Box<Term>,
+        end: usize,
+    ) -> Box<Term> {
+        binders
+            .into_iter()
+            .enumerate()
+            .rev()
+            .fold(body, |body, (idx, (l, name, ty))| {
+                let l = if idx == 0 { start } else { l };
+                Box::new(Term::new(
+                    TermKind::Lambda(name, ty, body),
+                    Span::new(l, end),
+                ))
+            })
+    }
+
+    /// Desugar `let rec f : T = e1 in e2` into `let f : T = fix f : T. e1 in
+    /// e2`.
+    pub fn let_rec(
+        name: String,
+        ty: Option<Ty>,
+        val: Box<Term>,
+        body: Box<Term>,
+        span: Span,
+    ) -> Box<Term> {
+        let val_span = val.span;
+        let fix = Term::new(TermKind::Fix(name.clone(), ty.clone(), val), val_span);
+        Box::new(Term::new(
+            TermKind::Let(name, ty, Box::new(fix), body),
+            span,
+        ))
+    }
+
+    /// Implicitly quantify the free type variables of every annotation. See
+    /// [`Ty::quantify_free_ty_vars`].
+    pub fn quantify_free_ty_vars(&mut self) {
+        self.quantify_free_ty_vars_(&mut Vec::new());
+    }
+
+    /// `scope` holds the type variables bound by enclosing type abstractions
+    /// and annotations.
+    fn quantify_free_ty_vars_(&mut self, scope: &mut Vec<String>) {
+        match &mut self.kind {
+            TermKind::Unit | TermKind::Bool(_) | TermKind::Absurd | TermKind::Var(_) => (),
+            TermKind::Appl(func, arg) => {
+                func.quantify_free_ty_vars_(scope);
+                arg.quantify_free_ty_vars_(scope);
+            }
+            TermKind::Lambda(_, _, body) => body.quantify_free_ty_vars_(scope),
+            TermKind::TypeAppl(term, _) => term.quantify_free_ty_vars_(scope),
+            TermKind::Ann(term, ty) => {
+                ty.quantify_free_ty_vars(scope);
+                let len = scope.len();
+                scope.extend(ty.forall_prefix());
+                term.quantify_free_ty_vars_(scope);
+                scope.truncate(len);
+            }
+            TermKind::Let(_, ty, val, body) => {
+                let len = scope.len();
+                if let Some(ty) = ty {
+                    ty.quantify_free_ty_vars(scope);
+                    scope.extend(ty.forall_prefix());
+                }
+                val.quantify_free_ty_vars_(scope);
+                scope.truncate(len);
+                body.quantify_free_ty_vars_(scope);
+            }
+            TermKind::If(cond, then, els) | TermKind::Case(cond, _, then, _, els) => {
+                cond.quantify_free_ty_vars_(scope);
+                then.quantify_free_ty_vars_(scope);
+                els.quantify_free_ty_vars_(scope);
+            }
+            TermKind::Tuple(terms) => {
+                for term in terms {
+                    term.quantify_free_ty_vars_(scope);
+                }
+            }
+            TermKind::Proj(term, _)
+            | TermKind::Inl(term)
+            | TermKind::Inr(term)
+            | TermKind::Field(term, _)
+            | TermKind::Variant(_, term)
+            | TermKind::Fold(term)
+            | TermKind::Unfold(term)
+            | TermKind::Pack(_, term, _) => {
+                term.quantify_free_ty_vars_(scope);
+            }
+            TermKind::Unpack(packed, name, _, body) => {
+                packed.quantify_free_ty_vars_(scope);
+                scope.push(name.clone());
+                body.quantify_free_ty_vars_(scope);
+                scope.pop();
+            }
+            TermKind::VariantCase(scrut, arms) => {
+                scrut.quantify_free_ty_vars_(scope);
+                for (_, _, term) in arms {
+                    term.quantify_free_ty_vars_(scope);
+                }
+            }
+            TermKind::Match(scrut, arms) => {
+                scrut.quantify_free_ty_vars_(scope);
+                for (_, term) in arms {
+                    term.quantify_free_ty_vars_(scope);
+                }
+            }
+            TermKind::Record(fields) => {
+                for (_, term) in fields {
+                    term.quantify_free_ty_vars_(scope);
+                }
+            }
+            TermKind::LetTuple(_, val, body) => {
+                val.quantify_free_ty_vars_(scope);
+                body.quantify_free_ty_vars_(scope);
+            }
+            TermKind::Fix(_, ty, body) => {
+                let len = scope.len();
+                if let Some(ty) = ty {
+                    ty.quantify_free_ty_vars(scope);
+                    scope.extend(ty.forall_prefix());
+                }
+                body.quantify_free_ty_vars_(scope);
+                scope.truncate(len);
+            }
+            TermKind::TyAbs(name, _, body) => {
+                scope.push(name.clone());
+                body.quantify_free_ty_vars_(scope);
+                scope.pop();
+            }
+        }
+    }
+
+    /// Lower to the core AST, reporting every unbound name.
+    pub fn into_core(self) -> Result<core::Term, Vec<LowerError>> {
+        let mut errors = Vec::new();
+        match self.into_core_(
+            &mut VecDeque::new(),
+            &mut VecDeque::new(),
+            &Signature::default(),
+            &mut errors,
+        ) {
+            Some(term) if errors.is_empty() => Ok(term),
+            _ => Err(errors),
+        }
+    }
+
+    /// `ctx` holds the term variables in scope and `ty_ctx` the type
+    /// variables bound by enclosing type abstractions, innermost first.
+    fn into_core_(
+        self,
+        ctx: &mut VecDeque<String>,
+        ty_ctx: &mut VecDeque<String>,
+        sig: &Signature,
+        errors: &mut Vec<LowerError>,
+    ) -> Option<core::Term> {
+        let span = self.span;
+        let kind = match self.kind {
+            TermKind::Appl(func, arg) => {
+                let func = func.into_core_(ctx, ty_ctx, sig, errors);
+                let arg = arg.into_core_(ctx, ty_ctx, sig, errors);
+                core::TermKind::Appl(Box::new(func?), Box::new(arg?))
+            }
+            TermKind::Lambda(name, ty, body) => {
+                let ty = ty.map(|ty| ty.into_core_(ty_ctx, sig, errors));
                 ctx.push_front(name);
-                let new_body = body.into_core_(ctx);
+                let new_body = body.into_core_(ctx, ty_ctx, sig, errors);
                 ctx.pop_front();
-                core::Term::Lambda(Box::new(new_body?))
+                let ty = match ty {
+                    Some(ty) => Some(ty?),
+                    None => None,
+                };
+                core::TermKind::Lambda(ty, Box::new(new_body?))
+            }
+            TermKind::Bool(b) => core::TermKind::Bool(b),
+            TermKind::Unit => core::TermKind::Unit,
+            TermKind::Absurd => core::TermKind::Absurd,
+            TermKind::Var(name) => match ctx.iter().position(|s| s == &name) {
+                Some(idx) => core::TermKind::Var(idx),
+                None => match sig.ctor(&name) {
+                    Some(arity) => core::TermKind::Ctor(name, arity),
+                    None => {
+                        errors.push(LowerError::new(LowerErrorKind::UnboundVar(name), span, ctx));
+                        return None;
+                    }
+                },
+            },
+            TermKind::Ann(term, ty) => {
+                let binders = ty.forall_binders();
+                let term = term.into_core_scoped(ctx, ty_ctx, &binders, sig, errors);
+                let ty = ty.into_core_(ty_ctx, sig, errors);
+                core::TermKind::Ann(Box::new(term?), ty?)
             }
-            Term::Bool(b) => core::Term::Bool(b),
-            Term::Unit => core::Term::Unit,
-            Term::Var(name) => {
-                let idx =
-                    ctx.iter()
-                        .enumerate()
-                        .find_map(|(idx, s)| if s == &name { Some(idx) } else { None })?;
-                core::Term::Var(idx)
+            TermKind::TypeAppl(term, ty) => {
+                let term = term.into_core_(ctx, ty_ctx, sig, errors);
+                let ty = ty.into_core_(ty_ctx, sig, errors);
+                core::TermKind::TypeAppl(Box::new(term?), ty?)
             }
-            Term::Ann(term, ty) => {
-                core::Term::Ann(Box::new(term.into_core_(ctx)?), ty.into_core()?)
+            TermKind::Let(name, ty, val, body) => {
+                let binders = ty.as_ref().map_or_else(Vec::new, Ty::forall_binders);
+                let ty = ty.map(|ty| ty.into_core_(ty_ctx, sig, errors));
+                let val = val.into_core_scoped(ctx, ty_ctx, &binders, sig, errors);
+                ctx.push_front(name);
+                let body = body.into_core_(ctx, ty_ctx, sig, errors);
+                ctx.pop_front();
+                let ty = match ty {
+                    Some(ty) => Some(ty?),
+                    None => None,
+                };
+                core::TermKind::Let(ty, Box::new(val?), Box::new(body?))
             }
-            Term::TypeAppl(term, ty) => {
-                core::Term::TypeAppl(Box::new(term.into_core_(ctx)?), ty.into_core()?)
+            TermKind::If(cond, then, els) => {
+                let cond = cond.into_core_(ctx, ty_ctx, sig, errors);
+                let then = then.into_core_(ctx, ty_ctx, sig, errors);
+                let els = els.into_core_(ctx, ty_ctx, sig, errors);
+                core::TermKind::If(Box::new(cond?), Box::new(then?), Box::new(els?))
             }
-        })
+            TermKind::Tuple(terms) => {
+                let terms = terms
+                    .into_iter()
+                    .map(|term| term.into_core_(ctx, ty_ctx, sig, errors))
+                    .collect::<Vec<_>>();
+                core::TermKind::Tuple(terms.into_iter().collect::<Option<_>>()?)
+            }
+            TermKind::Proj(term, idx) => {
+                core::TermKind::Proj(Box::new(term.into_core_(ctx, ty_ctx, sig, errors)?), idx)
+            }
+            TermKind::LetTuple(names, val, body) => {
+                let val = val.into_core_(ctx, ty_ctx, sig, errors);
+                let len = names.len();
+                for name in names {
+                    ctx.push_front(name);
+                }
+                let body = body.into_core_(ctx, ty_ctx, sig, errors);
+                ctx.drain(..len);
+                core::TermKind::LetTuple(len, Box::new(val?), Box::new(body?))
+            }
+            TermKind::Inl(term) => {
+                core::TermKind::Inl(Box::new(term.into_core_(ctx, ty_ctx, sig, errors)?))
+            }
+            TermKind::Inr(term) => {
+                core::TermKind::Inr(Box::new(term.into_core_(ctx, ty_ctx, sig, errors)?))
+            }
+            TermKind::Case(scrut, left_name, left, right_name, right) => {
+                let scrut = scrut.into_core_(ctx, ty_ctx, sig, errors);
+                ctx.push_front(left_name);
+                let left = left.into_core_(ctx, ty_ctx, sig, errors);
+                ctx.pop_front();
+                ctx.push_front(right_name);
+                let right = right.into_core_(ctx, ty_ctx, sig, errors);
+                ctx.pop_front();
+                core::TermKind::Case(Box::new(scrut?), Box::new(left?), Box::new(right?))
+            }
+            TermKind::Record(fields) => {
+                check_labels(fields.iter().map(|(label, _)| label), span, errors);
+                let fields = fields
+                    .into_iter()
+                    .map(|(label, term)| Some((label, term.into_core_(ctx, ty_ctx, sig, errors)?)))
+                    .collect::<Vec<_>>();
+                core::TermKind::Record(fields.into_iter().collect::<Option<_>>()?)
+            }
+            TermKind::Field(term, label) => {
+                core::TermKind::Field(Box::new(term.into_core_(ctx, ty_ctx, sig, errors)?), label)
+            }
+            TermKind::Fix(name, ty, body) => {
+                let binders = ty.as_ref().map_or_else(Vec::new, Ty::forall_binders);
+                let ty = ty.map(|ty| ty.into_core_(ty_ctx, sig, errors));
+                ctx.push_front(name);
+                let body = body.into_core_scoped(ctx, ty_ctx, &binders, sig, errors);
+                ctx.pop_front();
+                let ty = match ty {
+                    Some(ty) => Some(ty?),
+                    None => None,
+                };
+                core::TermKind::Fix(ty, Box::new(body?))
+            }
+            TermKind::TyAbs(name, kind, body) => {
+                let kind = kind.unwrap_or_else(|| {
+                    let mut uses = TyVarUses::default();
+                    body.ty_var_uses(&name, &mut uses);
+                    uses.kind(&name, span, errors)
+                });
+                ty_ctx.push_front(name);
+                let new_body = body.into_core_(ctx, ty_ctx, sig, errors);
+                ty_ctx.pop_front();
+                core::TermKind::TyAbs(kind, Box::new(new_body?))
+            }
+            TermKind::Variant(label, term) => {
+                core::TermKind::Variant(label, Box::new(term.into_core_(ctx, ty_ctx, sig, errors)?))
+            }
+            TermKind::Fold(term) => {
+                core::TermKind::Fold(Box::new(term.into_core_(ctx, ty_ctx, sig, errors)?))
+            }
+            TermKind::Pack(witness, term, ty) => {
+                let witness = witness.into_core_(ty_ctx, sig, errors);
+                let term = term.into_core_(ctx, ty_ctx, sig, errors);
+                let ty = ty.into_core_(ty_ctx, sig, errors);
+                core::TermKind::Pack(witness?, Box::new(term?), ty?)
+            }
+            TermKind::Unpack(packed, ty_name, name, body) => {
+                let packed = packed.into_core_(ctx, ty_ctx, sig, errors);
+                let mut uses = TyVarUses::default();
+                body.ty_var_uses(&ty_name, &mut uses);
+                uses.require_type(&ty_name, span, errors);
+                ty_ctx.push_front(ty_name);
+                ctx.push_front(name);
+                let body = body.into_core_(ctx, ty_ctx, sig, errors);
+                ctx.pop_front();
+                ty_ctx.pop_front();
+                core::TermKind::Unpack(Box::new(packed?), Box::new(body?))
+            }
+            TermKind::Unfold(term) => {
+                core::TermKind::Unfold(Box::new(term.into_core_(ctx, ty_ctx, sig, errors)?))
+            }
+            TermKind::VariantCase(scrut, arms) => {
+                check_labels(arms.iter().map(|(label, _, _)| label), span, errors);
+                let scrut = scrut.into_core_(ctx, ty_ctx, sig, errors);
+                let arms = arms
+                    .into_iter()
+                    .map(|(label, name, body)| {
+                        ctx.push_front(name);
+                        let body = body.into_core_(ctx, ty_ctx, sig, errors);
+                        ctx.pop_front();
+                        Some((label, body?))
+                    })
+                    .collect::<Vec<_>>();
+                core::TermKind::VariantCase(
+                    Box::new(scrut?),
+                    arms.into_iter().collect::<Option<_>>()?,
+                )
+            }
+            TermKind::Match(scrut, arms) => {
+                let scrut = scrut.into_core_(ctx, ty_ctx, sig, errors);
+                let arms = arms
+                    .into_iter()
+                    .map(|(pat, body)| {
+                        let mut bound = Vec::new();
+                        let pat = pat.into_core(&mut bound, sig, errors);
+                        let len = bound.len();
+                        for name in bound {
+                            ctx.push_front(name);
+                        }
+                        let body = body.into_core_(ctx, ty_ctx, sig, errors);
+                        ctx.drain(..len);
+                        Some((pat?, body?))
+                    })
+                    .collect::<Vec<_>>();
+                core::TermKind::Match(Box::new(scrut?), arms.into_iter().collect::<Option<_>>()?)
+            }
+        };
+        Some(core::Term::new(kind, span))
+    }
+
+    /// Lower a term annotated with a type whose leading foralls bind
+    /// `binders` (outermost first). Like `ScopedTypeVariables`, those type
+    /// variables are in scope in the term: it is wrapped in a type
+    /// abstraction for each, so that checking it against the annotation binds
+    /// them to the same universal variables as the annotation's foralls.
+    fn into_core_scoped(
+        self,
+        ctx: &mut VecDeque<String>,
+        ty_ctx: &mut VecDeque<String>,
+        binders: &[(String, core::Kind)],
+        sig: &Signature,
+        errors: &mut Vec<LowerError>,
+    ) -> Option<core::Term> {
+        let span = self.span;
+        for (name, _) in binders {
+            ty_ctx.push_front(name.clone());
+        }
+        let term = self.into_core_(ctx, ty_ctx, sig, errors);
+        for _ in binders {
+            ty_ctx.pop_front();
+        }
+        Some(binders.iter().rev().fold(term?, |term, (_, kind)| {
+            core::Term::new(core::TermKind::TyAbs(kind.clone(), Box::new(term)), span)
+        }))
+    }
+
+    /// Find how the type variable `name` is used in the annotations and type
+    /// applications in this term.
+    fn ty_var_uses(&self, name: &str, uses: &mut TyVarUses) {
+        // Annotations whose foralls bind `name` again shadow it in the terms
+        // they annotate.
+        let shadows = |ty: &Ty| ty.forall_prefix().iter().any(|n| n == name);
+        match &self.kind {
+            TermKind::Unit | TermKind::Bool(_) | TermKind::Absurd | TermKind::Var(_) => (),
+            TermKind::Appl(term1, term2) | TermKind::LetTuple(_, term1, term2) => {
+                term1.ty_var_uses(name, uses);
+                term2.ty_var_uses(name, uses);
+            }
+            TermKind::Lambda(_, ty, body) => {
+                if let Some(ty) = ty {
+                    ty.ty_var_uses(name, uses);
+                }
+                body.ty_var_uses(name, uses);
+            }
+            TermKind::Ann(term, ty) => {
+                ty.ty_var_uses(name, uses);
+                if !shadows(ty) {
+                    term.ty_var_uses(name, uses);
+                }
+            }
+            TermKind::TypeAppl(term, ty) => {
+                term.ty_var_uses(name, uses);
+                ty.ty_var_uses(name, uses);
+            }
+            TermKind::Let(_, ty, val, body) => {
+                if let Some(ty) = ty {
+                    ty.ty_var_uses(name, uses);
+                }
+                if !ty.as_ref().is_some_and(shadows) {
+                    val.ty_var_uses(name, uses);
+                }
+                body.ty_var_uses(name, uses);
+            }
+            TermKind::Fix(_, ty, body) => {
+                if let Some(ty) = ty {
+                    ty.ty_var_uses(name, uses);
+                }
+                if !ty.as_ref().is_some_and(shadows) {
+                    body.ty_var_uses(name, uses);
+                }
+            }
+            TermKind::TyAbs(bound, _, body) => {
+                if bound != name {
+                    body.ty_var_uses(name, uses);
+                }
+            }
+            TermKind::If(term1, term2, term3) | TermKind::Case(term1, _, term2, _, term3) => {
+                term1.ty_var_uses(name, uses);
+                term2.ty_var_uses(name, uses);
+                term3.ty_var_uses(name, uses);
+            }
+            TermKind::Tuple(terms) => {
+                for term in terms {
+                    term.ty_var_uses(name, uses);
+                }
+            }
+            TermKind::Record(fields) => {
+                for (_, term) in fields {
+                    term.ty_var_uses(name, uses);
+                }
+            }
+            TermKind::Proj(term, _)
+            | TermKind::Inl(term)
+            | TermKind::Inr(term)
+            | TermKind::Field(term, _)
+            | TermKind::Variant(_, term)
+            | TermKind::Fold(term)
+            | TermKind::Unfold(term) => term.ty_var_uses(name, uses),
+            TermKind::Pack(witness, term, ty) => {
+                witness.ty_var_uses(name, uses);
+                term.ty_var_uses(name, uses);
+                ty.ty_var_uses(name, uses);
+            }
+            TermKind::Unpack(packed, bound, _, body) => {
+                packed.ty_var_uses(name, uses);
+                if bound != name {
+                    body.ty_var_uses(name, uses);
+                }
+            }
+            TermKind::VariantCase(scrut, arms) => {
+                scrut.ty_var_uses(name, uses);
+                for (_, _, term) in arms {
+                    term.ty_var_uses(name, uses);
+                }
+            }
+            TermKind::Match(scrut, arms) => {
+                scrut.ty_var_uses(name, uses);
+                for (_, term) in arms {
+                    term.ty_var_uses(name, uses);
+                }
+            }
+        }
+    }
+}
+
+impl Pattern {
+    pub fn new(kind: PatternKind, span: Span) -> Self {
+        Self { kind, span }
+    }
+
+    /// Lower to the core AST, resolving which names are constructors and
+    /// pushing the variables this binds onto `bound`, in order.
+    fn into_core(
+        self,
+        bound: &mut Vec<String>,
+        sig: &Signature,
+        errors: &mut Vec<LowerError>,
+    ) -> Option<core::Pattern> {
+        let span = self.span;
+        let (name, pats) = match self.kind {
+            PatternKind::Wildcard => {
+                return Some(core::Pattern::new(core::PatternKind::Wildcard, span))
+            }
+            PatternKind::Var(name) if sig.ctor(&name).is_none() => {
+                if bound.contains(&name) {
+                    errors.push(LowerError::new(
+                        LowerErrorKind::DuplicateBinder(name.clone()),
+                        span,
+                        &VecDeque::new(),
+                    ));
+                }
+                bound.push(name);
+                return Some(core::Pattern::new(core::PatternKind::Var, span));
+            }
+            PatternKind::Var(name) => (name, Vec::new()),
+            PatternKind::Ctor(name, pats) => (name, pats),
+        };
+        match sig.ctor(&name) {
+            Some(arity) if arity != pats.len() => errors.push(LowerError::new(
+                LowerErrorKind::Arity(name.clone(), arity, pats.len()),
+                span,
+                &VecDeque::new(),
+            )),
+            Some(_) => (),
+            None => {
+                let scope = sig
+                    .ctors
+                    .iter()
+                    .rev()
+                    .map(|(ctor, _)| ctor.clone())
+                    .collect();
+                errors.push(LowerError::new(
+                    LowerErrorKind::UnboundCtor(name.clone()),
+                    span,
+                    &scope,
+                ));
+            }
+        }
+        let pats = pats
+            .into_iter()
+            .map(|pat| pat.into_core(bound, sig, errors))
+            .collect::<Vec<_>>();
+        Some(core::Pattern::new(
+            core::PatternKind::Ctor(name, pats.into_iter().collect::<Option<_>>()?),
+            span,
+        ))
     }
 }
 
 impl Ty {
-    pub fn into_core(self) -> Option<core::Ty> {
-        self.into_core_(&mut VecDeque::new())
+    pub fn new(kind: TyKind, span: Span) -> Self {
+        Self { kind, span }
     }
 
-    pub fn into_core_(self, ctx: &mut VecDeque<String>) -> Option<core::Ty> {
-        Some(match self {
-            Ty::Bool => core::Ty::Bool,
-            Ty::Unit => core::Ty::Unit,
-            Ty::Arrow(inp, out) => core::Ty::Arrow(
-                Box::new(inp.into_core_(ctx)?),
-                Box::new(out.into_core_(ctx)?),
-            ),
-            Ty::Forall(name, body) => {
+    /// Desugar `F T1 T2 ...` into nested applications.
+    pub fn apps(func: Ty, args: Vec<Ty>) -> Ty {
+        args.into_iter().fold(func, |func, arg| {
+            let span = func.span.to(arg.span);
+            Ty::new(TyKind::App(Box::new(func), Box::new(arg)), span)
+        })
+    }
+
+    /// The names bound by the foralls at the front of this type, along with
+    /// their kinds, outermost first.
+    pub fn forall_binders(&self) -> Vec<(String, core::Kind)> {
+        let mut binders = Vec::new();
+        let mut ty = self;
+        while let Some((name, kind, body)) = ty.as_forall() {
+            let kind = kind.cloned().unwrap_or_else(|| {
+                let mut uses = TyVarUses::default();
+                body.ty_var_uses(name, &mut uses);
+                uses.kind_or_type()
+            });
+            binders.push((name.to_string(), kind));
+            ty = body;
+        }
+        binders
+    }
+
+    /// The name, kind annotation and body of this type, if it is a forall.
+    fn as_forall(&self) -> Option<(&str, Option<&core::Kind>, &Ty)> {
+        match &self.kind {
+            TyKind::Forall(name, kind, _, body) => Some((name, kind.as_ref(), body)),
+            TyKind::ImplicitForall(name, body) => Some((name, None, body)),
+            _ => None,
+        }
+    }
+
+    /// Find how the type variable `name` is used in this type.
+    fn ty_var_uses(&self, name: &str, uses: &mut TyVarUses) {
+        match &self.kind {
+            TyKind::Unit | TyKind::Bool | TyKind::Top | TyKind::Never => (),
+            TyKind::Arrow(ty1, ty2)
+            | TyKind::Sum(ty1, ty2)
+            | TyKind::Intersection(ty1, ty2)
+            | TyKind::Union(ty1, ty2)
+            | TyKind::App(ty1, ty2) => {
+                ty1.ty_var_uses(name, uses);
+                ty2.ty_var_uses(name, uses);
+            }
+            TyKind::Product(tys) => {
+                for ty in tys {
+                    ty.ty_var_uses(name, uses);
+                }
+            }
+            TyKind::Record(fields, tail) | TyKind::Variant(fields, tail) => {
+                for (_, ty) in fields {
+                    ty.ty_var_uses(name, uses);
+                }
+                if let Some(TyKind::Var(var)) = tail.as_deref().map(|tail| &tail.kind) {
+                    uses.as_row |= var == name;
+                }
+            }
+            // The bound is outside the scope of the variable it bounds.
+            TyKind::Forall(bound, _, Some(upper), body) => {
+                upper.ty_var_uses(name, uses);
+                if bound != name {
+                    body.ty_var_uses(name, uses);
+                }
+            }
+            TyKind::Forall(bound, _, None, body)
+            | TyKind::ImplicitForall(bound, body)
+            | TyKind::Lam(bound, _, body)
+            | TyKind::Mu(bound, body)
+            | TyKind::Exists(bound, body) => {
+                if bound != name {
+                    body.ty_var_uses(name, uses);
+                }
+            }
+            TyKind::Var(var) => uses.as_type |= var == name,
+            TyKind::Con(_, args) => {
+                for ty in args {
+                    ty.ty_var_uses(name, uses);
+                }
+            }
+        }
+    }
+
+    /// Whether the type variable or data type `name` occurs to the left of
+    /// an arrow in this type, including by being passed to a parameter of a
+    /// data type that does. Arguments to type variables, and to synonyms
+    /// beyond their parameters, could be used either way, so any occurrence
+    /// in them counts, as does any occurrence in the bound of a quantifier.
+    fn occurs_negatively(&self, name: &str, sig: &Signature) -> bool {
+        match &self.kind {
+            TyKind::Unit | TyKind::Bool | TyKind::Top | TyKind::Never | TyKind::Var(_) => false,
+            TyKind::Arrow(inp, out) => inp.mentions(name) || out.occurs_negatively(name, sig),
+            TyKind::Sum(ty1, ty2) | TyKind::Intersection(ty1, ty2) | TyKind::Union(ty1, ty2) => {
+                ty1.occurs_negatively(name, sig) || ty2.occurs_negatively(name, sig)
+            }
+            TyKind::Product(tys) => tys.iter().any(|ty| ty.occurs_negatively(name, sig)),
+            TyKind::Record(fields, _) | TyKind::Variant(fields, _) => {
+                fields.iter().any(|(_, ty)| ty.occurs_negatively(name, sig))
+            }
+            TyKind::Forall(bound, _, upper, body) => {
+                upper.as_ref().is_some_and(|upper| upper.mentions(name))
+                    || (bound != name && body.occurs_negatively(name, sig))
+            }
+            TyKind::ImplicitForall(bound, body)
+            | TyKind::Lam(bound, _, body)
+            | TyKind::Mu(bound, body)
+            | TyKind::Exists(bound, body) => bound != name && body.occurs_negatively(name, sig),
+            TyKind::App(func, arg) => func.occurs_negatively(name, sig) || arg.mentions(name),
+            TyKind::Con(data, args) => {
+                let negative = sig.negative_params(data).unwrap_or(&[]);
+                args.iter().enumerate().any(|(idx, ty)| {
+                    if negative.get(idx).copied().unwrap_or(true) {
+                        ty.mentions(name)
+                    } else {
+                        ty.occurs_negatively(name, sig)
+                    }
+                })
+            }
+        }
+    }
+
+    /// Whether the type variable or data type `name` occurs in this type.
+    fn mentions(&self, name: &str) -> bool {
+        match &self.kind {
+            TyKind::Unit | TyKind::Bool | TyKind::Top | TyKind::Never => false,
+            TyKind::Var(var) => var == name,
+            TyKind::Arrow(ty1, ty2)
+            | TyKind::Sum(ty1, ty2)
+            | TyKind::Intersection(ty1, ty2)
+            | TyKind::Union(ty1, ty2)
+            | TyKind::App(ty1, ty2) => ty1.mentions(name) || ty2.mentions(name),
+            TyKind::Product(tys) => tys.iter().any(|ty| ty.mentions(name)),
+            TyKind::Record(fields, tail) | TyKind::Variant(fields, tail) => {
+                fields.iter().any(|(_, ty)| ty.mentions(name))
+                    || tail.as_ref().is_some_and(|tail| tail.mentions(name))
+            }
+            TyKind::Forall(bound, _, upper, body) => {
+                upper.as_ref().is_some_and(|upper| upper.mentions(name))
+                    || (bound != name && body.mentions(name))
+            }
+            TyKind::ImplicitForall(bound, body)
+            | TyKind::Lam(bound, _, body)
+            | TyKind::Mu(bound, body)
+            | TyKind::Exists(bound, body) => bound != name && body.mentions(name),
+            TyKind::Con(data, args) => data == name || args.iter().any(|ty| ty.mentions(name)),
+        }
+    }
+
+    /// The names bound by the foralls at the front of this type, outermost
+    /// first.
+    pub fn forall_prefix(&self) -> Vec<String> {
+        let mut names = Vec::new();
+        let mut ty = self;
+        while let Some((name, _, body)) = ty.as_forall() {
+            names.push(name.to_string());
+            ty = body;
+        }
+        names
+    }
+
+    /// Universally quantify the type variables that are free in this type
+    /// and not in `scope`, at the front and in order of first appearance.
+    pub fn quantify_free_ty_vars(&mut self, scope: &[String]) {
+        let mut free = Vec::new();
+        self.free_ty_vars(&mut scope.to_vec(), &mut free);
+        for name in free.into_iter().rev() {
+            let span = self.span;
+            let body = std::mem::replace(self, Ty::new(TyKind::Unit, span));
+            *self = Ty::new(TyKind::ImplicitForall(name, Box::new(body)), span);
+        }
+    }
+
+    fn free_ty_vars(&self, bound: &mut Vec<String>, acc: &mut Vec<String>) {
+        match &self.kind {
+            TyKind::Unit | TyKind::Bool | TyKind::Top | TyKind::Never => (),
+            TyKind::Arrow(inp, out)
+            | TyKind::Sum(inp, out)
+            | TyKind::Intersection(inp, out)
+            | TyKind::Union(inp, out)
+            | TyKind::App(inp, out) => {
+                inp.free_ty_vars(bound, acc);
+                out.free_ty_vars(bound, acc);
+            }
+            TyKind::Product(tys) => {
+                for ty in tys {
+                    ty.free_ty_vars(bound, acc);
+                }
+            }
+            TyKind::Record(fields, tail) | TyKind::Variant(fields, tail) => {
+                for (_, ty) in fields {
+                    ty.free_ty_vars(bound, acc);
+                }
+                if let Some(tail) = tail {
+                    tail.free_ty_vars(bound, acc);
+                }
+            }
+            TyKind::Forall(name, _, upper, body) => {
+                if let Some(upper) = upper {
+                    upper.free_ty_vars(bound, acc);
+                }
+                bound.push(name.clone());
+                body.free_ty_vars(bound, acc);
+                bound.pop();
+            }
+            TyKind::ImplicitForall(name, body)
+            | TyKind::Lam(name, _, body)
+            | TyKind::Mu(name, body)
+            | TyKind::Exists(name, body) => {
+                bound.push(name.clone());
+                body.free_ty_vars(bound, acc);
+                bound.pop();
+            }
+            TyKind::Var(name) => {
+                if !bound.contains(name) && !acc.contains(name) {
+                    acc.push(name.clone());
+                }
+            }
+            TyKind::Con(_, args) => {
+                for ty in args {
+                    ty.free_ty_vars(bound, acc);
+                }
+            }
+        }
+    }
+
+    /// Lower to the core AST, reporting every unbound type variable.
+    pub fn into_core(self) -> Result<core::Ty, Vec<LowerError>> {
+        let mut errors = Vec::new();
+        match self.into_core_(&mut VecDeque::new(), &Signature::default(), &mut errors) {
+            Some(ty) if errors.is_empty() => Ok(ty),
+            _ => Err(errors),
+        }
+    }
+
+    pub fn into_core_(
+        self,
+        ctx: &mut VecDeque<String>,
+        sig: &Signature,
+        errors: &mut Vec<LowerError>,
+    ) -> Option<core::Ty> {
+        let span = self.span;
+        let kind = match self.kind {
+            TyKind::Bool => core::TyKind::Bool,
+            TyKind::Unit => core::TyKind::Unit,
+            TyKind::Top => core::TyKind::Top,
+            TyKind::Never => core::TyKind::Never,
+            TyKind::Arrow(inp, out) => {
+                let inp = inp.into_core_(ctx, sig, errors);
+                let out = out.into_core_(ctx, sig, errors);
+                core::TyKind::Arrow(Box::new(inp?), Box::new(out?))
+            }
+            TyKind::Product(tys) => {
+                let tys = tys
+                    .into_iter()
+                    .map(|ty| ty.into_core_(ctx, sig, errors))
+                    .collect::<Vec<_>>();
+                core::TyKind::Product(tys.into_iter().collect::<Option<_>>()?)
+            }
+            TyKind::Sum(left, right) => {
+                let left = left.into_core_(ctx, sig, errors);
+                let right = right.into_core_(ctx, sig, errors);
+                core::TyKind::Sum(Box::new(left?), Box::new(right?))
+            }
+            TyKind::Intersection(left, right) => {
+                let left = left.into_core_(ctx, sig, errors);
+                let right = right.into_core_(ctx, sig, errors);
+                core::TyKind::Intersection(Box::new(left?), Box::new(right?))
+            }
+            TyKind::Union(left, right) => {
+                let left = left.into_core_(ctx, sig, errors);
+                let right = right.into_core_(ctx, sig, errors);
+                core::TyKind::Union(Box::new(left?), Box::new(right?))
+            }
+            TyKind::Record(fields, tail) => {
+                core::TyKind::Record(Ty::row_into_core(fields, tail, span, ctx, sig, errors)?)
+            }
+            TyKind::Variant(fields, tail) => {
+                core::TyKind::Variant(Ty::row_into_core(fields, tail, span, ctx, sig, errors)?)
+            }
+            TyKind::Forall(name, kind, upper, body) => {
+                let upper = upper.map(|upper| upper.into_core_(ctx, sig, errors));
+                let (kind, body) = Ty::binder_into_core(name, kind, *body, span, ctx, sig, errors)?;
+                let upper = match upper {
+                    Some(upper) => Some(Box::new(upper?)),
+                    None => None,
+                };
+                core::TyKind::Forall(kind, upper, Box::new(body))
+            }
+            TyKind::ImplicitForall(name, body) => {
+                let (kind, body) = Ty::binder_into_core(name, None, *body, span, ctx, sig, errors)?;
+                core::TyKind::Forall(kind, None, Box::new(body))
+            }
+            TyKind::Lam(name, kind, body) => {
+                let (kind, body) = Ty::binder_into_core(name, kind, *body, span, ctx, sig, errors)?;
+                core::TyKind::Lam(kind, Box::new(body))
+            }
+            TyKind::App(func, arg) => {
+                let func = func.into_core_(ctx, sig, errors);
+                let arg = arg.into_core_(ctx, sig, errors);
+                core::TyKind::App(Box::new(func?), Box::new(arg?))
+            }
+            TyKind::Exists(name, body) => {
+                let mut uses = TyVarUses::default();
+                body.ty_var_uses(&name, &mut uses);
+                uses.require_type(&name, span, errors);
                 ctx.push_front(name);
-                let new_body = body.into_core_(ctx);
+                let new_body = body.into_core_(ctx, sig, errors);
                 ctx.pop_front();
-                core::Ty::Forall(Box::new(new_body?))
+                core::TyKind::Exists(Box::new(new_body?))
             }
-            Ty::Var(name) => {
-                let idx =
-                    ctx.iter()
-                        .enumerate()
-                        .find_map(|(idx, s)| if s == &name { Some(idx) } else { None })?;
-                core::Ty::Var(idx)
+            TyKind::Mu(name, body) => {
+                let mut uses = TyVarUses::default();
+                body.ty_var_uses(&name, &mut uses);
+                uses.require_type(&name, span, errors);
+                if body.occurs_negatively(&name, sig) {
+                    errors.push(LowerError::new(
+                        LowerErrorKind::NegativeOccurrence(name.clone()),
+                        span,
+                        &VecDeque::new(),
+                    ));
+                }
+                ctx.push_front(name);
+                let new_body = body.into_core_(ctx, sig, errors);
+                ctx.pop_front();
+                core::TyKind::Mu(Box::new(new_body?))
             }
+            TyKind::Var(name) => match ctx.iter().position(|s| s == &name) {
+                Some(idx) => core::TyKind::Var(idx),
+                None => {
+                    errors.push(LowerError::new(
+                        LowerErrorKind::UnboundTyVar(name),
+                        span,
+                        ctx,
+                    ));
+                    return None;
+                }
+            },
+            TyKind::Con(name, args) => {
+                let args = args
+                    .into_iter()
+                    .map(|ty| ty.into_core_(ctx, sig, errors))
+                    .collect::<Vec<_>>();
+                // A synonym given more arguments than it has parameters
+                // stands for a type operator applied to the rest.
+                if let Some(synonym) = sig.synonym(&name) {
+                    let args = args.into_iter().collect::<Option<Vec<_>>>()?;
+                    if args.len() < synonym.params.len() {
+                        return Some(eta_expand(&synonym.params, args, span, |args| {
+                            synonym.body.clone().instantiate(args).kind
+                        }));
+                    }
+                    let (args, rest) = args.split_at(synonym.params.len());
+                    let expanded = synonym.body.clone().instantiate(args);
+                    return Some(rest.iter().fold(
+                        core::Ty::new(expanded.kind, span),
+                        |func, arg| {
+                            core::Ty::new(
+                                core::TyKind::App(Box::new(func), Box::new(arg.clone())),
+                                span,
+                            )
+                        },
+                    ));
+                }
+                match sig.data_params(&name) {
+                    Some(params) if params.len() < args.len() => {
+                        errors.push(LowerError::new(
+                            LowerErrorKind::Arity(name, params.len(), args.len()),
+                            span,
+                            &VecDeque::new(),
+                        ));
+                        return None;
+                    }
+                    Some(params) if params.len() > args.len() => {
+                        let args = args.into_iter().collect::<Option<Vec<_>>>()?;
+                        return Some(eta_expand(params, args, span, |args| {
+                            core::TyKind::Data(name, args.to_vec())
+                        }));
+                    }
+                    Some(_) => (),
+                    None => {
+                        let scope = sig.ty_names();
+                        errors.push(LowerError::new(
+                            LowerErrorKind::UnboundType(name),
+                            span,
+                            &scope,
+                        ));
+                        return None;
+                    }
+                }
+                core::TyKind::Data(name, args.into_iter().collect::<Option<_>>()?)
+            }
+        };
+        Some(core::Ty::new(kind, span))
+    }
+
+    /// Lower the body of a forall or type operator binding `name` at `span`,
+    /// along with the kind of `name`: `kind` if it was annotated with one,
+    /// and otherwise the one it is used as.
+    fn binder_into_core(
+        name: String,
+        kind: Option<core::Kind>,
+        body: Ty,
+        span: Span,
+        ctx: &mut VecDeque<String>,
+        sig: &Signature,
+        errors: &mut Vec<LowerError>,
+    ) -> Option<(core::Kind, core::Ty)> {
+        let kind = kind.unwrap_or_else(|| {
+            let mut uses = TyVarUses::default();
+            body.ty_var_uses(&name, &mut uses);
+            uses.kind(&name, span, errors)
+        });
+        ctx.push_front(name);
+        let body = body.into_core_(ctx, sig, errors);
+        ctx.pop_front();
+        Some((kind, body?))
+    }
+
+    fn row_into_core(
+        fields: Vec<(String, Ty)>,
+        tail: Option<Box<Ty>>,
+        span: Span,
+        ctx: &mut VecDeque<String>,
+        sig: &Signature,
+        errors: &mut Vec<LowerError>,
+    ) -> Option<core::Row> {
+        check_labels(fields.iter().map(|(label, _)| label), span, errors);
+        let fields = fields
+            .into_iter()
+            .map(|(label, ty)| Some((label, ty.into_core_(ctx, sig, errors)?)))
+            .collect::<Vec<_>>();
+        let tail = tail.map(|tail| tail.into_core_(ctx, sig, errors));
+        let tail = match tail {
+            Some(tail) => Some(tail?),
+            None => None,
+        };
+        Some(core::Row::new(
+            fields.into_iter().collect::<Option<_>>()?,
+            tail,
+        ))
+    }
+}
+
+/// The data types, constructors and type synonyms declared so far, which
+/// lowering needs to resolve type names and to tell constructors from
+/// variables.
+#[derive(Debug, Default)]
+pub struct Signature {
+    /// Each data type's name, the kinds of its parameters, and whether each
+    /// occurs negatively in its constructors.
+    data: Vec<(String, Vec<core::Kind>, Vec<bool>)>,
+    /// Each constructor's name and number of fields.
+    ctors: Vec<(String, usize)>,
+    /// Each type synonym, and whether each of its parameters occurs
+    /// negatively in its body.
+    synonyms: Vec<(core::Synonym, Vec<bool>)>,
+}
+
+impl Signature {
+    fn synonym(&self, name: &str) -> Option<&core::Synonym> {
+        self.synonyms
+            .iter()
+            .map(|(synonym, _)| synonym)
+            .find(|synonym| synonym.name == name)
+    }
+
+    /// Whether a data type or synonym called `name` has been declared.
+    fn declares_ty(&self, name: &str) -> bool {
+        self.data(name).is_some() || self.synonym(name).is_some()
+    }
+
+    /// The names of the data types and synonyms, for suggestions.
+    fn ty_names(&self) -> VecDeque<String> {
+        self.data
+            .iter()
+            .map(|(data, ..)| data.clone())
+            .chain(
+                self.synonyms
+                    .iter()
+                    .map(|(synonym, _)| synonym.name.clone()),
+            )
+            .rev()
+            .collect()
+    }
+
+    /// Whether each parameter of the data type or synonym `name` occurs
+    /// negatively in it.
+    fn negative_params(&self, name: &str) -> Option<&[bool]> {
+        self.data(name).or_else(|| {
+            self.synonyms
+                .iter()
+                .find(|(synonym, _)| synonym.name == name)
+                .map(|(_, negative)| negative.as_slice())
         })
     }
+
+    fn data(&self, name: &str) -> Option<&[bool]> {
+        self.data
+            .iter()
+            .find(|(data, ..)| data == name)
+            .map(|(_, _, negative)| negative.as_slice())
+    }
+
+    /// The kinds of the parameters of the data type `name`.
+    fn data_params(&self, name: &str) -> Option<&[core::Kind]> {
+        self.data
+            .iter()
+            .find(|(data, ..)| data == name)
+            .map(|(_, kinds, _)| kinds.as_slice())
+    }
+
+    fn ctor(&self, name: &str) -> Option<usize> {
+        self.ctors
+            .iter()
+            .find(|(ctor, _)| ctor == name)
+            .map(|(_, arity)| *arity)
+    }
+}
+
+/// The eta-expansion of a data type or synonym with parameters of kinds
+/// `params` that was only given `args` for the first few: a type operator
+/// taking the rest, and then giving `mk` applied to all of them.
+fn eta_expand(
+    params: &[core::Kind],
+    args: Vec<core::Ty>,
+    span: Span,
+    mk: impl FnOnce(&[core::Ty]) -> core::TyKind,
+) -> core::Ty {
+    let missing = params.len() - args.len();
+    let args = args
+        .into_iter()
+        .map(|arg| arg.shift(missing))
+        .chain(
+            (0..missing)
+                .rev()
+                .map(|idx| core::Ty::new(core::TyKind::Var(idx), span)),
+        )
+        .collect::<Vec<_>>();
+    params[params.len() - missing..]
+        .iter()
+        .rev()
+        .fold(core::Ty::new(mk(&args), span), |body, kind| {
+            core::Ty::new(core::TyKind::Lam(kind.clone(), Box::new(body)), span)
+        })
+}
+
+/// How a type variable is used, which determines its kind.
+#[derive(Debug, Default)]
+struct TyVarUses {
+    as_type: bool,
+    /// As the tail of a record or variant type.
+    as_row: bool,
+}
+
+impl TyVarUses {
+    fn kind_or_type(&self) -> core::Kind {
+        if self.as_row && !self.as_type {
+            core::Kind::Row
+        } else {
+            core::Kind::Type
+        }
+    }
+
+    /// The kind of the variable `name`, bound at `span` without a kind
+    /// annotation, reporting an error if it is used as more than one.
+    fn kind(&self, name: &str, span: Span, errors: &mut Vec<LowerError>) -> core::Kind {
+        if self.as_row && self.as_type {
+            errors.push(LowerError::new(
+                LowerErrorKind::KindMismatch(name.to_string()),
+                span,
+                &VecDeque::new(),
+            ));
+        }
+        self.kind_or_type()
+    }
+
+    /// Report an error if the variable `name`, bound at `span` by something
+    /// that only binds types (such as `mu` or `exists`), is used as a row.
+    fn require_type(&self, name: &str, span: Span, errors: &mut Vec<LowerError>) {
+        if self.as_row {
+            errors.push(LowerError::new(
+                LowerErrorKind::KindMismatch(name.to_string()),
+                span,
+                &VecDeque::new(),
+            ));
+        }
+    }
+}
+
+/// Report each label that appears more than once in a record or record type
+/// spanning `span`.
+fn check_labels<'a>(
+    labels: impl Iterator<Item = &'a String>,
+    span: Span,
+    errors: &mut Vec<LowerError>,
+) {
+    let mut seen: Vec<&String> = Vec::new();
+    let mut reported: Vec<&String> = Vec::new();
+    for label in labels {
+        if seen.contains(&label) {
+            if !reported.contains(&label) {
+                reported.push(label);
+                errors.push(LowerError::new(
+                    LowerErrorKind::DuplicateLabel(label.clone()),
+                    span,
+                    &VecDeque::new(),
+                ));
+            }
+        } else {
+            seen.push(label);
+        }
+    }
 }
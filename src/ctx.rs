@@ -4,14 +4,18 @@ use crate::trace;
 #[cfg(feature = "trace")]
 use tracing::instrument;
 
-use crate::ast::core::Ty;
+use crate::ast::core::{DataDecl, Kind, Ty};
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum TyCtxtEntry {
-    UnsolvedExst(usize),
+    /// An unsolved existential of the given kind. Once solved, it becomes a
+    /// `SolvedExst`, whatever its kind.
+    UnsolvedExst(usize, Kind),
     ExstMarker(usize),
     SolvedExst(usize, Ty),
-    Uvar,
+    /// A universal variable of the given kind, and its upper bound if it was
+    /// bound by a bounded quantifier.
+    Uvar(usize, Kind, Option<Ty>),
     TermVar(Ty),
 }
 
@@ -19,29 +23,67 @@ pub enum TyCtxtEntry {
 pub struct TyCtxt {
     arr: Vec<TyCtxtEntry>,
     fresh_evar: usize,
+    fresh_uvar: usize,
+    /// The data types declared so far. Unlike the entries in `arr`, these
+    /// are not ordered relative to the variables in context.
+    data: Vec<DataDecl>,
 }
 
 impl TyCtxt {
+    pub fn add_data(&mut self, decl: DataDecl) {
+        self.data.push(decl);
+    }
+
+    pub fn get_data(&self, name: &str) -> Option<&DataDecl> {
+        self.data.iter().find(|decl| decl.name == name)
+    }
+
+    /// The data type that declares the constructor `name`, along with the
+    /// constructor's position in it.
+    pub fn get_ctor(&self, name: &str) -> Option<(&DataDecl, usize)> {
+        self.data.iter().find_map(|decl| {
+            decl.ctors
+                .iter()
+                .position(|(ctor, _)| ctor == name)
+                .map(|idx| (decl, idx))
+        })
+    }
+
     #[cfg_attr(feature = "trace", instrument(level = "trace", skip(self)))]
-    pub fn get_uvar(&self, idx: usize) -> Option<usize> {
+    pub fn get_uvar(&self, uvar: usize) -> Option<usize> {
         trace!("ctx/get_uvar/enter");
         let res = self
             .arr
             .iter()
-            .rev()
-            .enumerate()
-            .filter_map(|(idx, x)| {
-                if x == &TyCtxtEntry::Uvar {
-                    Some(idx)
-                } else {
-                    None
-                }
-            })
-            .nth(idx);
+            .position(|x| matches!(x, TyCtxtEntry::Uvar(u, ..) if *u == uvar));
         trace!(?res, "ctx/get_uvar/leave");
         res
     }
 
+    /// The kind of a universal variable.
+    #[cfg_attr(feature = "trace", instrument(level = "trace", skip(self)))]
+    pub fn uvar_kind(&self, uvar: usize) -> Option<&Kind> {
+        trace!("ctx/uvar_kind/enter");
+        let res = self.arr.iter().find_map(|x| match x {
+            TyCtxtEntry::Uvar(u, kind, _) if *u == uvar => Some(kind),
+            _ => None,
+        });
+        trace!(?res, "ctx/uvar_kind/leave");
+        res
+    }
+
+    /// The upper bound of a universal variable, if it has one.
+    #[cfg_attr(feature = "trace", instrument(level = "trace", skip(self)))]
+    pub fn uvar_bound(&self, uvar: usize) -> Option<&Ty> {
+        trace!("ctx/uvar_bound/enter");
+        let res = self.arr.iter().find_map(|x| match x {
+            TyCtxtEntry::Uvar(u, _, bound) if *u == uvar => bound.as_ref(),
+            _ => None,
+        });
+        trace!(?res, "ctx/uvar_bound/leave");
+        res
+    }
+
     #[cfg_attr(feature = "trace", instrument(level = "trace", skip(self)))]
     pub fn get_term_var(&self, idx: usize) -> Option<&Ty> {
         trace!("ctx/get_term_var/enter");
@@ -84,22 +126,18 @@ impl TyCtxt {
     #[cfg_attr(feature = "trace", instrument(level = "trace", skip(self)))]
     fn get_unsolved_evar_mut(&mut self, idx: usize) -> Option<&mut TyCtxtEntry> {
         trace!("ctx/get_unsolved_evar_mut/enter");
-        let res = self.arr.iter_mut().find_map(|x| {
-            if let TyCtxtEntry::UnsolvedExst(eidx) = x {
-                if *eidx == idx {
-                    return Some(x);
-                }
-            }
-            None
+        let res = self.arr.iter_mut().find_map(|x| match x {
+            TyCtxtEntry::UnsolvedExst(eidx, _) if *eidx == idx => Some(x),
+            _ => None,
         });
         trace!(?res, "ctx/get_unsolved_evar_mut/leave");
         res
     }
 
     #[cfg_attr(feature = "trace", instrument(level = "trace", skip(self)))]
-    pub fn contains_uvar(&self, idx: usize) -> bool {
+    pub fn contains_uvar(&self, uvar: usize) -> bool {
         trace!("ctx/contains_uvar/enter");
-        let res = self.get_uvar(idx).is_some();
+        let res = self.get_uvar(uvar).is_some();
         trace!(?res, "ctx/contains_uvar/leave");
         res
     }
@@ -107,20 +145,31 @@ impl TyCtxt {
     #[cfg_attr(feature = "trace", instrument(level = "trace", skip(self)))]
     pub fn contains_evar(&self, idx: usize) -> bool {
         trace!("ctx/contains_evar/enter");
-        let res = self.arr.iter().any(|x| match x {
-            TyCtxtEntry::UnsolvedExst(i)
-            | TyCtxtEntry::ExstMarker(i)
-            | TyCtxtEntry::SolvedExst(i, _)
-                if *i == idx =>
-            {
-                true
-            }
-            _ => false,
+        let res = self.arr.iter().any(|x| {
+            matches!(
+                x,
+                TyCtxtEntry::UnsolvedExst(i, _)
+                | TyCtxtEntry::ExstMarker(i)
+                | TyCtxtEntry::SolvedExst(i, _)
+                    if *i == idx
+            )
         });
         trace!(?res, "ctx/contains_evar/leave");
         res
     }
 
+    /// The kind of an unsolved existential.
+    #[cfg_attr(feature = "trace", instrument(level = "trace", skip(self)))]
+    pub fn evar_kind(&self, idx: usize) -> Option<Kind> {
+        trace!("ctx/evar_kind/enter");
+        let res = self.arr.iter().find_map(|x| match x {
+            TyCtxtEntry::UnsolvedExst(i, kind) if *i == idx => Some(kind.clone()),
+            _ => None,
+        });
+        trace!(?res, "ctx/evar_kind/leave");
+        res
+    }
+
     #[cfg_attr(feature = "trace", instrument(level = "trace", skip(self)))]
     pub fn drop_after_marker(&mut self, evar: usize) -> Option<()> {
         trace!("ctx/drop_after_marker/enter");
@@ -140,9 +189,9 @@ impl TyCtxt {
     }
 
     #[cfg_attr(feature = "trace", instrument(level = "trace", skip(self)))]
-    pub fn drop_after_uvar(&mut self, idx: usize) -> Option<()> {
+    pub fn drop_after_uvar(&mut self, uvar: usize) -> Option<()> {
         trace!("ctx/drop_after_uvar/enter");
-        let uvar_idx = self.get_uvar(idx)?;
+        let uvar_idx = self.get_uvar(uvar)?;
 
         // Drop everything after the uvar.
         self.arr.truncate(uvar_idx);
@@ -163,6 +212,18 @@ impl TyCtxt {
         Some(())
     }
 
+    /// Remove just the term var, keeping everything after it in context.
+    #[cfg_attr(feature = "trace", instrument(level = "trace", skip(self)))]
+    pub fn remove_term_var(&mut self, idx: usize) -> Option<()> {
+        trace!("ctx/remove_term_var/enter");
+        let term_var_idx = self.get_term_var_idx(idx)?;
+
+        self.arr.remove(term_var_idx);
+
+        trace!("ctx/remove_term_var/leave: ok");
+        Some(())
+    }
+
     #[cfg_attr(feature = "trace", instrument(level = "trace", skip(self)))]
     pub fn fresh_evar(&mut self) -> usize {
         trace!("ctx/fresh_evar/enter");
@@ -172,6 +233,15 @@ impl TyCtxt {
         fresh_evar
     }
 
+    #[cfg_attr(feature = "trace", instrument(level = "trace", skip(self)))]
+    pub fn fresh_uvar(&mut self) -> usize {
+        trace!("ctx/fresh_uvar/enter");
+        let fresh_uvar = self.fresh_uvar;
+        self.fresh_uvar += 1;
+        trace!(%fresh_uvar, "ctx/fresh_uvar/leave");
+        fresh_uvar
+    }
+
     #[cfg_attr(feature = "trace", instrument(level = "trace", skip(self)))]
     pub fn add_marker(&mut self, evar: usize) {
         trace!("ctx/add_marker");
@@ -181,13 +251,26 @@ impl TyCtxt {
     #[cfg_attr(feature = "trace", instrument(level = "trace", skip(self)))]
     pub fn add_unsolved(&mut self, evar: usize) {
         trace!("ctx/add_unsolved");
-        self.arr.push(TyCtxtEntry::UnsolvedExst(evar));
+        self.arr.push(TyCtxtEntry::UnsolvedExst(evar, Kind::Type));
+    }
+
+    #[cfg_attr(feature = "trace", instrument(level = "trace", skip(self)))]
+    pub fn add_unsolved_row(&mut self, evar: usize) {
+        trace!("ctx/add_unsolved_row");
+        self.arr.push(TyCtxtEntry::UnsolvedExst(evar, Kind::Row));
+    }
+
+    /// Add an unsolved existential of the given kind.
+    #[cfg_attr(feature = "trace", instrument(level = "trace", skip(self)))]
+    pub fn add_unsolved_of_kind(&mut self, evar: usize, kind: &Kind) {
+        trace!("ctx/add_unsolved_of_kind");
+        self.arr.push(TyCtxtEntry::UnsolvedExst(evar, kind.clone()));
     }
 
     #[cfg_attr(feature = "trace", instrument(level = "trace", skip(self)))]
-    pub fn add_uvar(&mut self) {
+    pub fn add_uvar(&mut self, uvar: usize, kind: Kind, bound: Option<Ty>) {
         trace!("ctx/add_uvar");
-        self.arr.push(TyCtxtEntry::Uvar);
+        self.arr.push(TyCtxtEntry::Uvar(uvar, kind, bound));
     }
 
     #[cfg_attr(feature = "trace", instrument(level = "trace", skip(self)))]
@@ -214,22 +297,54 @@ impl TyCtxt {
     #[cfg_attr(feature = "trace", instrument(level = "trace", skip(self)))]
     pub fn insert_unsolved_before_evar(&mut self, evar: usize, unsolved: usize) -> Option<()> {
         trace!("ctx/insert_unsolved_before_evar/enter");
-        let evar_idx = self.arr.iter().enumerate().find_map(|(idx, x)| {
-            if let TyCtxtEntry::UnsolvedExst(eidx) = x {
-                if *eidx == evar {
-                    return Some(idx);
-                }
-            }
-            None
-        })?;
+        let evar_idx = self.get_unsolved_evar_idx(evar)?;
 
         self.arr
-            .insert(evar_idx, TyCtxtEntry::UnsolvedExst(unsolved));
+            .insert(evar_idx, TyCtxtEntry::UnsolvedExst(unsolved, Kind::Type));
 
         trace!("ctx/insert_unsolved_before_evar/leave");
         Some(())
     }
 
+    #[cfg_attr(feature = "trace", instrument(level = "trace", skip(self)))]
+    pub fn insert_unsolved_row_before_evar(&mut self, evar: usize, unsolved: usize) -> Option<()> {
+        trace!("ctx/insert_unsolved_row_before_evar/enter");
+        let evar_idx = self.get_unsolved_evar_idx(evar)?;
+
+        self.arr
+            .insert(evar_idx, TyCtxtEntry::UnsolvedExst(unsolved, Kind::Row));
+
+        trace!("ctx/insert_unsolved_row_before_evar/leave");
+        Some(())
+    }
+
+    fn get_unsolved_evar_idx(&self, evar: usize) -> Option<usize> {
+        self.arr.iter().position(|x| {
+            matches!(
+                x,
+                TyCtxtEntry::UnsolvedExst(eidx, _)
+                    if *eidx == evar
+            )
+        })
+    }
+
+    #[cfg_attr(feature = "trace", instrument(level = "trace", skip(self)))]
+    pub fn insert_unsolved_before_uvar(
+        &mut self,
+        uvar: usize,
+        unsolved: usize,
+        kind: Kind,
+    ) -> Option<()> {
+        trace!("ctx/insert_unsolved_before_uvar/enter");
+        let uvar_idx = self.get_uvar(uvar)?;
+
+        self.arr
+            .insert(uvar_idx, TyCtxtEntry::UnsolvedExst(unsolved, kind));
+
+        trace!("ctx/insert_unsolved_before_uvar/leave");
+        Some(())
+    }
+
     #[cfg_attr(feature = "trace", instrument(level = "trace", skip(self)))]
     pub fn get_solved(&self) -> HashMap<usize, &Ty> {
         trace!("ctx/get_solved/enter");
@@ -253,6 +368,8 @@ impl TyCtxt {
         trace!("ctx/clear");
         self.arr.clear();
         self.fresh_evar = 0;
+        self.fresh_uvar = 0;
+        self.data.clear();
     }
 
     pub fn slice(&'_ self, range: Range<usize>) -> TyCtxtView<'_> {
@@ -263,7 +380,7 @@ impl TyCtxt {
 
     pub fn slice_until_evar(&'_ self, evar: usize) -> Option<TyCtxtView<'_>> {
         let evar_idx = self.arr.iter().enumerate().find_map(|(idx, x)| match x {
-            TyCtxtEntry::UnsolvedExst(i)
+            TyCtxtEntry::UnsolvedExst(i, _)
             | TyCtxtEntry::ExstMarker(i)
             | TyCtxtEntry::SolvedExst(i, _)
                 if *i == evar =>
@@ -274,6 +391,11 @@ impl TyCtxt {
         })?;
         Some(self.slice(0..evar_idx))
     }
+
+    pub fn slice_until_uvar(&'_ self, uvar: usize) -> Option<TyCtxtView<'_>> {
+        let uvar_idx = self.get_uvar(uvar)?;
+        Some(self.slice(0..uvar_idx))
+    }
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
@@ -285,44 +407,34 @@ impl<'a> TyCtxtView<'a> {
     #[cfg_attr(feature = "trace", instrument(level = "trace", skip(self)))]
     pub fn contains_evar(&self, idx: usize) -> bool {
         trace!("ctxview/contains_evar/enter");
-        let res = self.arr.iter().any(|x| match x {
-            TyCtxtEntry::UnsolvedExst(i)
-            | TyCtxtEntry::ExstMarker(i)
-            | TyCtxtEntry::SolvedExst(i, _)
-                if *i == idx =>
-            {
-                true
-            }
-            _ => false,
+        let res = self.arr.iter().any(|x| {
+            matches!(
+                x,
+                TyCtxtEntry::UnsolvedExst(i, _)
+                | TyCtxtEntry::ExstMarker(i)
+                | TyCtxtEntry::SolvedExst(i, _)
+                    if *i == idx
+            )
         });
         trace!(?res, "ctxview/contains_evar/leave");
         res
     }
 
     #[cfg_attr(feature = "trace", instrument(level = "trace", skip(self)))]
-    pub fn contains_uvar(&self, idx: usize) -> bool {
+    pub fn contains_uvar(&self, uvar: usize) -> bool {
         trace!("ctxview/contains_uvar/enter");
-        let res = self.get_uvar(idx).is_some();
+        let res = self.get_uvar(uvar).is_some();
         trace!(?res, "ctxview/contains_uvar/leave");
         res
     }
 
     #[cfg_attr(feature = "trace", instrument(level = "trace", skip(self)))]
-    pub fn get_uvar(&self, idx: usize) -> Option<usize> {
+    pub fn get_uvar(&self, uvar: usize) -> Option<usize> {
         trace!("ctxview/get_uvar/enter");
         let res = self
             .arr
             .iter()
-            .rev()
-            .enumerate()
-            .filter_map(|(idx, x)| {
-                if x == &TyCtxtEntry::Uvar {
-                    Some(idx)
-                } else {
-                    None
-                }
-            })
-            .nth(idx);
+            .position(|x| matches!(x, TyCtxtEntry::Uvar(u, ..) if *u == uvar));
         trace!(?res, "ctxview/get_uvar/leave");
         res
     }
@@ -0,0 +1,518 @@
+//! Rendering of parse, lowering, kind and type errors as user-facing messages.
+
+use std::fmt::{self, Write};
+
+use lalrpop_util::ParseError;
+
+use crate::ast::core::{Ty, TyKind};
+use crate::ast::parse::{LowerError, LowerErrorKind};
+use crate::encode::{EncodeError, EncodeErrorKind};
+use crate::kindck::{KindError, KindErrorKind};
+use crate::pp::pp_core_ty;
+use crate::span::Span;
+use crate::typeck::{TypeError, TypeErrorKind};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => f.write_str("error"),
+            Severity::Warning => f.write_str("warning"),
+        }
+    }
+}
+
+/// How a [`Diagnostic`] should be rendered.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// rustc-style messages with source excerpts, for humans.
+    Human,
+    /// One `file:line:col: severity: message` line per label, for editors.
+    Plain,
+}
+
+/// A message attached to a span of the source.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+}
+
+impl Label {
+    pub fn new(span: Span, message: impl Into<String>) -> Self {
+        Self {
+            span,
+            message: message.into(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    pub primary: Label,
+    pub secondary: Vec<Label>,
+    pub notes: Vec<String>,
+}
+
+impl Diagnostic {
+    pub fn new(severity: Severity, message: impl Into<String>, primary: Label) -> Self {
+        Self {
+            severity,
+            message: message.into(),
+            primary,
+            secondary: Vec::new(),
+            notes: Vec::new(),
+        }
+    }
+
+    pub fn error(message: impl Into<String>, primary: Label) -> Self {
+        Self::new(Severity::Error, message, primary)
+    }
+
+    #[must_use]
+    pub fn with_secondary(mut self, label: Label) -> Self {
+        self.secondary.push(label);
+        self
+    }
+
+    #[must_use]
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    pub fn render(&self, format: Format, source: &str, filename: &str) -> String {
+        match format {
+            Format::Human => self.render_human(source, filename),
+            Format::Plain => self.render_plain(source, filename),
+        }
+    }
+
+    fn render_human(&self, source: &str, filename: &str) -> String {
+        let mut labels = vec![(&self.primary, true)];
+        labels.extend(self.secondary.iter().map(|label| (label, false)));
+        labels.sort_by_key(|(label, _)| label.span.start);
+        let mut lines = labels
+            .iter()
+            .map(|(label, _)| line_col(source, label.span.start).0)
+            .collect::<Vec<_>>();
+        lines.sort_unstable();
+        lines.dedup();
+        let gutter = lines.last().map_or(1, |line| (line + 1).to_string().len());
+        let pad = " ".repeat(gutter);
+
+        let mut out = String::new();
+        let (line, col) = line_col(source, self.primary.span.start);
+        let _ = writeln!(out, "{}: {}", self.severity, self.message);
+        let _ = writeln!(out, "{}--> {}:{}:{}", pad, filename, line + 1, col + 1);
+        let _ = writeln!(out, "{} |", pad);
+        for line in lines {
+            let text = source.lines().nth(line).unwrap_or("");
+            let _ = writeln!(out, "{:>width$} | {}", line + 1, text, width = gutter);
+            for (label, primary) in &labels {
+                let (start_line, start_col) = line_col(source, label.span.start);
+                if start_line != line {
+                    continue;
+                }
+                // Multi-line spans are underlined up to the end of their
+                // first line.
+                let (end_line, end_col) = line_col(source, label.span.end);
+                let end_col = if end_line == line {
+                    end_col
+                } else {
+                    text.chars().count()
+                };
+                let width = end_col.saturating_sub(start_col).max(1);
+                let marker = if *primary { "^" } else { "-" };
+                let _ = writeln!(
+                    out,
+                    "{} | {}{} {}",
+                    pad,
+                    " ".repeat(start_col),
+                    marker.repeat(width),
+                    label.message
+                );
+            }
+        }
+        if !self.notes.is_empty() {
+            let _ = writeln!(out, "{} |", pad);
+        }
+        for note in &self.notes {
+            let _ = writeln!(out, "{} = note: {}", pad, note);
+        }
+        out
+    }
+
+    fn render_plain(&self, source: &str, filename: &str) -> String {
+        let mut out = String::new();
+        let (line, col) = line_col(source, self.primary.span.start);
+        let _ = write!(
+            out,
+            "{}:{}:{}: {}: {}",
+            filename,
+            line + 1,
+            col + 1,
+            self.severity,
+            self.message
+        );
+        if !self.primary.message.is_empty() {
+            let _ = write!(out, ": {}", self.primary.message);
+        }
+        out.push('\n');
+        for label in &self.secondary {
+            let (line, col) = line_col(source, label.span.start);
+            let _ = writeln!(
+                out,
+                "{}:{}:{}: note: {}",
+                filename,
+                line + 1,
+                col + 1,
+                label.message
+            );
+        }
+        for note in &self.notes {
+            let _ = writeln!(out, "{}:{}:{}: note: {}", filename, line + 1, col + 1, note);
+        }
+        out
+    }
+}
+
+/// The zero-based line and (character) column of a byte offset. An offset
+/// inside a character counts as the start of that character.
+fn line_col(source: &str, offset: usize) -> (usize, usize) {
+    let mut offset = offset.min(source.len());
+    while !source.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    let before = &source[..offset];
+    let line = before.matches('\n').count();
+    let line_start = before.rfind('\n').map_or(0, |idx| idx + 1);
+    (line, before[line_start..].chars().count())
+}
+
+fn pp_ty(ty: &Ty) -> String {
+    let arena = pretty::Arena::new();
+    pp_core_ty(ty.clone(), &arena)
+        .into_doc()
+        .pretty(80)
+        .to_string()
+}
+
+impl Diagnostic {
+    /// Convert an error from parsing `source`, which is needed to find where
+    /// an invalid token ends.
+    pub fn from_parse_error<T: fmt::Display, E: fmt::Display>(
+        err: ParseError<usize, T, E>,
+        source: &str,
+    ) -> Self {
+        let expected_note = |expected: &[String]| match expected {
+            [] => None,
+            [one] => Some(format!("expected {}", one)),
+            many => Some(format!("expected one of {}", many.join(", "))),
+        };
+        let (diag, expected) = match err {
+            ParseError::InvalidToken { location } => {
+                let len = source
+                    .get(location..)
+                    .and_then(|rest| rest.chars().next())
+                    .map_or(1, char::len_utf8);
+                (
+                    Diagnostic::error(
+                        "invalid token",
+                        Label::new(Span::new(location, location + len), "not recognised"),
+                    ),
+                    None,
+                )
+            }
+            ParseError::UnrecognizedEOF { location, expected } => (
+                Diagnostic::error(
+                    "unexpected end of input",
+                    Label::new(Span::new(location, location), "input ends here"),
+                ),
+                expected_note(&expected),
+            ),
+            ParseError::UnrecognizedToken {
+                token: (l, tok, r),
+                expected,
+            } => (
+                Diagnostic::error(
+                    format!("unexpected token `{}`", tok),
+                    Label::new(Span::new(l, r), "unexpected token"),
+                ),
+                expected_note(&expected),
+            ),
+            ParseError::ExtraToken { token: (l, tok, r) } => (
+                Diagnostic::error(
+                    format!("unexpected extra token `{}`", tok),
+                    Label::new(Span::new(l, r), "expected end of input"),
+                ),
+                None,
+            ),
+            ParseError::User { error } => (
+                Diagnostic::error(error.to_string(), Label::new(Span::new(0, 0), "")),
+                None,
+            ),
+        };
+        match expected {
+            Some(note) => diag.with_note(note),
+            None => diag,
+        }
+    }
+}
+
+impl From<LowerError> for Diagnostic {
+    fn from(err: LowerError) -> Self {
+        match err.kind {
+            LowerErrorKind::DuplicateLabel(_) => {
+                return Diagnostic::error(err.to_string(), Label::new(err.span, "in this record"));
+            }
+            LowerErrorKind::KindMismatch(_) => {
+                return Diagnostic::error(err.to_string(), Label::new(err.span, "bound here"))
+                    .with_note("a type variable that ends a record or variant type is a row, and cannot be used as a type");
+            }
+            LowerErrorKind::NegativeOccurrence(_) => {
+                return Diagnostic::error(err.to_string(), Label::new(err.span, "in this recursive type"))
+                    .with_note("the variable of a recursive type cannot appear to the left of an arrow, or terms that never terminate could be written without `fix`");
+            }
+            LowerErrorKind::NegativeData(_) => {
+                return Diagnostic::error(err.to_string(), Label::new(err.span, "in this field"))
+                    .with_note("a data type cannot appear to the left of an arrow in its own constructors, or terms that never terminate could be written without `fix`");
+            }
+            LowerErrorKind::Arity(..) => {
+                return Diagnostic::error(err.to_string(), Label::new(err.span, "used here"));
+            }
+            LowerErrorKind::DuplicateBinder(_) => {
+                return Diagnostic::error(
+                    err.to_string(),
+                    Label::new(err.span, "bound again here"),
+                );
+            }
+            LowerErrorKind::Redeclared(_) => {
+                return Diagnostic::error(
+                    err.to_string(),
+                    Label::new(err.span, "declared again here"),
+                );
+            }
+            _ => (),
+        }
+        let suggestions = err.suggestions();
+        let label = match suggestions.first() {
+            Some(best) => format!("not found in this scope; did you mean `{}`?", best),
+            None => "not found in this scope".to_string(),
+        };
+        let mut diag = Diagnostic::error(err.to_string(), Label::new(err.span, label));
+        if suggestions.len() > 1 {
+            diag = diag.with_note(format!(
+                "similarly named: {}",
+                quoted_list(&suggestions[1..])
+            ));
+        }
+        if err.in_scope.is_empty() {
+            diag.with_note("nothing of this sort is in scope here")
+        } else {
+            diag.with_note(format!("in scope here: {}", quoted_list(&err.in_scope)))
+        }
+    }
+}
+
+fn quoted_list<S: AsRef<str>>(names: &[S]) -> String {
+    names
+        .iter()
+        .map(|name| format!("`{}`", name.as_ref()))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+impl From<TypeError> for Diagnostic {
+    fn from(err: TypeError) -> Self {
+        let message = err.to_string();
+        match err.kind {
+            TypeErrorKind::NotSubtype(sub, sup) => {
+                let diag = Diagnostic::error(
+                    message,
+                    Label::new(err.span, format!("this has type `{}`", pp_ty(&sub))),
+                );
+                // Point at where the expected type came from, if that is
+                // somewhere else.
+                if sup.span.end <= err.span.start || sup.span.start >= err.span.end {
+                    diag.with_secondary(Label::new(
+                        sup.span,
+                        format!("expected `{}` because of this", pp_ty(&sup)),
+                    ))
+                } else {
+                    diag
+                }
+            }
+            TypeErrorKind::OutOfBound(_, bound) => Diagnostic::error(
+                message,
+                Label::new(err.span, "this instantiates a bounded quantifier"),
+            )
+            .with_secondary(Label::new(bound.span, "the bound is given here")),
+            TypeErrorKind::Occurs(..) => Diagnostic::error(
+                message,
+                Label::new(err.span, "cannot construct an infinite type"),
+            ),
+            TypeErrorKind::UnboundVar => {
+                Diagnostic::error(message, Label::new(err.span, "not in context"))
+            }
+            TypeErrorKind::IllFormedAnn(_) => Diagnostic::error(
+                message,
+                Label::new(err.span, "not well-formed in this context"),
+            ),
+            TypeErrorKind::NotMonotype(_) => {
+                Diagnostic::error(message, Label::new(err.span, "expected a monotype")).with_note(
+                    "type application cannot instantiate a quantifier with a polymorphic type",
+                )
+            }
+            TypeErrorKind::NotAFunction(ty) => Diagnostic::error(
+                message,
+                Label::new(err.span, format!("this has type `{}`", pp_ty(&ty))),
+            )
+            .with_note("only terms with function types can be applied to arguments"),
+            TypeErrorKind::NotPolymorphic(ty) => Diagnostic::error(
+                message,
+                Label::new(err.span, format!("this has type `{}`", pp_ty(&ty))),
+            )
+            .with_note("only terms with forall types can be applied to type arguments"),
+            TypeErrorKind::RowQuantifier(ty) => Diagnostic::error(
+                message,
+                Label::new(err.span, format!("this has type `{}`", pp_ty(&ty))),
+            )
+            .with_note("it quantifies over a row, which is only ever inferred"),
+            TypeErrorKind::KindMismatch(..) => Diagnostic::error(
+                message,
+                Label::new(err.span, "this type argument has the wrong kind"),
+            ),
+            TypeErrorKind::NoSuchComponent(ty, _)
+            | TypeErrorKind::TupleArity(ty, _)
+            | TypeErrorKind::NoSuchField(ty, _)
+            | TypeErrorKind::NotRecursive(ty) => {
+                let unknown = matches!(ty.kind, TyKind::ExstVar(_));
+                let diag = Diagnostic::error(
+                    message,
+                    Label::new(err.span, format!("this has type `{}`", pp_ty(&ty))),
+                );
+                if unknown {
+                    diag.with_note("the type of this term is not known yet; try annotating it")
+                } else {
+                    diag
+                }
+            }
+            TypeErrorKind::NotASum(ty) => Diagnostic::error(
+                message,
+                Label::new(err.span, format!("this has type `{}`", pp_ty(&ty))),
+            )
+            .with_note("only terms with sum types can be scrutinised by `case`"),
+            TypeErrorKind::NotExistential(_) => Diagnostic::error(
+                message,
+                Label::new(err.span, "not an existential type"),
+            )
+            .with_note("only terms with existential types can be unpacked, and packed terms must be annotated with one"),
+            TypeErrorKind::EscapingTyVar(_) => Diagnostic::error(
+                message,
+                Label::new(err.span, "the type of this mentions the abstract type"),
+            )
+            .with_note("the abstract type is only in scope in the body of the `unpack`"),
+            TypeErrorKind::UnannotatedFix => Diagnostic::error(
+                message,
+                Label::new(err.span, "type annotation needed"),
+            )
+            .with_note("annotate the recursive variable, as in `fix f : T. e` or `let rec f : T = e in ...`"),
+            TypeErrorKind::UnannotatedFold => Diagnostic::error(
+                message,
+                Label::new(err.span, "type annotation needed"),
+            )
+            .with_note("annotate it with the recursive type, as in `(fold e : mu 'a. T)`"),
+            TypeErrorKind::CtorMismatch(_, ty) => Diagnostic::error(
+                message,
+                Label::new(err.span, format!("this is matched against `{}`", pp_ty(&ty))),
+            ),
+            TypeErrorKind::NonExhaustive(_) => Diagnostic::error(
+                message,
+                Label::new(err.span, "not every value of this is matched"),
+            )
+            .with_note("add an arm for it, or one with a `_` pattern"),
+            TypeErrorKind::UnreachableArm => Diagnostic::error(
+                message,
+                Label::new(err.span, "the arms before this match everything it does"),
+            ),
+            TypeErrorKind::UnknownEvar(_)
+            | TypeErrorKind::UnknownUvar(_)
+            | TypeErrorKind::UnknownCtor(_) => {
+                Diagnostic::error(message, Label::new(err.span, "while checking this"))
+                    .with_note("this is a bug in the typechecker")
+            }
+        }
+    }
+}
+
+impl From<KindError> for Diagnostic {
+    fn from(err: KindError) -> Self {
+        let message = err.to_string();
+        match err.kind {
+            KindErrorKind::Mismatch(..) => {
+                Diagnostic::error(message, Label::new(err.span, "this has the wrong kind"))
+            }
+            KindErrorKind::NotAnOperator(_) => Diagnostic::error(
+                message,
+                Label::new(err.span, "this is applied to an argument"),
+            )
+            .with_note("only type operators can be applied to arguments; a type variable standing for one must be annotated with its kind, as in `('f : * -> *) => T`"),
+            KindErrorKind::IllFormed => {
+                Diagnostic::error(message, Label::new(err.span, "while checking this"))
+                    .with_note("this is a bug in the kind checker")
+            }
+        }
+    }
+}
+
+impl From<EncodeError> for Diagnostic {
+    fn from(err: EncodeError) -> Self {
+        let message = err.to_string();
+        match err.kind {
+            EncodeErrorKind::Nested(_) => Diagnostic::error(
+                message,
+                Label::new(err.span, "in this field"),
+            )
+            .with_note("a data type can only be encoded if each field is either the data type itself, a function returning it, or a type that doesn't mention it"),
+            EncodeErrorKind::Irregular(_) => Diagnostic::error(
+                message,
+                Label::new(err.span, "in this field"),
+            )
+            .with_note("encoding a data type whose parameters change in its fields needs a fold that is polymorphic in its result, which System F cannot express"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grammar::TermParser;
+
+    #[test]
+    fn invalid_tokens_end_at_a_char_boundary() {
+        let source = "λx. x";
+        let err = TermParser::new().parse(source).unwrap_err();
+        let diag = Diagnostic::from_parse_error(err, source);
+        assert_eq!(diag.primary.span, Span::new(0, 'λ'.len_utf8()));
+        assert!(diag
+            .render(Format::Human, source, "<stdin>")
+            .contains("1 | λx. x\n  | ^ not recognised"));
+        assert!(diag
+            .render(Format::Plain, source, "<stdin>")
+            .starts_with("<stdin>:1:1: error: invalid token"));
+    }
+
+    #[test]
+    fn offsets_inside_a_character_count_as_its_start() {
+        assert_eq!(line_col("aλb", 2), (0, 1));
+        assert_eq!(line_col("a\nλb", 4), (1, 1));
+    }
+}
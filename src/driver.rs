@@ -0,0 +1,94 @@
+//! Running the whole pipeline over a program's source.
+
+use crate::ast::core::{DeclKind, Program, Synonym};
+use crate::ctx::TyCtxt;
+use crate::diagnostics::Diagnostic;
+use crate::encode::{Encoder, Encoding};
+use crate::grammar::ProgramParser;
+use crate::kindck;
+use crate::typeck::{self, CheckedDef};
+
+/// Options controlling how source is interpreted.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Options {
+    /// Universally quantify the free type variables of signatures and
+    /// annotations, rather than reporting them as unbound.
+    pub implicit_forall: bool,
+}
+
+/// Parse, lower, kind check and typecheck a program, returning the name of every
+/// definition in order, along with what checking it found out, and then the
+/// type synonyms it declares, for displaying types with.
+#[allow(clippy::type_complexity)]
+pub fn check_program(
+    source: &str,
+    options: Options,
+) -> Result<(Vec<(String, CheckedDef)>, Vec<Synonym>), Vec<Diagnostic>> {
+    let program = lower_program(source, options)?;
+    let mut ctx = TyCtxt::default();
+    let defs = typeck::check_program(&mut ctx, &program)
+        .map_err(|errs| errs.into_iter().map(Diagnostic::from).collect::<Vec<_>>())?;
+    let names = program.decls.iter().filter_map(|decl| match &decl.kind {
+        DeclKind::Def(name, _, _) => Some(name.clone()),
+        DeclKind::Data(_) | DeclKind::Type(_) => None,
+    });
+    let synonyms = program
+        .decls
+        .iter()
+        .filter_map(|decl| match &decl.kind {
+            DeclKind::Type(synonym) => Some(synonym.clone()),
+            DeclKind::Def(..) | DeclKind::Data(_) => None,
+        })
+        .collect();
+    Ok((names.zip(defs).collect(), synonyms))
+}
+
+/// Parse, lower and typecheck a program, and then encode each of its data
+/// types in System F, returning their names and encodings in order. The
+/// encodings are checked by the typechecker too.
+pub fn encode_program(
+    source: &str,
+    options: Options,
+) -> Result<Vec<(String, Encoding)>, Vec<Diagnostic>> {
+    let program = lower_program(source, options)?;
+    let mut ctx = TyCtxt::default();
+    typeck::check_program(&mut ctx, &program)
+        .map_err(|errs| errs.into_iter().map(Diagnostic::from).collect::<Vec<_>>())?;
+
+    let mut encoder = Encoder::default();
+    let mut encodings = Vec::new();
+    for decl in &program.decls {
+        if let DeclKind::Data(data) = &decl.kind {
+            let encoding = encoder
+                .encode(data, decl.span)
+                .map_err(|err| vec![Diagnostic::from(err)])?;
+            let terms = encoding
+                .ctors
+                .iter()
+                .map(|(_, term)| term)
+                .chain(std::iter::once(&encoding.fold));
+            for term in terms {
+                let mut ctx = TyCtxt::default();
+                typeck::infer(&mut ctx, term).map_err(|err| vec![Diagnostic::from(err)])?;
+            }
+            encodings.push((data.name.clone(), encoding));
+        }
+    }
+    Ok(encodings)
+}
+
+/// Parse and lower a program, and kind check the result.
+fn lower_program(source: &str, options: Options) -> Result<Program, Vec<Diagnostic>> {
+    let mut parsed = ProgramParser::new()
+        .parse(source)
+        .map_err(|err| vec![Diagnostic::from_parse_error(err, source)])?;
+    if options.implicit_forall {
+        parsed.quantify_free_ty_vars();
+    }
+    let program = parsed
+        .into_core()
+        .map_err(|errs| errs.into_iter().map(Diagnostic::from).collect::<Vec<_>>())?;
+    kindck::check_program(&program)
+        .map_err(|errs| errs.into_iter().map(Diagnostic::from).collect::<Vec<_>>())?;
+    Ok(program)
+}
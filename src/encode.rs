@@ -0,0 +1,518 @@
+//! Böhm–Berarducci (or Church) encodings of data types, which turn a `data`
+//! declaration into plain System F: a type, a term for each constructor and
+//! a fold, none of which mention data types at all.
+//!
+//! A data type is encoded as the type of its own fold. For example,
+//! `data List 'a = Nil | Cons 'a (List 'a);` becomes
+//! `'r => 'r -> ('a -> 'r -> 'r) -> 'r`, with `Nil = \n c. n`,
+//! `Cons = \x xs n c. c x (xs n c)` and `foldList = \n c xs. xs n c`. A data
+//! type with no constructors is encoded as `never`, and its fold as
+//! `absurd`.
+//!
+//! The encodings are built with universal variables standing for the
+//! parameters and the result type, which are only closed over once a type is
+//! complete, so that nothing has to be shifted. The terms are annotated with
+//! their types, and instantiate the foralls in them by inference, so they
+//! need no type abstractions or applications either.
+
+use std::fmt;
+
+use crate::ast::core::{DataDecl, Kind, Term, TermKind, Ty, TyKind};
+use crate::span::Span;
+
+/// An error produced when a data type has no encoding, along with the span
+/// of the field responsible.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EncodeError {
+    pub kind: EncodeErrorKind,
+    pub span: Span,
+}
+
+/// The kind of an [`EncodeError`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EncodeErrorKind {
+    /// The data type occurs in a field other than as the field itself or the
+    /// result of a function, so folding over the field would need a map
+    /// over the type containing it.
+    Nested(String),
+    /// The data type is applied to something other than its own parameters
+    /// in a field, so its fold would have to be polymorphic in the result.
+    Irregular(String),
+}
+
+impl EncodeError {
+    pub fn new(kind: EncodeErrorKind, span: Span) -> Self {
+        Self { kind, span }
+    }
+}
+
+impl fmt::Display for EncodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.kind.fmt(f)
+    }
+}
+
+impl fmt::Display for EncodeErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EncodeErrorKind::Nested(name) => write!(
+                f,
+                "cannot encode `{}`, as it occurs nested inside another type",
+                name
+            ),
+            EncodeErrorKind::Irregular(name) => write!(
+                f,
+                "cannot encode `{}`, as it is applied to something other than its parameters",
+                name
+            ),
+        }
+    }
+}
+
+impl std::error::Error for EncodeError {}
+
+/// The encoding of a data type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Encoding {
+    /// The encoded type, quantified over the data type's parameters.
+    pub ty: Ty,
+    /// The name and encoding of each constructor, in the order they were
+    /// declared, annotated with their types.
+    pub ctors: Vec<(String, Term)>,
+    /// The fold, annotated with its type, which takes a function for each
+    /// constructor and then the term to fold over.
+    pub fold: Term,
+}
+
+/// Encodes data types in the order they were declared, so that the fields
+/// of each can refer to the ones before it.
+#[derive(Debug, Default)]
+pub struct Encoder {
+    /// The name of each data type encoded so far, and its encoded type, with
+    /// the parameters bound as in the data type's fields.
+    encoded: Vec<(String, Ty)>,
+}
+
+impl Encoder {
+    /// Encode `data`, declared at `span`. Any data type its fields refer to,
+    /// other than itself, must have been encoded already.
+    pub fn encode(&mut self, data: &DataDecl, span: Span) -> Result<Encoding, EncodeError> {
+        let arity = data.params.len();
+        let params = (0..arity)
+            .map(|uvar| Ty::new(TyKind::UnivVar(uvar), span))
+            .collect::<Vec<_>>();
+        let res = Ty::new(TyKind::UnivVar(arity), span);
+        let mut fresh = arity + 1;
+        let ctors = data
+            .ctors
+            .iter()
+            .map(|(name, fields)| {
+                let fields = fields
+                    .iter()
+                    .map(|field| field.clone().instantiate(&params).normalize())
+                    .collect::<Vec<_>>();
+                (name, fields)
+            })
+            .collect::<Vec<_>>();
+
+        // Each constructor becomes a function of its fields, and then of a
+        // function for each constructor, that calls the one for itself with
+        // the fields, folding over those of the data type itself.
+        let cases = ctors.len();
+        let mut terms = Vec::new();
+        for (idx, (_, fields)) in ctors.iter().enumerate() {
+            let args = fields
+                .iter()
+                .enumerate()
+                .map(|(field_idx, field)| {
+                    let var = cases + fields.len() - 1 - field_idx;
+                    self.fold_field(data, &params, field, var, 0, cases)
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            let body = appl(var(cases - 1 - idx, span), args);
+            terms.push(lambdas(fields.len() + cases, body));
+        }
+
+        let case_tys = ctors
+            .iter()
+            .map(|(_, fields)| {
+                let fields = fields
+                    .iter()
+                    .map(|field| self.encode_ty(field, &data.name, &res, &mut fresh))
+                    .collect::<Vec<_>>();
+                arrows(fields, res.clone())
+            })
+            .collect::<Vec<_>>();
+        // A data type with no constructors would be encoded as `'r => 'r`,
+        // which has no terms either, but is only empty by parametricity.
+        let encoded = if cases == 0 {
+            Ty::new(TyKind::Never, span)
+        } else {
+            Ty::new(
+                TyKind::Forall(
+                    Kind::Type,
+                    None,
+                    Box::new(arrows(case_tys.clone(), res.clone()).close(arity)),
+                ),
+                span,
+            )
+        };
+
+        let ctors = ctors
+            .iter()
+            .zip(terms)
+            .map(|((name, fields), term)| {
+                let fields = fields
+                    .iter()
+                    .map(|field| self.encode_ty(field, &data.name, &encoded, &mut fresh))
+                    .collect::<Vec<_>>();
+                let ty = foralls(arrows(fields, encoded.clone()), &data.params);
+                (name.to_string(), ann(term, ty))
+            })
+            .collect();
+
+        // The fold just passes the functions on to the term it folds over,
+        // and there is nothing to fold over a `never`.
+        let fold = if cases == 0 {
+            Term::new(TermKind::Absurd, span)
+        } else {
+            let body = appl(
+                var(0, span),
+                (0..cases).map(|idx| var(cases - idx, span)).collect(),
+            );
+            lambdas(cases + 1, body)
+        };
+        let fold_ty = arrows(
+            case_tys
+                .into_iter()
+                .chain(std::iter::once(encoded.clone()))
+                .collect(),
+            res,
+        );
+        let fold_kinds = data
+            .params
+            .iter()
+            .cloned()
+            .chain(std::iter::once(Kind::Type))
+            .collect::<Vec<_>>();
+        let fold = ann(fold, foralls(fold_ty, &fold_kinds));
+
+        let ty = foralls(encoded.clone(), &data.params);
+        self.encoded
+            .push((data.name.clone(), strip_foralls(ty.clone(), arity)));
+        Ok(Encoding { ty, ctors, fold })
+    }
+
+    /// The term that folds over the field of type `field`, which is bound to
+    /// the term variable `var` outside of `depth` more lambdas, given the
+    /// functions for each of the `cases` constructors, the innermost of which
+    /// are bound just outside of the same lambdas.
+    fn fold_field(
+        &self,
+        data: &DataDecl,
+        params: &[Ty],
+        field: &Ty,
+        var: usize,
+        depth: usize,
+        cases: usize,
+    ) -> Result<Term, EncodeError> {
+        let span = field.span;
+        // The field applied to the arguments bound by the lambdas.
+        let applied = appl(
+            self::var(var + depth, span),
+            (0..depth).rev().map(|idx| self::var(idx, span)).collect(),
+        );
+        if !mentions(field, &data.name) {
+            return Ok(applied);
+        }
+        match &field.kind {
+            TyKind::Data(name, args) if name == &data.name => {
+                if args != params {
+                    return Err(EncodeError::new(
+                        EncodeErrorKind::Irregular(data.name.clone()),
+                        span,
+                    ));
+                }
+                Ok(appl(
+                    applied,
+                    (0..cases)
+                        .map(|idx| self::var(cases - 1 - idx + depth, span))
+                        .collect(),
+                ))
+            }
+            TyKind::Arrow(inp, out) if !mentions(inp, &data.name) => {
+                let body = self.fold_field(data, params, out, var, depth + 1, cases)?;
+                Ok(lambdas(1, body))
+            }
+            _ => Err(EncodeError::new(
+                EncodeErrorKind::Nested(data.name.clone()),
+                span,
+            )),
+        }
+    }
+
+    /// Replace the data type `name` in `ty` with `with`, and every other data
+    /// type with its encoding. `fresh` is the next universal variable that
+    /// isn't in use.
+    fn encode_ty(&self, ty: &Ty, name: &str, with: &Ty, fresh: &mut usize) -> Ty {
+        let span = ty.span;
+        let binder = |body: &Ty, fresh: &mut usize| {
+            let uvar = *fresh;
+            *fresh += 1;
+            let body = body.clone().open(&Ty::new(TyKind::UnivVar(uvar), span));
+            Box::new(self.encode_ty(&body, name, with, fresh).close(uvar))
+        };
+        match &ty.kind {
+            TyKind::Bool
+            | TyKind::Unit
+            | TyKind::Top
+            | TyKind::Never
+            | TyKind::Var(_)
+            | TyKind::UnivVar(_)
+            | TyKind::ExstVar(_) => ty.clone(),
+            TyKind::Forall(kind, bound, body) => Ty::new(
+                TyKind::Forall(
+                    kind.clone(),
+                    bound
+                        .as_ref()
+                        .map(|bound| Box::new(self.encode_ty(bound, name, with, fresh))),
+                    binder(body, fresh),
+                ),
+                span,
+            ),
+            TyKind::Lam(kind, body) => {
+                Ty::new(TyKind::Lam(kind.clone(), binder(body, fresh)), span)
+            }
+            TyKind::App(func, arg) => Ty::new(
+                TyKind::App(
+                    Box::new(self.encode_ty(func, name, with, fresh)),
+                    Box::new(self.encode_ty(arg, name, with, fresh)),
+                ),
+                span,
+            ),
+            TyKind::Mu(body) => Ty::new(TyKind::Mu(binder(body, fresh)), span),
+            TyKind::Exists(body) => Ty::new(TyKind::Exists(binder(body, fresh)), span),
+            TyKind::Arrow(inp, out) => Ty::new(
+                TyKind::Arrow(
+                    Box::new(self.encode_ty(inp, name, with, fresh)),
+                    Box::new(self.encode_ty(out, name, with, fresh)),
+                ),
+                span,
+            ),
+            TyKind::Sum(left, right) => Ty::new(
+                TyKind::Sum(
+                    Box::new(self.encode_ty(left, name, with, fresh)),
+                    Box::new(self.encode_ty(right, name, with, fresh)),
+                ),
+                span,
+            ),
+            TyKind::Intersection(left, right) => Ty::new(
+                TyKind::Intersection(
+                    Box::new(self.encode_ty(left, name, with, fresh)),
+                    Box::new(self.encode_ty(right, name, with, fresh)),
+                ),
+                span,
+            ),
+            TyKind::Union(left, right) => Ty::new(
+                TyKind::Union(
+                    Box::new(self.encode_ty(left, name, with, fresh)),
+                    Box::new(self.encode_ty(right, name, with, fresh)),
+                ),
+                span,
+            ),
+            TyKind::Product(tys) => Ty::new(
+                TyKind::Product(
+                    tys.iter()
+                        .map(|ty| self.encode_ty(ty, name, with, fresh))
+                        .collect(),
+                ),
+                span,
+            ),
+            TyKind::Record(row) => Ty::new(
+                TyKind::Record(row.clone().map(|ty| self.encode_ty(&ty, name, with, fresh))),
+                span,
+            ),
+            TyKind::Variant(row) => Ty::new(
+                TyKind::Variant(row.clone().map(|ty| self.encode_ty(&ty, name, with, fresh))),
+                span,
+            ),
+            TyKind::Row(row) => Ty::new(
+                TyKind::Row(row.clone().map(|ty| self.encode_ty(&ty, name, with, fresh))),
+                span,
+            ),
+            TyKind::Data(data, _) if data == name => with.clone(),
+            TyKind::Data(data, args) => {
+                let args = args
+                    .iter()
+                    .map(|ty| self.encode_ty(ty, name, with, fresh))
+                    .collect::<Vec<_>>();
+                let (_, encoded) = self
+                    .encoded
+                    .iter()
+                    .find(|(encoded, _)| encoded == data)
+                    .expect("data types are encoded in order");
+                encoded.clone().instantiate(&args).normalize()
+            }
+        }
+    }
+}
+
+/// Whether the data type `name` occurs in `ty`.
+fn mentions(ty: &Ty, name: &str) -> bool {
+    match &ty.kind {
+        TyKind::Bool
+        | TyKind::Unit
+        | TyKind::Top
+        | TyKind::Never
+        | TyKind::Var(_)
+        | TyKind::UnivVar(_)
+        | TyKind::ExstVar(_) => false,
+        TyKind::Forall(_, bound, body) => {
+            bound.as_ref().is_some_and(|bound| mentions(bound, name)) || mentions(body, name)
+        }
+        TyKind::Lam(_, body) | TyKind::Mu(body) | TyKind::Exists(body) => mentions(body, name),
+        TyKind::Arrow(ty1, ty2)
+        | TyKind::Sum(ty1, ty2)
+        | TyKind::Intersection(ty1, ty2)
+        | TyKind::Union(ty1, ty2)
+        | TyKind::App(ty1, ty2) => mentions(ty1, name) || mentions(ty2, name),
+        TyKind::Product(tys) => tys.iter().any(|ty| mentions(ty, name)),
+        TyKind::Record(row) | TyKind::Variant(row) | TyKind::Row(row) => {
+            row.tys().any(|ty| mentions(ty, name))
+        }
+        TyKind::Data(data, args) => data == name || args.iter().any(|ty| mentions(ty, name)),
+    }
+}
+
+fn var(idx: usize, span: Span) -> Term {
+    Term::new(TermKind::Var(idx), span)
+}
+
+fn appl(func: Term, args: Vec<Term>) -> Term {
+    args.into_iter().fold(func, |func, arg| {
+        let span = func.span;
+        Term::new(TermKind::Appl(Box::new(func), Box::new(arg)), span)
+    })
+}
+
+fn lambdas(count: usize, body: Term) -> Term {
+    (0..count).fold(body, |body, _| {
+        let span = body.span;
+        Term::new(TermKind::Lambda(None, Box::new(body)), span)
+    })
+}
+
+fn ann(term: Term, ty: Ty) -> Term {
+    let span = term.span;
+    Term::new(TermKind::Ann(Box::new(term), ty), span)
+}
+
+fn arrows(inps: Vec<Ty>, out: Ty) -> Ty {
+    inps.into_iter().rev().fold(out, |out, inp| {
+        let span = out.span;
+        Ty::new(TyKind::Arrow(Box::new(inp), Box::new(out)), span)
+    })
+}
+
+/// Quantify `ty` over the universal variables from 0 up, one of each kind
+/// in `kinds`, the first outermost.
+fn foralls(ty: Ty, kinds: &[Kind]) -> Ty {
+    kinds
+        .iter()
+        .enumerate()
+        .rev()
+        .fold(ty, |body, (uvar, kind)| {
+            let span = body.span;
+            Ty::new(
+                TyKind::Forall(kind.clone(), None, Box::new(body.close(uvar))),
+                span,
+            )
+        })
+}
+
+fn strip_foralls(ty: Ty, count: usize) -> Ty {
+    (0..count).fold(ty, |ty, _| match ty.kind {
+        TyKind::Forall(_, _, body) => *body,
+        _ => unreachable!("there is a forall for each parameter"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::core::DeclKind;
+    use crate::driver::{self, Options};
+    use crate::grammar::{ProgramParser, TypeParser};
+
+    /// Encode the data types in `source`, stopping at the first error.
+    fn encode(source: &str) -> Result<Vec<Encoding>, EncodeErrorKind> {
+        let program = ProgramParser::new()
+            .parse(source)
+            .unwrap()
+            .into_core()
+            .unwrap();
+        let mut encoder = Encoder::default();
+        program
+            .decls
+            .iter()
+            .filter_map(|decl| match &decl.kind {
+                DeclKind::Data(data) => Some(encoder.encode(data, decl.span)),
+                _ => None,
+            })
+            .collect::<Result<_, _>>()
+            .map_err(|err| err.kind)
+    }
+
+    fn ty(source: &str) -> Ty {
+        TypeParser::new()
+            .parse(source)
+            .unwrap()
+            .into_core()
+            .unwrap()
+    }
+
+    #[test]
+    fn data_types_are_encoded_as_their_folds() {
+        let source = "data List 'a = Nil | Cons 'a (List 'a);";
+        let encodings = encode(source).unwrap();
+        assert_eq!(
+            encodings[0].ty,
+            ty("'a => 'r => 'r -> ('a -> 'r -> 'r) -> 'r")
+        );
+        // The constructors and fold are checked against their annotations.
+        assert!(driver::encode_program(source, Options::default()).is_ok());
+    }
+
+    #[test]
+    fn functions_returning_the_data_type_are_folded_under_lambdas() {
+        let source = "data Tree = Leaf | Node (bool -> Tree);";
+        let encodings = encode(source).unwrap();
+        assert_eq!(
+            encodings[0].ty,
+            ty("'r => 'r -> ((bool -> 'r) -> 'r) -> 'r")
+        );
+        assert!(driver::encode_program(source, Options::default()).is_ok());
+    }
+
+    #[test]
+    fn empty_data_types_are_encoded_as_never() {
+        let encodings = encode("data Void;").unwrap();
+        assert_eq!(encodings[0].ty, ty("never"));
+        assert!(
+            matches!(encodings[0].fold.kind, TermKind::Ann(ref term, _) if term.kind == TermKind::Absurd)
+        );
+        assert!(driver::encode_program("data Void;", Options::default()).is_ok());
+    }
+
+    #[test]
+    fn nested_and_irregular_data_types_are_rejected() {
+        assert_eq!(
+            encode("data List 'a = Nil | Cons 'a (List 'a); data Rose = Node (List Rose);"),
+            Err(EncodeErrorKind::Nested("Rose".to_string()))
+        );
+        assert_eq!(
+            encode("data Nest 'a = Nil | Cons 'a (Nest ('a * 'a));"),
+            Err(EncodeErrorKind::Irregular("Nest".to_string()))
+        );
+    }
+}
@@ -0,0 +1,388 @@
+//! A call-by-value evaluator for the core language.
+//!
+//! Types play no part in evaluation, so they are erased: the bodies of type
+//! abstractions are evaluated straight away, and annotations and type
+//! applications are ignored. Terms are assumed to have typechecked, and
+//! evaluating one that has not may panic.
+
+use std::{fmt, rc::Rc};
+
+use crate::ast::core::{Pattern, PatternKind, Term, TermKind};
+
+#[derive(Clone, Debug)]
+pub enum Value {
+    Unit,
+    Bool(bool),
+    Tuple(Vec<Value>),
+    Record(Vec<(String, Value)>),
+    Inl(Box<Value>),
+    Inr(Box<Value>),
+    Variant(String, Box<Value>),
+    /// A constructor applied to all of its fields.
+    Data(String, Vec<Value>),
+    /// A constructor that takes the given number of fields, applied to
+    /// fewer of them.
+    Ctor(String, usize, Vec<Value>),
+    /// A lambda's body, along with the environment it was created in.
+    Closure(Env, Rc<Term>),
+    /// The body of a `fix`, along with the environment it was created in.
+    /// This is only ever bound to the recursive variable, and is unrolled
+    /// again each time that variable is used.
+    Fix(Env, Rc<Term>),
+    /// `absurd`, which can never be applied, as nothing has type `never`.
+    Absurd,
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Unit => f.write_str("()"),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Tuple(values) => {
+                f.write_str("(")?;
+                for (idx, value) in values.iter().enumerate() {
+                    if idx > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{}", value)?;
+                }
+                f.write_str(")")
+            }
+            Value::Record(fields) => {
+                f.write_str("{")?;
+                for (idx, (label, value)) in fields.iter().enumerate() {
+                    if idx > 0 {
+                        f.write_str(",")?;
+                    }
+                    write!(f, " {} = {}", label, value)?;
+                }
+                f.write_str(" }")
+            }
+            Value::Inl(value) => write!(f, "inl {}", value),
+            Value::Inr(value) => write!(f, "inr {}", value),
+            Value::Variant(label, value) => write!(f, "<{} = {}>", label, value),
+            Value::Data(name, values) => {
+                f.write_str(name)?;
+                for value in values {
+                    match value {
+                        Value::Data(_, fields) if !fields.is_empty() => write!(f, " ({})", value)?,
+                        _ => write!(f, " {}", value)?,
+                    }
+                }
+                Ok(())
+            }
+            Value::Closure(..) | Value::Ctor(..) | Value::Absurd => f.write_str("<function>"),
+            Value::Fix(..) => f.write_str("<fix>"),
+        }
+    }
+}
+
+/// The values of the term variables in scope. Variables are looked up by
+/// De Bruijn index, so the most recently bound one is at index 0.
+#[derive(Clone, Debug, Default)]
+pub struct Env {
+    vars: Vec<Value>,
+}
+
+impl Env {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, value: Value) {
+        self.vars.push(value);
+    }
+
+    pub fn get(&self, idx: usize) -> Option<&Value> {
+        self.vars.iter().rev().nth(idx)
+    }
+}
+
+/// Evaluate a closed, well-typed term.
+pub fn eval(term: &Term) -> Value {
+    eval_in(&Env::new(), term)
+}
+
+/// Evaluate a well-typed term whose free variables are bound in `env`.
+pub fn eval_in(env: &Env, term: &Term) -> Value {
+    match &term.kind {
+        TermKind::Unit => Value::Unit,
+        TermKind::Absurd => Value::Absurd,
+        TermKind::Bool(b) => Value::Bool(*b),
+        TermKind::Var(idx) => match env.get(*idx) {
+            Some(Value::Fix(env, body)) => unroll(env, body),
+            Some(value) => value.clone(),
+            None => panic!("unbound variable {} during evaluation", idx),
+        },
+        TermKind::Lambda(_, body) => Value::Closure(env.clone(), Rc::new((**body).clone())),
+        TermKind::Appl(func, arg) => {
+            let func = eval_in(env, func);
+            let arg = eval_in(env, arg);
+            apply(func, arg)
+        }
+        TermKind::Ann(term, _)
+        | TermKind::TypeAppl(term, _)
+        | TermKind::TyAbs(_, term)
+        | TermKind::Fold(term)
+        | TermKind::Unfold(term)
+        | TermKind::Pack(_, term, _) => eval_in(env, term),
+        TermKind::Let(_, val, body) | TermKind::Unpack(val, body) => {
+            let val = eval_in(env, val);
+            let mut env = env.clone();
+            env.push(val);
+            eval_in(&env, body)
+        }
+        TermKind::If(cond, then, els) => match eval_in(env, cond) {
+            Value::Bool(true) => eval_in(env, then),
+            Value::Bool(false) => eval_in(env, els),
+            value => panic!("`if` on non-boolean value {}", value),
+        },
+        TermKind::Fix(_, body) => unroll(env, &Rc::new((**body).clone())),
+        TermKind::Tuple(terms) => {
+            Value::Tuple(terms.iter().map(|term| eval_in(env, term)).collect())
+        }
+        TermKind::Proj(term, idx) => match eval_in(env, term) {
+            Value::Tuple(mut values) if *idx < values.len() => values.swap_remove(*idx),
+            value => panic!("projected component {} of value {}", idx, value),
+        },
+        TermKind::LetTuple(len, val, body) => match eval_in(env, val) {
+            Value::Tuple(values) if values.len() == *len => {
+                let mut env = env.clone();
+                for value in values {
+                    env.push(value);
+                }
+                eval_in(&env, body)
+            }
+            value => panic!("destructured value {} as a {}-tuple", value, len),
+        },
+        TermKind::Record(fields) => Value::Record(
+            fields
+                .iter()
+                .map(|(label, term)| (label.clone(), eval_in(env, term)))
+                .collect(),
+        ),
+        TermKind::Field(term, label) => match eval_in(env, term) {
+            Value::Record(fields) => match fields.into_iter().find(|(l, _)| l == label) {
+                Some((_, value)) => value,
+                None => panic!("accessed missing field `{}`", label),
+            },
+            value => panic!("accessed field `{}` of non-record value {}", label, value),
+        },
+        TermKind::Inl(term) => Value::Inl(Box::new(eval_in(env, term))),
+        TermKind::Inr(term) => Value::Inr(Box::new(eval_in(env, term))),
+        TermKind::Case(scrut, left, right) => {
+            let (value, branch) = match eval_in(env, scrut) {
+                Value::Inl(value) => (value, left),
+                Value::Inr(value) => (value, right),
+                value => panic!("`case` on non-sum value {}", value),
+            };
+            let mut env = env.clone();
+            env.push(*value);
+            eval_in(&env, branch)
+        }
+        TermKind::Variant(label, term) => {
+            Value::Variant(label.clone(), Box::new(eval_in(env, term)))
+        }
+        TermKind::VariantCase(scrut, arms) => match eval_in(env, scrut) {
+            Value::Variant(label, value) => match arms.iter().find(|(l, _)| *l == label) {
+                Some((_, arm)) => {
+                    let mut env = env.clone();
+                    env.push(*value);
+                    eval_in(&env, arm)
+                }
+                None => panic!("no `case` arm for label `{}`", label),
+            },
+            value => panic!("`case` on non-variant value {}", value),
+        },
+        TermKind::Ctor(name, 0) => Value::Data(name.clone(), Vec::new()),
+        TermKind::Ctor(name, arity) => Value::Ctor(name.clone(), *arity, Vec::new()),
+        TermKind::Match(scrut, arms) => {
+            let value = eval_in(env, scrut);
+            for (pat, arm) in arms {
+                let mut env = env.clone();
+                if bind(&mut env, pat, &value) {
+                    return eval_in(&env, arm);
+                }
+            }
+            panic!("no `match` arm for value {}", value)
+        }
+    }
+}
+
+/// If `pat` matches `value`, bind its variables in `env`, in order, and
+/// return true.
+fn bind(env: &mut Env, pat: &Pattern, value: &Value) -> bool {
+    match (&pat.kind, value) {
+        (PatternKind::Wildcard, _) => true,
+        (PatternKind::Var, _) => {
+            env.push(value.clone());
+            true
+        }
+        (PatternKind::Ctor(name, pats), Value::Data(ctor, values)) => {
+            name == ctor
+                && pats
+                    .iter()
+                    .zip(values)
+                    .all(|(pat, value)| bind(env, pat, value))
+        }
+        (PatternKind::Ctor(name, _), value) => {
+            panic!("matched constructor `{}` against value {}", name, value)
+        }
+    }
+}
+
+/// Evaluate the body of a `fix` with the recursive variable bound to the
+/// `fix` itself.
+fn unroll(env: &Env, body: &Rc<Term>) -> Value {
+    let mut inner = env.clone();
+    inner.push(Value::Fix(env.clone(), Rc::clone(body)));
+    eval_in(&inner, body)
+}
+
+/// Apply a function value to an argument.
+pub fn apply(func: Value, arg: Value) -> Value {
+    match func {
+        Value::Closure(mut env, body) => {
+            env.push(arg);
+            eval_in(&env, &body)
+        }
+        Value::Ctor(name, arity, mut values) => {
+            values.push(arg);
+            if values.len() == arity {
+                Value::Data(name, values)
+            } else {
+                Value::Ctor(name, arity, values)
+            }
+        }
+        Value::Absurd => panic!("applied `absurd` to value {}", arg),
+        value => panic!("applied non-function value {}", value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::core::DeclKind;
+    use crate::ctx::TyCtxt;
+    use crate::grammar::{ProgramParser, TermParser};
+    use crate::typeck;
+
+    /// Typecheck and evaluate a term, and show the result.
+    fn run(source: &str) -> String {
+        let term = TermParser::new()
+            .parse(source)
+            .unwrap()
+            .into_core()
+            .unwrap();
+        typeck::infer(&mut TyCtxt::default(), &term).unwrap();
+        eval(&term).to_string()
+    }
+
+    /// Typecheck a program and evaluate its last definition, which must not
+    /// refer to the others.
+    fn run_last(source: &str) -> String {
+        let program = ProgramParser::new()
+            .parse(source)
+            .unwrap()
+            .into_core()
+            .unwrap();
+        typeck::check_program(&mut TyCtxt::default(), &program).unwrap();
+        let body = program
+            .decls
+            .iter()
+            .rev()
+            .find_map(|decl| match &decl.kind {
+                DeclKind::Def(_, _, body) => Some(body),
+                _ => None,
+            })
+            .unwrap();
+        eval(body).to_string()
+    }
+
+    #[test]
+    fn conditionals_take_one_branch() {
+        assert_eq!(run("if true then () else ((\\x. x) ())"), "()");
+        assert_eq!(run("(\\b. if b then false else true) false"), "true");
+    }
+
+    #[test]
+    fn type_abstractions_are_erased() {
+        assert_eq!(run("(/\\'a. \\(x : 'a). x) [bool] true"), "true");
+    }
+
+    #[test]
+    fn fix_unrolls_on_each_use() {
+        assert_eq!(
+            run("let rec not : bool -> bool = \\b. if b then false else not true in not false"),
+            "false"
+        );
+    }
+
+    #[test]
+    fn tuples_are_built_and_taken_apart() {
+        assert_eq!(run("let (x, y) = (true, ()) in (y, x)"), "((), true)");
+        assert_eq!(run("(true, false, ()).1"), "false");
+    }
+
+    #[test]
+    fn case_takes_the_branch_for_its_injection() {
+        assert_eq!(
+            run("case (inr true : unit + bool) of inl x -> false | inr y -> y"),
+            "true"
+        );
+    }
+
+    #[test]
+    fn fields_are_looked_up_by_label() {
+        assert_eq!(run("{ x = true, y = () }.y"), "()");
+    }
+
+    #[test]
+    fn variants_are_matched_by_label() {
+        assert_eq!(
+            run("case <b = true> of { <a = x> -> false | <b = y> -> y }"),
+            "true"
+        );
+    }
+
+    #[test]
+    fn fold_and_unfold_are_erased() {
+        assert_eq!(
+            run("unfold (fold (inl () : unit + bool) : mu 'l. unit + bool)"),
+            "inl ()"
+        );
+    }
+
+    #[test]
+    fn pack_and_unpack_are_erased() {
+        assert_eq!(
+            run("unpack (pack [bool, (true, \\b. b)] as exists 'a. 'a * ('a -> bool)) as ['a, p] in p.1 p.0"),
+            "true"
+        );
+    }
+
+    #[test]
+    fn matches_take_the_first_arm_that_fits() {
+        assert_eq!(
+            run_last(
+                "data List 'a = Nil | Cons 'a (List 'a);
+                 def second : bool = match Cons true (Cons false Nil) with {
+                     Cons _ (Cons x _) -> x | _ -> true
+                 };"
+            ),
+            "false"
+        );
+        assert_eq!(
+            run_last(
+                "data Nat = Z | S Nat;
+                 def two : Nat = S (S Z);"
+            ),
+            "S (S Z)"
+        );
+    }
+
+    #[test]
+    fn absurd_is_a_function() {
+        assert_eq!(run("absurd"), "<function>");
+    }
+}
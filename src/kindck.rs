@@ -0,0 +1,391 @@
+//! Kind checking, which makes sure that type operators are only applied to
+//! arguments of the kinds they expect, and that everything else is used as a
+//! type. It runs over a whole program before the typechecker does, so that
+//! the typechecker can assume every type it is given is well-kinded.
+
+use std::fmt;
+
+use crate::ast::core::{DeclKind, Kind, Program, Row, Term, TermKind, Ty, TyKind};
+use crate::ctx::TyCtxt;
+use crate::span::Span;
+
+/// An error produced by the kind checker, along with the span of the type
+/// responsible.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KindError {
+    pub kind: KindErrorKind,
+    pub span: Span,
+}
+
+/// The kind of a [`KindError`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KindErrorKind {
+    /// A type of the second kind was used where one of the first was
+    /// expected.
+    Mismatch(Kind, Kind),
+    /// A type of this kind, which is not a type operator, was applied to an
+    /// argument.
+    NotAnOperator(Kind),
+    /// A type variable was not in scope, or a data type was not declared or
+    /// not given an argument for each of its parameters. Lowering never
+    /// produces these, so this indicates a bug.
+    IllFormed,
+}
+
+impl KindError {
+    pub fn new(kind: KindErrorKind, span: Span) -> Self {
+        Self { kind, span }
+    }
+}
+
+impl fmt::Display for KindError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.kind.fmt(f)
+    }
+}
+
+impl fmt::Display for KindErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KindErrorKind::Mismatch(expected, found) => write!(
+                f,
+                "expected a type of kind `{}`, found one of kind `{}`",
+                expected, found
+            ),
+            KindErrorKind::NotAnOperator(kind) => write!(
+                f,
+                "a type of kind `{}` cannot be applied to an argument",
+                kind
+            ),
+            KindErrorKind::IllFormed => write!(f, "ill-formed type"),
+        }
+    }
+}
+
+/// Work out the kind of `ty` in `ctx`, where `bound` holds the kinds of the
+/// variables bound outside of it, the innermost last.
+pub fn infer_kind(ctx: &TyCtxt, bound: &mut Vec<Kind>, ty: &Ty) -> Result<Kind, KindError> {
+    let span = ty.span;
+    let kind = match &ty.kind {
+        TyKind::Bool | TyKind::Unit | TyKind::Top | TyKind::Never => Kind::Type,
+        TyKind::Var(idx) => bound
+            .len()
+            .checked_sub(idx + 1)
+            .map(|idx| bound[idx].clone())
+            .ok_or_else(|| KindError::new(KindErrorKind::IllFormed, span))?,
+        TyKind::UnivVar(uvar) => ctx
+            .uvar_kind(*uvar)
+            .cloned()
+            .ok_or_else(|| KindError::new(KindErrorKind::IllFormed, span))?,
+        // Existentials only appear in types made by the typechecker, which
+        // keeps them at the right kinds itself.
+        TyKind::ExstVar(evar) => {
+            if !ctx.contains_evar(*evar) {
+                return Err(KindError::new(KindErrorKind::IllFormed, span));
+            }
+            ctx.evar_kind(*evar).unwrap_or(Kind::Type)
+        }
+        TyKind::Arrow(inp, out)
+        | TyKind::Sum(inp, out)
+        | TyKind::Intersection(inp, out)
+        | TyKind::Union(inp, out) => {
+            check_kind(ctx, bound, inp, &Kind::Type)?;
+            check_kind(ctx, bound, out, &Kind::Type)?;
+            Kind::Type
+        }
+        TyKind::Product(tys) => {
+            for ty in tys {
+                check_kind(ctx, bound, ty, &Kind::Type)?;
+            }
+            Kind::Type
+        }
+        TyKind::Record(row) | TyKind::Variant(row) => {
+            check_row(ctx, bound, row)?;
+            Kind::Type
+        }
+        TyKind::Row(row) => {
+            check_row(ctx, bound, row)?;
+            Kind::Row
+        }
+        // The bound is outside the scope of the variable it bounds.
+        TyKind::Forall(kind, upper, body) => {
+            if let Some(upper) = upper {
+                check_kind(ctx, bound, upper, kind)?;
+            }
+            bound.push(kind.clone());
+            let res = check_kind(ctx, bound, body, &Kind::Type);
+            bound.pop();
+            res?;
+            Kind::Type
+        }
+        TyKind::Mu(body) | TyKind::Exists(body) => {
+            bound.push(Kind::Type);
+            let res = check_kind(ctx, bound, body, &Kind::Type);
+            bound.pop();
+            res?;
+            Kind::Type
+        }
+        TyKind::Data(name, args) => {
+            let params = ctx
+                .get_data(name)
+                .map(|data| data.params.clone())
+                .filter(|params| params.len() == args.len())
+                .ok_or_else(|| KindError::new(KindErrorKind::IllFormed, span))?;
+            for (arg, kind) in args.iter().zip(&params) {
+                check_kind(ctx, bound, arg, kind)?;
+            }
+            Kind::Type
+        }
+        TyKind::Lam(kind, body) => {
+            bound.push(kind.clone());
+            let res = infer_kind(ctx, bound, body);
+            bound.pop();
+            Kind::Arrow(Box::new(kind.clone()), Box::new(res?))
+        }
+        TyKind::App(func, arg) => match infer_kind(ctx, bound, func)? {
+            Kind::Arrow(inp, out) => {
+                check_kind(ctx, bound, arg, &inp)?;
+                *out
+            }
+            kind => {
+                return Err(KindError::new(
+                    KindErrorKind::NotAnOperator(kind),
+                    func.span,
+                ))
+            }
+        },
+    };
+    Ok(kind)
+}
+
+/// Check that `ty` has kind `expected`.
+pub fn check_kind(
+    ctx: &TyCtxt,
+    bound: &mut Vec<Kind>,
+    ty: &Ty,
+    expected: &Kind,
+) -> Result<(), KindError> {
+    let found = infer_kind(ctx, bound, ty)?;
+    if &found == expected {
+        Ok(())
+    } else {
+        Err(KindError::new(
+            KindErrorKind::Mismatch(expected.clone(), found),
+            ty.span,
+        ))
+    }
+}
+
+fn check_row(ctx: &TyCtxt, bound: &mut Vec<Kind>, row: &Row) -> Result<(), KindError> {
+    for (_, ty) in &row.fields {
+        check_kind(ctx, bound, ty, &Kind::Type)?;
+    }
+    if let Some(tail) = &row.tail {
+        check_kind(ctx, bound, tail, &Kind::Row)?;
+    }
+    Ok(())
+}
+
+/// Kind check every type in a program: the signatures of definitions and
+/// the annotations in their bodies must be types, the fields of data types
+/// too, and the bodies of synonyms may be of any kind.
+///
+/// Every error is reported, not just the first.
+pub fn check_program(program: &Program) -> Result<(), Vec<KindError>> {
+    let mut ctx = TyCtxt::default();
+    let mut errors = Vec::new();
+    for decl in &program.decls {
+        match &decl.kind {
+            DeclKind::Def(_, ty, body) => {
+                errors.extend(check_kind(&ctx, &mut Vec::new(), ty, &Kind::Type).err());
+                check_term(&ctx, &mut Vec::new(), body, &mut errors);
+            }
+            // The data type is in scope in its own fields.
+            DeclKind::Data(data) => {
+                ctx.add_data(data.clone());
+                let mut bound = data.params.clone();
+                for (_, fields) in &data.ctors {
+                    for field in fields {
+                        errors.extend(check_kind(&ctx, &mut bound, field, &Kind::Type).err());
+                    }
+                }
+            }
+            DeclKind::Type(synonym) => {
+                let mut bound = synonym.params.clone();
+                errors.extend(infer_kind(&ctx, &mut bound, &synonym.body).err());
+            }
+        }
+    }
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+/// Kind check the types in a term, where `bound` holds the kinds of the type
+/// variables bound by the type abstractions (and `unpack`s) around it.
+fn check_term(ctx: &TyCtxt, bound: &mut Vec<Kind>, term: &Term, errors: &mut Vec<KindError>) {
+    let ty = |bound: &mut Vec<Kind>, ty: &Ty, errors: &mut Vec<KindError>| {
+        errors.extend(check_kind(ctx, bound, ty, &Kind::Type).err());
+    };
+    match &term.kind {
+        TermKind::Unit
+        | TermKind::Bool(_)
+        | TermKind::Absurd
+        | TermKind::Var(_)
+        | TermKind::Ctor(..) => (),
+        TermKind::Lambda(ann, body) | TermKind::Fix(ann, body) => {
+            if let Some(ann) = ann {
+                ty(bound, ann, errors);
+            }
+            check_term(ctx, bound, body, errors);
+        }
+        TermKind::Ann(term, ann) => {
+            check_term(ctx, bound, term, errors);
+            ty(bound, ann, errors);
+        }
+        // The argument's kind depends on the quantifier it instantiates, so
+        // the typechecker checks it.
+        TermKind::TypeAppl(term, arg) => {
+            check_term(ctx, bound, term, errors);
+            errors.extend(infer_kind(ctx, bound, arg).err());
+        }
+        TermKind::Let(ann, val, body) => {
+            if let Some(ann) = ann {
+                ty(bound, ann, errors);
+            }
+            check_term(ctx, bound, val, errors);
+            check_term(ctx, bound, body, errors);
+        }
+        TermKind::TyAbs(kind, body) => {
+            bound.push(kind.clone());
+            check_term(ctx, bound, body, errors);
+            bound.pop();
+        }
+        TermKind::Pack(witness, packed, ann) => {
+            ty(bound, witness, errors);
+            check_term(ctx, bound, packed, errors);
+            ty(bound, ann, errors);
+        }
+        TermKind::Unpack(packed, body) => {
+            check_term(ctx, bound, packed, errors);
+            bound.push(Kind::Type);
+            check_term(ctx, bound, body, errors);
+            bound.pop();
+        }
+        TermKind::Proj(term, _)
+        | TermKind::Inl(term)
+        | TermKind::Inr(term)
+        | TermKind::Field(term, _)
+        | TermKind::Variant(_, term)
+        | TermKind::Fold(term)
+        | TermKind::Unfold(term) => check_term(ctx, bound, term, errors),
+        TermKind::Appl(term1, term2) | TermKind::LetTuple(_, term1, term2) => {
+            check_term(ctx, bound, term1, errors);
+            check_term(ctx, bound, term2, errors);
+        }
+        TermKind::If(term1, term2, term3) | TermKind::Case(term1, term2, term3) => {
+            check_term(ctx, bound, term1, errors);
+            check_term(ctx, bound, term2, errors);
+            check_term(ctx, bound, term3, errors);
+        }
+        TermKind::Tuple(terms) => {
+            for term in terms {
+                check_term(ctx, bound, term, errors);
+            }
+        }
+        TermKind::Record(fields) => {
+            for (_, term) in fields {
+                check_term(ctx, bound, term, errors);
+            }
+        }
+        TermKind::VariantCase(scrut, arms) => {
+            check_term(ctx, bound, scrut, errors);
+            for (_, term) in arms {
+                check_term(ctx, bound, term, errors);
+            }
+        }
+        TermKind::Match(scrut, arms) => {
+            check_term(ctx, bound, scrut, errors);
+            for (_, term) in arms {
+                check_term(ctx, bound, term, errors);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grammar::ProgramParser;
+
+    fn check(source: &str) -> Result<(), Vec<KindErrorKind>> {
+        let program = ProgramParser::new()
+            .parse(source)
+            .unwrap()
+            .into_core()
+            .unwrap();
+        check_program(&program).map_err(|errs| errs.into_iter().map(|err| err.kind).collect())
+    }
+
+    fn arrow(inp: Kind, out: Kind) -> Kind {
+        Kind::Arrow(Box::new(inp), Box::new(out))
+    }
+
+    #[test]
+    fn operators_are_applied_to_arguments_of_their_kinds() {
+        assert_eq!(
+            check(
+                "data List 'a = Nil | Cons 'a (List 'a);
+                 type Functor ('f : * -> *) = 'a => 'b => ('a -> 'b) -> 'f 'a -> 'f 'b;
+                 def map : Functor List = \\f. \\xs. xs;"
+            ),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn operators_are_not_types() {
+        assert_eq!(
+            check("data List 'a = Nil; def x : List = ();"),
+            Err(vec![KindErrorKind::Mismatch(
+                Kind::Type,
+                arrow(Kind::Type, Kind::Type)
+            )])
+        );
+        assert_eq!(
+            check(
+                "data Wrap ('f : * -> *) 'a = MkWrap ('f 'a);
+                 def x : Wrap bool bool = ();"
+            ),
+            Err(vec![KindErrorKind::Mismatch(
+                arrow(Kind::Type, Kind::Type),
+                Kind::Type
+            )])
+        );
+    }
+
+    #[test]
+    fn types_cannot_be_applied() {
+        assert_eq!(
+            check("def x : 'a => 'a bool = ();"),
+            Err(vec![KindErrorKind::NotAnOperator(Kind::Type)])
+        );
+    }
+
+    #[test]
+    fn bounds_have_the_kinds_of_their_variables() {
+        assert_eq!(
+            check("def x : 'a <: { x : bool } => 'a -> 'a = \\y. y;"),
+            Ok(())
+        );
+        assert_eq!(
+            check("def x : ('f : * -> *) <: bool => unit = ();"),
+            Err(vec![KindErrorKind::Mismatch(
+                arrow(Kind::Type, Kind::Type),
+                Kind::Type
+            )])
+        );
+    }
+}
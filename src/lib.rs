@@ -1,7 +1,13 @@
 pub mod ast;
 pub mod ctx;
+pub mod diagnostics;
+pub mod driver;
+pub mod encode;
+pub mod eval;
 pub mod grammar;
+pub mod kindck;
 pub mod pp;
+pub mod span;
 pub mod typeck;
 
 pub use pretty;
@@ -1,9 +1,15 @@
 #![warn(clippy::pedantic)]
 
-use std::io::{self, prelude::*};
+use std::{
+    fs,
+    io::{self, prelude::*},
+    process,
+};
 
 use ctx::TyCtxt;
-use sysf_rs::{ctx, grammar, pp, typeck};
+use diagnostics::{Diagnostic, Format};
+use driver::Options;
+use sysf_rs::{ctx, diagnostics, driver, eval, grammar, pp, typeck};
 
 // fn builtins() -> HashMap<String, Option<Ty>> {
 //     let mut parser = grammar::TypeParser::new();
@@ -49,6 +55,27 @@ fn main() {
         // ctx.clear();
     }*/
 
+    let format = if std::env::args().any(|arg| arg == "--error-format=plain") {
+        Format::Plain
+    } else {
+        Format::Human
+    };
+    let options = Options {
+        implicit_forall: std::env::args().any(|arg| arg == "--implicit-forall"),
+    };
+    let show_implicit = std::env::args().any(|arg| arg == "--show-implicit");
+    let encode_data = std::env::args().any(|arg| arg == "--encode-data");
+
+    if let Some(path) = std::env::args().skip(1).find(|arg| !arg.starts_with("--")) {
+        let source = fs::read_to_string(&path).expect("io");
+        if encode_data {
+            encode_file(&source, &path, options, format);
+        } else {
+            check_file(&source, &path, options, format);
+        }
+        return;
+    }
+
     let stdin = io::stdin();
     let stdout = io::stdout();
     let parser = grammar::TermParser::new();
@@ -59,32 +86,117 @@ fn main() {
         let mut contents = String::new();
         print!(">>> ");
         stdout.lock().flush().expect("io");
-        stdin.lock().read_line(&mut contents).expect("io");
+        if stdin.lock().read_line(&mut contents).expect("io") == 0 {
+            println!();
+            break;
+        }
+        if contents.trim().is_empty() {
+            continue;
+        }
 
-        let parsed = parser.parse(&contents).unwrap();
-        let pp = pp::pp_parse_term(*parsed.clone(), &arena).into_doc();
+        let mut parsed = match parser.parse(&contents) {
+            Ok(parsed) => parsed,
+            Err(err) => {
+                report(
+                    &Diagnostic::from_parse_error(err, &contents),
+                    format,
+                    &contents,
+                );
+                continue;
+            }
+        };
+        if options.implicit_forall {
+            parsed.quantify_free_ty_vars();
+        }
+        let pp = pp::pp_parse_term(*parsed.clone(), &arena, show_implicit).into_doc();
         println!("=== Parsed ===\n\n{}\n", pp.pretty(80));
 
-        let core = parsed.into_core().unwrap();
+        let core = match parsed.into_core() {
+            Ok(core) => core,
+            Err(errs) => {
+                for err in errs {
+                    report(&Diagnostic::from(err), format, &contents);
+                }
+                continue;
+            }
+        };
         let pp = pp::pp_core_term(core.clone(), &arena).into_doc();
         println!("=== Resolved ===\n\n{}\n", pp.pretty(80));
 
         let inferred = typeck::infer(&mut ctx, &core);
         match inferred {
-            None => println!(
-                "=== Inferred ===\n\nUninferrable.\n\n=== Context ===\n\n{:?}",
-                ctx
-            ),
-            Some(inf) => {
+            Err(err) => {
+                println!("=== Inferred ===\n\nUninferrable.\n\n=== Context ===\n\n{ctx:?}\n");
+                report(&Diagnostic::from(err), format, &contents);
+            }
+            Ok(inf) => {
                 let inf = inf.subst_ctx(&ctx);
                 let pp = pp::pp_core_ty(inf, &arena).into_doc();
                 println!(
-                    "=== Inferred ===\n\n{}\n\n=== Context ===\n\n{:?}",
+                    "=== Inferred ===\n\n{}\n\n=== Context ===\n\n{:?}\n",
                     pp.pretty(80),
                     ctx
                 );
+                if core.uses_fix() {
+                    println!("Note: this term uses `fix`, so evaluating it may not terminate.\n");
+                }
+                println!("=== Evaluated ===\n\n{}", eval::eval(&core));
             }
         }
         ctx.clear();
     }
 }
+
+/// Print the type of each definition in the program `source`, read from
+/// `path`.
+fn check_file(source: &str, path: &str, options: Options, format: Format) {
+    let arena = pretty::Arena::new();
+    match driver::check_program(source, options) {
+        Ok((defs, synonyms)) => {
+            for (name, def) in defs {
+                let pp = pp::pp_core_ty_folding(def.ty, &synonyms, &arena).into_doc();
+                if def.normalising {
+                    println!("{} : {}", name, pp.pretty(80));
+                } else {
+                    println!("{} : {} (uses `fix`)", name, pp.pretty(80));
+                }
+            }
+        }
+        Err(diags) => {
+            for diag in diags {
+                eprint!("{}", diag.render(format, source, path));
+            }
+            process::exit(1);
+        }
+    }
+}
+
+/// Print the encoding of each data type in the program `source`, read from
+/// `path`.
+fn encode_file(source: &str, path: &str, options: Options, format: Format) {
+    let arena = pretty::Arena::new();
+    match driver::encode_program(source, options) {
+        Ok(encodings) => {
+            for (name, encoding) in encodings {
+                let pp = pp::pp_core_ty(encoding.ty, &arena).into_doc();
+                println!("{} = {}", name, pp.pretty(80));
+                for (ctor, term) in encoding.ctors {
+                    let pp = pp::pp_core_term(term, &arena).into_doc();
+                    println!("{} = {}", ctor, pp.pretty(80));
+                }
+                let pp = pp::pp_core_term(encoding.fold, &arena).into_doc();
+                println!("fold{} = {}", name, pp.pretty(80));
+            }
+        }
+        Err(diags) => {
+            for diag in diags {
+                eprint!("{}", diag.render(format, source, path));
+            }
+            process::exit(1);
+        }
+    }
+}
+
+fn report(diag: &Diagnostic, format: Format, source: &str) {
+    eprint!("{}", diag.render(format, source, "<stdin>"));
+}
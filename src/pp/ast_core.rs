@@ -1,4 +1,4 @@
-use crate::ast::core::{Term, Ty};
+use crate::ast::core::{Kind, Pattern, PatternKind, Row, Synonym, Term, TermKind, Ty, TyKind};
 use pretty::{DocAllocator, DocBuilder};
 
 type Builder<'a, A> = DocBuilder<'a, A, ()>;
@@ -7,17 +7,27 @@ pub fn pp_core_term<'a, A: DocAllocator<'a, ()>>(term: Term, arena: &'a A) -> Bu
 where
     <A as DocAllocator<'a, ()>>::Doc: Clone,
 {
-    match term {
-        Term::Var(idx) => arena.text(format!("{}", idx)),
-        Term::Bool(b) => arena.text(format!("{}", b)),
-        Term::Unit => arena.text("()"),
-        Term::Lambda(body) => arena
-            .text("\\ _")
+    match term.kind {
+        TermKind::Var(idx) => arena.text(format!("{}", idx)),
+        TermKind::Bool(b) => arena.text(format!("{}", b)),
+        TermKind::Unit => arena.text("()"),
+        TermKind::Absurd => arena.text("absurd"),
+        TermKind::Lambda(ty, body) => arena
+            .text("\\ ")
+            .append(match ty {
+                Some(ty) => arena
+                    .intersperse(
+                        [arena.text(":"), arena.text("_"), pp_core_ty(ty, arena)],
+                        arena.softline(),
+                    )
+                    .parens(),
+                None => arena.text("_"),
+            })
             .append(arena.softline())
             .append(pp_core_term(*body, arena))
             .nest(2)
             .parens(),
-        Term::Appl(func, arg) => arena
+        TermKind::Appl(func, arg) => arena
             .intersperse(
                 [
                     arena.text("$"),
@@ -28,7 +38,7 @@ where
             )
             .nest(2)
             .parens(),
-        Term::Ann(term, ty) => arena
+        TermKind::Ann(term, ty) => arena
             .intersperse(
                 [
                     arena.text(":"),
@@ -38,7 +48,7 @@ where
                 arena.softline(),
             )
             .parens(),
-        Term::TypeAppl(term, ty) => arena
+        TermKind::TypeAppl(term, ty) => arena
             .intersperse(
                 [
                     arena.text("[]"),
@@ -48,6 +58,255 @@ where
                 arena.softline(),
             )
             .parens(),
+        TermKind::Let(ty, val, body) => {
+            let binder = match ty {
+                Some(ty) => arena
+                    .intersperse(
+                        [arena.text(":"), arena.text("_"), pp_core_ty(ty, arena)],
+                        arena.softline(),
+                    )
+                    .parens(),
+                None => arena.text("_"),
+            };
+            arena
+                .intersperse(
+                    [
+                        arena.text("let"),
+                        binder,
+                        pp_core_term(*val, arena).nest(2),
+                        pp_core_term(*body, arena).nest(2),
+                    ],
+                    arena.softline(),
+                )
+                .parens()
+        }
+        TermKind::If(cond, then, els) => arena
+            .intersperse(
+                [
+                    arena.text("if"),
+                    pp_core_term(*cond, arena).nest(2),
+                    pp_core_term(*then, arena).nest(2),
+                    pp_core_term(*els, arena).nest(2),
+                ],
+                arena.softline(),
+            )
+            .parens(),
+        TermKind::Fix(ty, body) => arena
+            .text("fix ")
+            .append(match ty {
+                Some(ty) => arena
+                    .intersperse(
+                        [arena.text(":"), arena.text("_"), pp_core_ty(ty, arena)],
+                        arena.softline(),
+                    )
+                    .parens(),
+                None => arena.text("_"),
+            })
+            .append(arena.softline())
+            .append(pp_core_term(*body, arena))
+            .nest(2)
+            .parens(),
+        TermKind::Tuple(terms) => arena
+            .intersperse(
+                std::iter::once(arena.text(","))
+                    .chain(terms.into_iter().map(|term| pp_core_term(term, arena))),
+                arena.softline(),
+            )
+            .nest(2)
+            .parens(),
+        TermKind::Proj(term, idx) => arena
+            .intersperse(
+                [
+                    arena.text("."),
+                    pp_core_term(*term, arena).nest(2),
+                    arena.text(format!("{}", idx)),
+                ],
+                arena.softline(),
+            )
+            .parens(),
+        TermKind::LetTuple(len, val, body) => arena
+            .intersperse(
+                [
+                    arena.text("let"),
+                    arena
+                        .intersperse(vec![arena.text("_"); len], arena.space())
+                        .parens(),
+                    pp_core_term(*val, arena).nest(2),
+                    pp_core_term(*body, arena).nest(2),
+                ],
+                arena.softline(),
+            )
+            .parens(),
+        TermKind::Inl(term) => arena
+            .intersperse(
+                [arena.text("inl"), pp_core_term(*term, arena).nest(2)],
+                arena.softline(),
+            )
+            .parens(),
+        TermKind::Inr(term) => arena
+            .intersperse(
+                [arena.text("inr"), pp_core_term(*term, arena).nest(2)],
+                arena.softline(),
+            )
+            .parens(),
+        TermKind::Case(scrut, left, right) => arena
+            .intersperse(
+                [
+                    arena.text("case"),
+                    pp_core_term(*scrut, arena).nest(2),
+                    arena
+                        .intersperse(
+                            [arena.text("inl _"), pp_core_term(*left, arena)],
+                            arena.softline(),
+                        )
+                        .nest(2)
+                        .parens(),
+                    arena
+                        .intersperse(
+                            [arena.text("inr _"), pp_core_term(*right, arena)],
+                            arena.softline(),
+                        )
+                        .nest(2)
+                        .parens(),
+                ],
+                arena.softline(),
+            )
+            .nest(2)
+            .parens(),
+        TermKind::Record(fields) => arena
+            .intersperse(
+                std::iter::once(arena.text("record")).chain(fields.into_iter().map(
+                    |(label, term)| {
+                        arena
+                            .intersperse(
+                                [arena.text(label), pp_core_term(term, arena).nest(2)],
+                                arena.softline(),
+                            )
+                            .parens()
+                    },
+                )),
+                arena.softline(),
+            )
+            .nest(2)
+            .parens(),
+        TermKind::Field(term, label) => arena
+            .intersperse(
+                [
+                    arena.text("."),
+                    pp_core_term(*term, arena).nest(2),
+                    arena.text(label),
+                ],
+                arena.softline(),
+            )
+            .parens(),
+        TermKind::Variant(label, term) => arena
+            .intersperse(
+                [
+                    arena.text("variant"),
+                    arena.text(label),
+                    pp_core_term(*term, arena).nest(2),
+                ],
+                arena.softline(),
+            )
+            .parens(),
+        TermKind::VariantCase(scrut, arms) => arena
+            .intersperse(
+                std::iter::once(arena.text("case"))
+                    .chain(std::iter::once(pp_core_term(*scrut, arena).nest(2)))
+                    .chain(arms.into_iter().map(|(label, arm)| {
+                        arena
+                            .intersperse(
+                                [
+                                    arena.text(format!("<{} = _>", label)),
+                                    pp_core_term(arm, arena),
+                                ],
+                                arena.softline(),
+                            )
+                            .nest(2)
+                            .parens()
+                    })),
+                arena.softline(),
+            )
+            .nest(2)
+            .parens(),
+        TermKind::Fold(term) => arena
+            .intersperse(
+                [arena.text("fold"), pp_core_term(*term, arena).nest(2)],
+                arena.softline(),
+            )
+            .parens(),
+        TermKind::Unfold(term) => arena
+            .intersperse(
+                [arena.text("unfold"), pp_core_term(*term, arena).nest(2)],
+                arena.softline(),
+            )
+            .parens(),
+        TermKind::Pack(witness, term, ty) => arena
+            .intersperse(
+                [
+                    arena.text("pack"),
+                    pp_core_ty(witness, arena).nest(2),
+                    pp_core_term(*term, arena).nest(2),
+                    pp_core_ty(ty, arena).nest(2),
+                ],
+                arena.softline(),
+            )
+            .parens(),
+        TermKind::Unpack(packed, body) => arena
+            .intersperse(
+                [
+                    arena.text("unpack"),
+                    pp_core_term(*packed, arena).nest(2),
+                    arena.text("(_ _)"),
+                    pp_core_term(*body, arena).nest(2),
+                ],
+                arena.softline(),
+            )
+            .parens(),
+        TermKind::TyAbs(kind, body) => arena
+            .text(format!("/\\ {}", binder(&kind)))
+            .append(arena.softline())
+            .append(pp_core_term(*body, arena))
+            .nest(2)
+            .parens(),
+        TermKind::Ctor(name, _) => arena.text(name),
+        TermKind::Match(scrut, arms) => arena
+            .intersperse(
+                std::iter::once(arena.text("match"))
+                    .chain(std::iter::once(pp_core_term(*scrut, arena).nest(2)))
+                    .chain(arms.into_iter().map(|(pat, arm)| {
+                        arena
+                            .intersperse(
+                                [pp_core_pattern(pat, arena), pp_core_term(arm, arena)],
+                                arena.softline(),
+                            )
+                            .nest(2)
+                            .parens()
+                    })),
+                arena.softline(),
+            )
+            .nest(2)
+            .parens(),
+    }
+}
+
+/// Variables are shown as `_`, like other binders, and wildcards as `*`.
+fn pp_core_pattern<'a, A: DocAllocator<'a, ()>>(pat: Pattern, arena: &'a A) -> Builder<'a, A>
+where
+    <A as DocAllocator<'a, ()>>::Doc: Clone,
+{
+    match pat.kind {
+        PatternKind::Wildcard => arena.text("*"),
+        PatternKind::Var => arena.text("_"),
+        PatternKind::Ctor(name, pats) if pats.is_empty() => arena.text(name),
+        PatternKind::Ctor(name, pats) => arena
+            .intersperse(
+                std::iter::once(arena.text(name))
+                    .chain(pats.into_iter().map(|pat| pp_core_pattern(pat, arena))),
+                arena.softline(),
+            )
+            .nest(2)
+            .parens(),
     }
 }
 
@@ -55,30 +314,208 @@ pub fn pp_core_ty<'a, A: DocAllocator<'a, ()>>(ty: Ty, arena: &'a A) -> Builder<
 where
     <A as DocAllocator<'a, ()>>::Doc: Clone,
 {
-    match ty {
-        Ty::Bool => arena.text("bool"),
-        Ty::Unit => arena.text("unit"),
-        Ty::Var(idx) => arena.text(format!("{}", idx)),
-        Ty::Arrow(inp, out) => arena
+    pp_core_ty_folding(ty, &[], arena)
+}
+
+/// Like [`pp_core_ty`], but showing the expansions of `synonyms` as uses of
+/// them, preferring the ones declared last.
+pub fn pp_core_ty_folding<'a, A: DocAllocator<'a, ()>>(
+    ty: Ty,
+    synonyms: &[Synonym],
+    arena: &'a A,
+) -> Builder<'a, A>
+where
+    <A as DocAllocator<'a, ()>>::Doc: Clone,
+{
+    let folded = synonyms
+        .iter()
+        .rev()
+        .find_map(|synonym| Some((&synonym.name, synonym.fold(&ty)?)));
+    if let Some((name, args)) = folded {
+        return pp_con(name.clone(), args, synonyms, arena);
+    }
+    if let Some(ty) = ty.eta_contract() {
+        return pp_core_ty_folding(ty, synonyms, arena);
+    }
+    match ty.kind {
+        TyKind::Bool => arena.text("bool"),
+        TyKind::Unit => arena.text("unit"),
+        TyKind::Top => arena.text("top"),
+        TyKind::Never => arena.text("never"),
+        TyKind::Var(idx) => arena.text(format!("{}", idx)),
+        TyKind::Arrow(inp, out) => arena
             .intersperse(
                 [
-                    pp_core_ty(*inp, arena),
+                    pp_core_ty_folding(*inp, synonyms, arena),
                     arena.text("->").nest(2),
-                    pp_core_ty(*out, arena).nest(2),
+                    pp_core_ty_folding(*out, synonyms, arena).nest(2),
                 ],
                 arena.softline(),
             )
             .parens(),
-        Ty::Forall(body) => arena
+        TyKind::Product(tys) => arena
+            .intersperse(
+                tys.into_iter()
+                    .map(|ty| pp_core_ty_folding(ty, synonyms, arena).nest(2)),
+                arena.softline().append(arena.text("* ")),
+            )
+            .parens(),
+        TyKind::Sum(left, right) => arena
             .intersperse(
                 [
-                    arena.text("_"),
+                    pp_core_ty_folding(*left, synonyms, arena),
+                    arena.text("+").nest(2),
+                    pp_core_ty_folding(*right, synonyms, arena).nest(2),
+                ],
+                arena.softline(),
+            )
+            .parens(),
+        TyKind::Intersection(left, right) => arena
+            .intersperse(
+                [
+                    pp_core_ty_folding(*left, synonyms, arena),
+                    arena.text("&").nest(2),
+                    pp_core_ty_folding(*right, synonyms, arena).nest(2),
+                ],
+                arena.softline(),
+            )
+            .parens(),
+        TyKind::Union(left, right) => arena
+            .intersperse(
+                [
+                    pp_core_ty_folding(*left, synonyms, arena),
+                    arena.text("|").nest(2),
+                    pp_core_ty_folding(*right, synonyms, arena).nest(2),
+                ],
+                arena.softline(),
+            )
+            .parens(),
+        TyKind::Record(row) => pp_core_row(row, synonyms, arena).braces(),
+        TyKind::Variant(row) => pp_core_row(row, synonyms, arena).angles(),
+        TyKind::Row(row) => arena
+            .text("row ")
+            .append(pp_core_row(row, synonyms, arena))
+            .parens(),
+        TyKind::Forall(kind, bound, body) => arena
+            .intersperse(
+                [
+                    match bound {
+                        Some(bound) => arena
+                            .text(binder(&kind))
+                            .append(arena.softline())
+                            .append("<:")
+                            .append(arena.softline())
+                            .append(pp_core_ty_folding(*bound, synonyms, arena))
+                            .group(),
+                        None => arena.text(binder(&kind)),
+                    },
                     arena.text("=>").nest(2),
-                    pp_core_ty(*body, arena).nest(2),
+                    pp_core_ty_folding(*body, synonyms, arena).nest(2),
                 ],
                 arena.softline(),
             )
             .parens(),
-        Ty::ExstVar(idx) => arena.text(format!("'__exst{}", idx)),
+        TyKind::Exists(body) => arena
+            .text("exists _.")
+            .append(arena.softline())
+            .append(pp_core_ty_folding(*body, synonyms, arena))
+            .nest(2)
+            .parens(),
+        TyKind::Mu(body) => arena
+            .text("mu _.")
+            .append(arena.softline())
+            .append(pp_core_ty_folding(*body, synonyms, arena))
+            .nest(2)
+            .parens(),
+        TyKind::Data(name, args) => pp_con(name, args, synonyms, arena),
+        TyKind::Lam(kind, body) => arena
+            .text(format!("\\ {}.", binder(&kind)))
+            .append(arena.softline())
+            .append(pp_core_ty_folding(*body, synonyms, arena))
+            .nest(2)
+            .parens(),
+        TyKind::App(func, arg) => {
+            // Print `((f a) b)` as `(f a b)`.
+            let mut args = vec![*arg];
+            let mut func = *func;
+            while let TyKind::App(inner, arg) = func.kind {
+                args.push(*arg);
+                func = *inner;
+            }
+            arena
+                .intersperse(
+                    std::iter::once(pp_core_ty_folding(func, synonyms, arena)).chain(
+                        args.into_iter()
+                            .rev()
+                            .map(|ty| pp_core_ty_folding(ty, synonyms, arena).nest(2)),
+                    ),
+                    arena.softline(),
+                )
+                .parens()
+        }
+        TyKind::UnivVar(idx) => arena.text(format!("'__univ{}", idx)),
+        TyKind::ExstVar(idx) => arena.text(format!("'__exst{}", idx)),
+    }
+}
+
+/// A variable of kind `kind` being bound, whose name isn't known.
+fn binder(kind: &Kind) -> String {
+    match kind {
+        Kind::Type => "_".to_string(),
+        Kind::Row => "_ : row".to_string(),
+        Kind::Arrow(..) => format!("_ : {}", kind),
+    }
+}
+
+/// A data type or synonym applied to `args`.
+fn pp_con<'a, A: DocAllocator<'a, ()>>(
+    name: String,
+    args: Vec<Ty>,
+    synonyms: &[Synonym],
+    arena: &'a A,
+) -> Builder<'a, A>
+where
+    <A as DocAllocator<'a, ()>>::Doc: Clone,
+{
+    if args.is_empty() {
+        return arena.text(name);
+    }
+    arena
+        .intersperse(
+            std::iter::once(arena.text(name)).chain(
+                args.into_iter()
+                    .map(|ty| pp_core_ty_folding(ty, synonyms, arena).nest(2)),
+            ),
+            arena.softline(),
+        )
+        .parens()
+}
+
+/// The fields of a row followed by its tail, if it has one, without the
+/// surrounding brackets.
+fn pp_core_row<'a, A: DocAllocator<'a, ()>>(
+    row: Row,
+    synonyms: &[Synonym],
+    arena: &'a A,
+) -> Builder<'a, A>
+where
+    <A as DocAllocator<'a, ()>>::Doc: Clone,
+{
+    let fields = arena.intersperse(
+        row.fields.into_iter().map(|(label, ty)| {
+            arena
+                .text(format!("{} :", label))
+                .append(arena.softline())
+                .append(pp_core_ty_folding(ty, synonyms, arena))
+                .nest(2)
+        }),
+        arena.text(",").append(arena.softline()),
+    );
+    match row.tail {
+        Some(tail) => fields
+            .append(arena.softline())
+            .append(arena.text("| "))
+            .append(pp_core_ty_folding(*tail, synonyms, arena)),
+        None => fields,
     }
 }
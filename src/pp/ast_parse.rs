@@ -1,83 +1,534 @@
-use crate::ast::parse::{Term, Ty};
+use crate::ast::core::Kind;
+use crate::ast::parse::{Pattern, PatternKind, Term, TermKind, Ty, TyKind};
 use pretty::{DocAllocator, DocBuilder};
 
 type Builder<'a, A> = DocBuilder<'a, A, ()>;
 
-pub fn pp_parse_term<'a, A: DocAllocator<'a, ()>>(term: Term, arena: &'a A) -> Builder<'a, A>
+/// Pretty-print a surface term. Quantifiers inserted by implicit
+/// quantification are only shown if `show_implicit` is set.
+pub fn pp_parse_term<'a, A: DocAllocator<'a, ()>>(
+    term: Term,
+    arena: &'a A,
+    show_implicit: bool,
+) -> Builder<'a, A>
 where
     <A as DocAllocator<'a, ()>>::Doc: Clone,
 {
-    match term {
-        Term::Var(name) => arena.text(name),
-        Term::Bool(b) => arena.text(format!("{}", b)),
-        Term::Unit => arena.text("()"),
-        Term::Lambda(name, body) => arena
-            .text(format!("\\ {}", name))
+    match term.kind {
+        TermKind::Var(name) => arena.text(name),
+        TermKind::Bool(b) => arena.text(format!("{}", b)),
+        TermKind::Unit => arena.text("()"),
+        TermKind::Absurd => arena.text("absurd"),
+        TermKind::Lambda(name, ty, body) => arena
+            .text("\\ ")
+            .append(match ty {
+                Some(ty) => arena
+                    .intersperse(
+                        [
+                            arena.text(":"),
+                            arena.text(name),
+                            pp_parse_ty(ty, arena, show_implicit),
+                        ],
+                        arena.softline(),
+                    )
+                    .parens(),
+                None => arena.text(name),
+            })
             .append(arena.softline())
-            .append(pp_parse_term(*body, arena))
+            .append(pp_parse_term(*body, arena, show_implicit))
             .nest(2)
             .parens(),
-        Term::Appl(func, arg) => arena
+        TermKind::Appl(func, arg) => arena
             .intersperse(
                 [
                     arena.text("$"),
-                    pp_parse_term(*func, arena),
-                    pp_parse_term(*arg, arena),
+                    pp_parse_term(*func, arena, show_implicit),
+                    pp_parse_term(*arg, arena, show_implicit),
                 ],
                 arena.softline(),
             )
             .nest(2)
             .parens(),
-        Term::Ann(term, ty) => arena
+        TermKind::Ann(term, ty) => arena
             .intersperse(
                 [
                     arena.text(":"),
-                    pp_parse_term(*term, arena).nest(2),
-                    pp_parse_ty(ty, arena).nest(2),
+                    pp_parse_term(*term, arena, show_implicit).nest(2),
+                    pp_parse_ty(ty, arena, show_implicit).nest(2),
                 ],
                 arena.softline(),
             )
             .parens(),
-        Term::TypeAppl(term, ty) => arena
+        TermKind::TypeAppl(term, ty) => arena
             .intersperse(
                 [
                     arena.text("[]"),
-                    pp_parse_term(*term, arena).nest(2),
-                    pp_parse_ty(ty, arena).nest(2),
+                    pp_parse_term(*term, arena, show_implicit).nest(2),
+                    pp_parse_ty(ty, arena, show_implicit).nest(2),
                 ],
                 arena.softline(),
             )
             .parens(),
+        TermKind::Let(name, ty, val, body) => {
+            let binder = match ty {
+                Some(ty) => arena
+                    .intersperse(
+                        [
+                            arena.text(":"),
+                            arena.text(name),
+                            pp_parse_ty(ty, arena, show_implicit),
+                        ],
+                        arena.softline(),
+                    )
+                    .parens(),
+                None => arena.text(name),
+            };
+            arena
+                .intersperse(
+                    [
+                        arena.text("let"),
+                        binder,
+                        pp_parse_term(*val, arena, show_implicit).nest(2),
+                        pp_parse_term(*body, arena, show_implicit).nest(2),
+                    ],
+                    arena.softline(),
+                )
+                .parens()
+        }
+        TermKind::If(cond, then, els) => arena
+            .intersperse(
+                [
+                    arena.text("if"),
+                    pp_parse_term(*cond, arena, show_implicit).nest(2),
+                    pp_parse_term(*then, arena, show_implicit).nest(2),
+                    pp_parse_term(*els, arena, show_implicit).nest(2),
+                ],
+                arena.softline(),
+            )
+            .parens(),
+        TermKind::Fix(name, ty, body) => arena
+            .text("fix ")
+            .append(match ty {
+                Some(ty) => arena
+                    .intersperse(
+                        [
+                            arena.text(":"),
+                            arena.text(name),
+                            pp_parse_ty(ty, arena, show_implicit),
+                        ],
+                        arena.softline(),
+                    )
+                    .parens(),
+                None => arena.text(name),
+            })
+            .append(arena.softline())
+            .append(pp_parse_term(*body, arena, show_implicit))
+            .nest(2)
+            .parens(),
+        TermKind::Tuple(terms) => arena
+            .intersperse(
+                std::iter::once(arena.text(",")).chain(
+                    terms
+                        .into_iter()
+                        .map(|term| pp_parse_term(term, arena, show_implicit)),
+                ),
+                arena.softline(),
+            )
+            .nest(2)
+            .parens(),
+        TermKind::Proj(term, idx) => arena
+            .intersperse(
+                [
+                    arena.text("."),
+                    pp_parse_term(*term, arena, show_implicit).nest(2),
+                    arena.text(format!("{}", idx)),
+                ],
+                arena.softline(),
+            )
+            .parens(),
+        TermKind::LetTuple(names, val, body) => arena
+            .intersperse(
+                [
+                    arena.text("let"),
+                    arena
+                        .intersperse(
+                            names.into_iter().map(|name| arena.text(name)),
+                            arena.space(),
+                        )
+                        .parens(),
+                    pp_parse_term(*val, arena, show_implicit).nest(2),
+                    pp_parse_term(*body, arena, show_implicit).nest(2),
+                ],
+                arena.softline(),
+            )
+            .parens(),
+        TermKind::Inl(term) => arena
+            .intersperse(
+                [
+                    arena.text("inl"),
+                    pp_parse_term(*term, arena, show_implicit).nest(2),
+                ],
+                arena.softline(),
+            )
+            .parens(),
+        TermKind::Inr(term) => arena
+            .intersperse(
+                [
+                    arena.text("inr"),
+                    pp_parse_term(*term, arena, show_implicit).nest(2),
+                ],
+                arena.softline(),
+            )
+            .parens(),
+        TermKind::Case(scrut, left_name, left, right_name, right) => arena
+            .intersperse(
+                [
+                    arena.text("case"),
+                    pp_parse_term(*scrut, arena, show_implicit).nest(2),
+                    arena
+                        .intersperse(
+                            [
+                                arena.text(format!("inl {}", left_name)),
+                                pp_parse_term(*left, arena, show_implicit),
+                            ],
+                            arena.softline(),
+                        )
+                        .nest(2)
+                        .parens(),
+                    arena
+                        .intersperse(
+                            [
+                                arena.text(format!("inr {}", right_name)),
+                                pp_parse_term(*right, arena, show_implicit),
+                            ],
+                            arena.softline(),
+                        )
+                        .nest(2)
+                        .parens(),
+                ],
+                arena.softline(),
+            )
+            .nest(2)
+            .parens(),
+        TermKind::Record(fields) => arena
+            .intersperse(
+                std::iter::once(arena.text("record")).chain(fields.into_iter().map(
+                    |(label, term)| {
+                        arena
+                            .intersperse(
+                                [
+                                    arena.text(label),
+                                    pp_parse_term(term, arena, show_implicit).nest(2),
+                                ],
+                                arena.softline(),
+                            )
+                            .parens()
+                    },
+                )),
+                arena.softline(),
+            )
+            .nest(2)
+            .parens(),
+        TermKind::Field(term, label) => arena
+            .intersperse(
+                [
+                    arena.text("."),
+                    pp_parse_term(*term, arena, show_implicit).nest(2),
+                    arena.text(label),
+                ],
+                arena.softline(),
+            )
+            .parens(),
+        TermKind::Variant(label, term) => arena
+            .intersperse(
+                [
+                    arena.text("variant"),
+                    arena.text(label),
+                    pp_parse_term(*term, arena, show_implicit).nest(2),
+                ],
+                arena.softline(),
+            )
+            .parens(),
+        TermKind::VariantCase(scrut, arms) => arena
+            .intersperse(
+                std::iter::once(arena.text("case"))
+                    .chain(std::iter::once(
+                        pp_parse_term(*scrut, arena, show_implicit).nest(2),
+                    ))
+                    .chain(arms.into_iter().map(|(label, name, arm)| {
+                        arena
+                            .intersperse(
+                                [
+                                    arena.text(format!("<{} = {}>", label, name)),
+                                    pp_parse_term(arm, arena, show_implicit),
+                                ],
+                                arena.softline(),
+                            )
+                            .nest(2)
+                            .parens()
+                    })),
+                arena.softline(),
+            )
+            .nest(2)
+            .parens(),
+        TermKind::Fold(term) => arena
+            .intersperse(
+                [
+                    arena.text("fold"),
+                    pp_parse_term(*term, arena, show_implicit).nest(2),
+                ],
+                arena.softline(),
+            )
+            .parens(),
+        TermKind::Unfold(term) => arena
+            .intersperse(
+                [
+                    arena.text("unfold"),
+                    pp_parse_term(*term, arena, show_implicit).nest(2),
+                ],
+                arena.softline(),
+            )
+            .parens(),
+        TermKind::Pack(witness, term, ty) => arena
+            .intersperse(
+                [
+                    arena.text("pack"),
+                    pp_parse_ty(witness, arena, show_implicit).nest(2),
+                    pp_parse_term(*term, arena, show_implicit).nest(2),
+                    pp_parse_ty(ty, arena, show_implicit).nest(2),
+                ],
+                arena.softline(),
+            )
+            .parens(),
+        TermKind::Unpack(packed, ty_name, name, body) => arena
+            .intersperse(
+                [
+                    arena.text("unpack"),
+                    pp_parse_term(*packed, arena, show_implicit).nest(2),
+                    arena.text(format!("({} {})", ty_name, name)),
+                    pp_parse_term(*body, arena, show_implicit).nest(2),
+                ],
+                arena.softline(),
+            )
+            .parens(),
+        TermKind::TyAbs(name, kind, body) => arena
+            .text(format!("/\\ {}", binder(name, kind)))
+            .append(arena.softline())
+            .append(pp_parse_term(*body, arena, show_implicit))
+            .nest(2)
+            .parens(),
+        TermKind::Match(scrut, arms) => arena
+            .intersperse(
+                std::iter::once(arena.text("match"))
+                    .chain(std::iter::once(
+                        pp_parse_term(*scrut, arena, show_implicit).nest(2),
+                    ))
+                    .chain(arms.into_iter().map(|(pat, arm)| {
+                        arena
+                            .intersperse(
+                                [
+                                    pp_parse_pattern(pat, arena),
+                                    pp_parse_term(arm, arena, show_implicit),
+                                ],
+                                arena.softline(),
+                            )
+                            .nest(2)
+                            .parens()
+                    })),
+                arena.softline(),
+            )
+            .nest(2)
+            .parens(),
+    }
+}
+
+fn pp_parse_pattern<'a, A: DocAllocator<'a, ()>>(pat: Pattern, arena: &'a A) -> Builder<'a, A>
+where
+    <A as DocAllocator<'a, ()>>::Doc: Clone,
+{
+    match pat.kind {
+        PatternKind::Wildcard => arena.text("_"),
+        PatternKind::Var(name) => arena.text(name),
+        PatternKind::Ctor(name, pats) => arena
+            .intersperse(
+                std::iter::once(arena.text(name))
+                    .chain(pats.into_iter().map(|pat| pp_parse_pattern(pat, arena))),
+                arena.softline(),
+            )
+            .nest(2)
+            .parens(),
     }
 }
 
-pub fn pp_parse_ty<'a, A: DocAllocator<'a, ()>>(ty: Ty, arena: &'a A) -> Builder<'a, A>
+pub fn pp_parse_ty<'a, A: DocAllocator<'a, ()>>(
+    ty: Ty,
+    arena: &'a A,
+    show_implicit: bool,
+) -> Builder<'a, A>
 where
     <A as DocAllocator<'a, ()>>::Doc: Clone,
 {
-    match ty {
-        Ty::Bool => arena.text("bool"),
-        Ty::Unit => arena.text("unit"),
-        Ty::Var(name) => arena.text(name),
-        Ty::Arrow(inp, out) => arena
+    match ty.kind {
+        TyKind::Bool => arena.text("bool"),
+        TyKind::Unit => arena.text("unit"),
+        TyKind::Top => arena.text("top"),
+        TyKind::Never => arena.text("never"),
+        TyKind::Var(name) => arena.text(name),
+        TyKind::Arrow(inp, out) => arena
             .intersperse(
                 [
-                    pp_parse_ty(*inp, arena),
+                    pp_parse_ty(*inp, arena, show_implicit),
                     arena.text("->").nest(2),
-                    pp_parse_ty(*out, arena).nest(2),
+                    pp_parse_ty(*out, arena, show_implicit).nest(2),
                 ],
                 arena.softline(),
             )
             .parens(),
-        Ty::Forall(name, body) => arena
+        TyKind::Product(tys) => arena
+            .intersperse(
+                tys.into_iter()
+                    .map(|ty| pp_parse_ty(ty, arena, show_implicit).nest(2)),
+                arena.softline().append(arena.text("* ")),
+            )
+            .parens(),
+        TyKind::Sum(left, right) => arena
+            .intersperse(
+                [
+                    pp_parse_ty(*left, arena, show_implicit),
+                    arena.text("+").nest(2),
+                    pp_parse_ty(*right, arena, show_implicit).nest(2),
+                ],
+                arena.softline(),
+            )
+            .parens(),
+        TyKind::Intersection(left, right) => arena
+            .intersperse(
+                [
+                    pp_parse_ty(*left, arena, show_implicit),
+                    arena.text("&").nest(2),
+                    pp_parse_ty(*right, arena, show_implicit).nest(2),
+                ],
+                arena.softline(),
+            )
+            .parens(),
+        TyKind::Union(left, right) => arena
+            .intersperse(
+                [
+                    pp_parse_ty(*left, arena, show_implicit),
+                    arena.text("|").nest(2),
+                    pp_parse_ty(*right, arena, show_implicit).nest(2),
+                ],
+                arena.softline(),
+            )
+            .parens(),
+        TyKind::Record(fields, tail) => pp_parse_row(fields, tail, arena, show_implicit).braces(),
+        TyKind::Variant(fields, tail) => pp_parse_row(fields, tail, arena, show_implicit).angles(),
+        TyKind::Exists(name, body) => arena
+            .text(format!("exists {}.", name))
+            .append(arena.softline())
+            .append(pp_parse_ty(*body, arena, show_implicit))
+            .nest(2)
+            .parens(),
+        TyKind::Mu(name, body) => arena
+            .text(format!("mu {}.", name))
+            .append(arena.softline())
+            .append(pp_parse_ty(*body, arena, show_implicit))
+            .nest(2)
+            .parens(),
+        TyKind::Con(name, args) if args.is_empty() => arena.text(name),
+        TyKind::Con(name, args) => arena
+            .intersperse(
+                std::iter::once(arena.text(name)).chain(
+                    args.into_iter()
+                        .map(|ty| pp_parse_ty(ty, arena, show_implicit).nest(2)),
+                ),
+                arena.softline(),
+            )
+            .parens(),
+        TyKind::ImplicitForall(_, body) if !show_implicit => {
+            pp_parse_ty(*body, arena, show_implicit)
+        }
+        TyKind::Forall(name, kind, upper, body) => arena
+            .intersperse(
+                [
+                    match upper {
+                        Some(upper) => arena
+                            .text(binder(name, kind))
+                            .append(arena.softline())
+                            .append("<:")
+                            .append(arena.softline())
+                            .append(pp_parse_ty(*upper, arena, show_implicit))
+                            .group(),
+                        None => arena.text(binder(name, kind)),
+                    },
+                    arena.text("=>").nest(2),
+                    pp_parse_ty(*body, arena, show_implicit).nest(2),
+                ],
+                arena.softline(),
+            )
+            .parens(),
+        TyKind::Lam(name, kind, body) => arena
+            .text(format!("\\ {}.", binder(name, kind)))
+            .append(arena.softline())
+            .append(pp_parse_ty(*body, arena, show_implicit))
+            .nest(2)
+            .parens(),
+        TyKind::App(func, arg) => arena
+            .intersperse(
+                [
+                    pp_parse_ty(*func, arena, show_implicit),
+                    pp_parse_ty(*arg, arena, show_implicit).nest(2),
+                ],
+                arena.softline(),
+            )
+            .parens(),
+        TyKind::ImplicitForall(name, body) => arena
             .intersperse(
                 [
                     arena.text(name),
                     arena.text("=>").nest(2),
-                    pp_parse_ty(*body, arena).nest(2),
+                    pp_parse_ty(*body, arena, show_implicit).nest(2),
                 ],
                 arena.softline(),
             )
             .parens(),
     }
 }
+
+/// A type variable being bound, with its kind if it was annotated with one.
+fn binder(name: String, kind: Option<Kind>) -> String {
+    match kind {
+        Some(kind) => format!("{} : {}", name, kind),
+        None => name,
+    }
+}
+
+/// The fields of a row followed by its tail, if it has one, without the
+/// surrounding brackets.
+fn pp_parse_row<'a, A: DocAllocator<'a, ()>>(
+    fields: Vec<(String, Ty)>,
+    tail: Option<Box<Ty>>,
+    arena: &'a A,
+    show_implicit: bool,
+) -> Builder<'a, A>
+where
+    <A as DocAllocator<'a, ()>>::Doc: Clone,
+{
+    let fields = arena.intersperse(
+        fields.into_iter().map(|(label, ty)| {
+            arena
+                .text(format!("{} :", label))
+                .append(arena.softline())
+                .append(pp_parse_ty(ty, arena, show_implicit))
+                .nest(2)
+        }),
+        arena.text(",").append(arena.softline()),
+    );
+    match tail {
+        Some(tail) => fields
+            .append(arena.softline())
+            .append(arena.text("| "))
+            .append(pp_parse_ty(*tail, arena, show_implicit)),
+        None => fields,
+    }
+}
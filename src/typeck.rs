@@ -1,128 +1,1214 @@
-use crate::ast::core::{Term, Ty};
+use crate::ast::core::{
+    DeclKind, Kind, Pattern, PatternKind, Program, Row, Term, TermKind, Ty, TyKind,
+};
 use crate::ctx::TyCtxt;
-use subtyping::subtype;
+use crate::kindck;
+use subtyping::{articulate, check_bound, extend_row, subtype};
+
+pub use error::{TypeError, TypeErrorKind};
 
 use crate::trace;
 #[cfg(feature = "trace")]
 use tracing::instrument;
 
+mod error;
+mod exhaustiveness;
 pub mod subtyping;
 
 #[cfg_attr(feature = "trace", instrument(level = "trace", skip(ctx)))]
-pub fn infer(ctx: &mut TyCtxt, term: &Term) -> Option<Ty> {
+pub fn infer(ctx: &mut TyCtxt, term: &Term) -> Result<Ty, TypeError> {
     trace!("infer/enter");
-    let res = match term {
+    let span = term.span;
+    let res = match &term.kind {
         // Var
-        Term::Var(idx) => ctx.get_term_var(*idx).cloned()?,
+        TermKind::Var(idx) => ctx
+            .get_term_var(*idx)
+            .cloned()
+            .ok_or_else(|| TypeError::new(TypeErrorKind::UnboundVar, span))?,
         // Anno
-        Term::Ann(term, ty) => {
-            if !ty.is_wellformed_in(ctx) {
-                trace!("infer/leave: Anno: type is not well-formed");
-                return None;
-            }
+        TermKind::Ann(term, ty) => {
+            check_wellformed(ctx, ty)?;
             check(ctx, term, ty)?;
             ty.clone()
         }
         // 1I=>
-        Term::Unit => Ty::Unit,
+        TermKind::Unit => Ty::new(TyKind::Unit, span),
         // BoolI=> (not in paper)
-        Term::Bool(_) => Ty::Bool,
+        TermKind::Bool(_) => Ty::new(TyKind::Bool, span),
+        // AbsurdI=> (not in paper)
+        //
+        // `absurd : 'a => never -> 'a`
+        TermKind::Absurd => Ty::new(
+            TyKind::Forall(
+                Kind::Type,
+                None,
+                Box::new(Ty::new(
+                    TyKind::Arrow(
+                        Box::new(Ty::new(TyKind::Never, span)),
+                        Box::new(Ty::new(TyKind::Var(0), span)),
+                    ),
+                    span,
+                )),
+            ),
+            span,
+        ),
+        // ->I=> with an annotated binder (not in paper)
+        TermKind::Lambda(Some(ann), body) => {
+            check_wellformed(ctx, ann)?;
+            let beta = ctx.fresh_evar();
+            ctx.add_unsolved(beta);
+            ctx.add_term_var(ann.clone());
+            check(ctx, body, &Ty::new(TyKind::ExstVar(beta), body.span))?;
+            ctx.drop_after_term_var(0);
+            Ty::new(
+                TyKind::Arrow(
+                    Box::new(ann.clone()),
+                    Box::new(Ty::new(TyKind::ExstVar(beta), body.span)),
+                ),
+                span,
+            )
+        }
         // ->I=>
-        Term::Lambda(body) => {
+        TermKind::Lambda(None, body) => {
             let alpha = ctx.fresh_evar();
             let beta = ctx.fresh_evar();
             ctx.add_unsolved(alpha);
             ctx.add_unsolved(beta);
-            ctx.add_term_var(Ty::ExstVar(alpha));
-            check(ctx, body, &Ty::ExstVar(beta))?;
+            ctx.add_term_var(Ty::new(TyKind::ExstVar(alpha), span));
+            check(ctx, body, &Ty::new(TyKind::ExstVar(beta), body.span))?;
             ctx.drop_after_term_var(0);
-            Ty::Arrow(Box::new(Ty::ExstVar(alpha)), Box::new(Ty::ExstVar(beta)))
+            Ty::new(
+                TyKind::Arrow(
+                    Box::new(Ty::new(TyKind::ExstVar(alpha), span)),
+                    Box::new(Ty::new(TyKind::ExstVar(beta), body.span)),
+                ),
+                span,
+            )
         }
         // ->E
-        Term::Appl(func, arg) => {
-            let fty = infer(ctx, &*func)?;
-            let fty = fty.subst_ctx(ctx);
-            infer_appl(ctx, &fty, &*arg)?
+        TermKind::Appl(func, arg) => {
+            let fty = infer(ctx, func)?;
+            // Errors about the function's type should point at the function.
+            let fty = Ty::new(expose(ctx, fty.subst_ctx(ctx)).kind, func.span);
+            infer_appl(ctx, &fty, arg)?
         }
         // TypeApp=>
-        Term::TypeAppl(term, ty) => {
-            let fty = infer(ctx, &*term)?;
+        TermKind::TypeAppl(term, ty) => {
+            let fty = infer(ctx, term)?.subst_ctx(ctx);
             if !ty.is_mono_wellformed_in((&*ctx).into()) {
                 trace!("infer/leave: TypeApp=>: not a monotype");
-                return None;
+                return Err(TypeError::new(
+                    TypeErrorKind::NotMonotype(ty.clone().subst_ctx(ctx)),
+                    ty.span,
+                ));
+            }
+            match fty.kind {
+                TyKind::Forall(Kind::Row, ..) => {
+                    trace!("infer/leave: TypeApp=>: row quantifier");
+                    return Err(TypeError::new(
+                        TypeErrorKind::RowQuantifier(Ty::new(fty.kind, term.span)),
+                        term.span,
+                    ));
+                }
+                TyKind::Forall(kind, bound, body) => {
+                    let found = kindck::infer_kind(ctx, &mut Vec::new(), ty).map_err(|_| {
+                        TypeError::new(TypeErrorKind::IllFormedAnn(ty.clone()), ty.span)
+                    })?;
+                    if found != kind {
+                        trace!("infer/leave: TypeApp=>: kind mismatch");
+                        return Err(TypeError::new(
+                            TypeErrorKind::KindMismatch(kind, found),
+                            ty.span,
+                        ));
+                    }
+                    if let Some(bound) = bound {
+                        check_bound(ctx, ty, &bound)?;
+                    }
+                    body.open(ty).normalize()
+                }
+                _ => {
+                    trace!("infer/leave: TypeApp=>: not polymorphic");
+                    return Err(TypeError::new(
+                        TypeErrorKind::NotPolymorphic(Ty::new(fty.kind, term.span)),
+                        term.span,
+                    ));
+                }
+            }
+        }
+        // Let=> (not in paper)
+        TermKind::Let(ann, val, body) => {
+            let bound = let_binding_ty(ctx, ann.as_ref(), val)?;
+            ctx.add_term_var(bound);
+            let res = infer(ctx, body)?;
+            let res = res.subst_ctx(ctx);
+            // Existentials created while inferring the body may still appear
+            // in its type, so only the binding itself goes out of scope.
+            ctx.remove_term_var(0);
+            res
+        }
+        // If=> (not in paper)
+        TermKind::If(cond, then, els) => {
+            check(ctx, cond, &Ty::new(TyKind::Bool, cond.span))?;
+            // Join the branches' types through a fresh existential.
+            let alpha = ctx.fresh_evar();
+            ctx.add_unsolved(alpha);
+            let res = Ty::new(TyKind::ExstVar(alpha), span);
+            check(ctx, then, &res)?;
+            check(ctx, els, &res.clone().subst_ctx(ctx))?;
+            res
+        }
+        // Fix=> (not in paper)
+        TermKind::Fix(Some(ann), body) => {
+            check_wellformed(ctx, ann)?;
+            ctx.add_term_var(ann.clone());
+            check(ctx, body, ann)?;
+            ctx.drop_after_term_var(0);
+            ann.clone()
+        }
+        TermKind::Fix(None, _) => {
+            trace!("infer/leave: Fix=>: no annotation");
+            return Err(TypeError::new(TypeErrorKind::UnannotatedFix, span));
+        }
+        // MuI=> (not in paper)
+        //
+        // Which recursive type is meant can't be worked out from the term.
+        TermKind::Fold(_) => {
+            trace!("infer/leave: MuI=>: no annotation");
+            return Err(TypeError::new(TypeErrorKind::UnannotatedFold, span));
+        }
+        // ∃I=> (not in paper)
+        TermKind::Pack(witness, packed, ann) => {
+            check_wellformed(ctx, witness)?;
+            check_wellformed(ctx, ann)?;
+            match &ann.kind {
+                TyKind::Exists(body) => check(ctx, packed, &body.as_ref().clone().open(witness))?,
+                _ => {
+                    trace!("infer/leave: ∃I=>: not an existential");
+                    return Err(TypeError::new(
+                        TypeErrorKind::NotExistential(ann.clone()),
+                        ann.span,
+                    ));
+                }
+            }
+            ann.clone()
+        }
+        // ∃E=> (not in paper)
+        //
+        // The abstract type is only in scope in the body, so it must not
+        // appear in the body's type.
+        TermKind::Unpack(packed, body) => {
+            let uvar = ctx.fresh_uvar();
+            let body = open_unpack(ctx, uvar, packed, body)?;
+            let res = infer(ctx, &body)?.subst_ctx(ctx);
+            let res = hoist_evars(ctx, uvar, res)?;
+            ctx.drop_after_uvar(uvar)
+                .ok_or_else(|| TypeError::new(TypeErrorKind::UnknownUvar(uvar), span))?;
+            if !res.is_wellformed_in(ctx) {
+                trace!("infer/leave: ∃E=>: abstract type escapes");
+                return Err(TypeError::new(TypeErrorKind::EscapingTyVar(res), body.span));
+            }
+            res
+        }
+        // MuE=> (not in paper)
+        TermKind::Unfold(term) => {
+            let ty = infer(ctx, term)?.subst_ctx(ctx);
+            let ty = expose(ctx, ty);
+            match &ty.kind {
+                TyKind::Mu(body) => body.as_ref().clone().open(&ty),
+                _ => {
+                    trace!("infer/leave: MuE=>: not a recursive type");
+                    return Err(TypeError::new(TypeErrorKind::NotRecursive(ty), term.span));
+                }
+            }
+        }
+        // ProdI=> (not in paper)
+        TermKind::Tuple(terms) => {
+            let mut tys = Vec::new();
+            for term in terms {
+                tys.push(infer(ctx, term)?);
             }
-            fty.subst_uvar0(ty)
+            Ty::new(TyKind::Product(tys), span).subst_ctx(ctx)
+        }
+        // ProdE=> (not in paper)
+        TermKind::Proj(term, idx) => {
+            let ty = infer(ctx, term)?.subst_ctx(ctx);
+            let ty = expose(ctx, ty);
+            match &ty.kind {
+                TyKind::Product(tys) if *idx < tys.len() => tys[*idx].clone(),
+                _ => {
+                    trace!("infer/leave: ProdE=>: no such component");
+                    return Err(TypeError::new(
+                        TypeErrorKind::NoSuchComponent(ty, *idx),
+                        term.span,
+                    ));
+                }
+            }
+        }
+        // LetProd=> (not in paper)
+        TermKind::LetTuple(len, val, body) => {
+            for ty in split_product(ctx, val, *len)? {
+                ctx.add_term_var(ty);
+            }
+            let res = infer(ctx, body)?.subst_ctx(ctx);
+            for _ in 0..*len {
+                ctx.remove_term_var(0);
+            }
+            res
+        }
+        // RecordI=> (not in paper)
+        TermKind::Record(fields) => {
+            let mut tys = Vec::new();
+            for (label, term) in fields {
+                tys.push((label.clone(), infer(ctx, term)?));
+            }
+            Ty::new(TyKind::Record(Row::closed(tys)), span).subst_ctx(ctx)
+        }
+        // RecordE=> (not in paper)
+        TermKind::Field(term, label) => {
+            let ty = infer(ctx, term)?.subst_ctx(ctx);
+            let ty = expose(ctx, ty);
+            field_ty(ctx, &ty, label)?.ok_or_else(|| {
+                trace!("infer/leave: RecordE=>: no such field");
+                TypeError::new(TypeErrorKind::NoSuchField(ty, label.clone()), term.span)
+            })?
+        }
+        // VariantI=> (not in paper)
+        //
+        // The other cases are left open, for the context to decide.
+        TermKind::Variant(label, inj) => {
+            let inj_ty = infer(ctx, inj)?;
+            let rho = ctx.fresh_evar();
+            ctx.add_unsolved_row(rho);
+            let row = Row::new(
+                vec![(label.clone(), inj_ty)],
+                Some(Ty::new(TyKind::ExstVar(rho), span)),
+            );
+            Ty::new(TyKind::Variant(row), span).subst_ctx(ctx)
+        }
+        // VariantE=> (not in paper)
+        TermKind::VariantCase(scrut, arms) => {
+            let alpha = ctx.fresh_evar();
+            ctx.add_unsolved(alpha);
+            let res = Ty::new(TyKind::ExstVar(alpha), span);
+            check_variant_case(ctx, scrut, arms, &res)?;
+            res.subst_ctx(ctx)
+        }
+        // SumIL=>; SumIR=> (not in paper)
+        //
+        // The other side of the sum is left for the context to decide.
+        TermKind::Inl(inj) | TermKind::Inr(inj) => {
+            let inj_ty = infer(ctx, inj)?;
+            let other = ctx.fresh_evar();
+            ctx.add_unsolved(other);
+            let other = Ty::new(TyKind::ExstVar(other), span);
+            let (left, right) = match &term.kind {
+                TermKind::Inl(_) => (inj_ty, other),
+                _ => (other, inj_ty),
+            };
+            Ty::new(TyKind::Sum(Box::new(left), Box::new(right)), span).subst_ctx(ctx)
+        }
+        // SumE=> (not in paper)
+        TermKind::Case(scrut, left, right) => {
+            // Join the branches' types through a fresh existential, as for
+            // `if`.
+            let alpha = ctx.fresh_evar();
+            ctx.add_unsolved(alpha);
+            let res = Ty::new(TyKind::ExstVar(alpha), span);
+            check_case(ctx, scrut, left, right, &res)?;
+            res.subst_ctx(ctx)
+        }
+        // CtorI=> (not in paper)
+        TermKind::Ctor(name, _) => match ctx.get_ctor(name) {
+            Some((data, idx)) => data.ctor_ty(idx, span),
+            None => {
+                trace!("infer/leave: CtorI=>: unknown constructor");
+                return Err(TypeError::new(
+                    TypeErrorKind::UnknownCtor(name.clone()),
+                    span,
+                ));
+            }
+        },
+        // Match=> (not in paper)
+        TermKind::Match(scrut, arms) => {
+            // Join the arms' types through a fresh existential, as for `if`.
+            let alpha = ctx.fresh_evar();
+            ctx.add_unsolved(alpha);
+            let res = Ty::new(TyKind::ExstVar(alpha), span);
+            check_match(ctx, scrut, arms, &res)?;
+            res.subst_ctx(ctx)
+        }
+        // ΛI=> (not in paper)
+        TermKind::TyAbs(kind, body) => {
+            let uvar = ctx.fresh_uvar();
+            ctx.add_uvar(uvar, kind.clone(), None);
+            let body = body
+                .as_ref()
+                .clone()
+                .open_ty(&Ty::new(TyKind::UnivVar(uvar), span));
+            let res = infer(ctx, &body)?.subst_ctx(ctx);
+            let res = generalize(ctx, uvar, kind.clone(), res)?;
+            ctx.drop_after_uvar(uvar)
+                .ok_or_else(|| TypeError::new(TypeErrorKind::UnknownUvar(uvar), span))?;
+            res
         }
     };
+    let res = res.normalize();
     trace!(?res, "infer/leave: ok");
-    Some(res)
+    Ok(res)
 }
 
 #[cfg_attr(feature = "trace", instrument(level = "trace", skip(ctx)))]
-pub fn check(ctx: &mut TyCtxt, term: &Term, ty: &Ty) -> Option<()> {
+pub fn check(ctx: &mut TyCtxt, term: &Term, ty: &Ty) -> Result<(), TypeError> {
     trace!("check/enter");
+    let ty = &ty.clone().normalize();
 
-    match (term, ty) {
+    match (&term.kind, &ty.kind) {
         // 1I; BoolI (not in paper)
-        (_, Ty::Unit | Ty::Bool) => (),
+        (TermKind::Unit, TyKind::Unit) | (TermKind::Bool(_), TyKind::Bool) => (),
+        // ΛI (not in paper)
+        (TermKind::TyAbs(_, term_body), TyKind::Forall(kind, bound, ty_body)) => {
+            let uvar = ctx.fresh_uvar();
+            ctx.add_uvar(uvar, kind.clone(), bound.as_deref().cloned());
+            let univ = ty.with_kind(TyKind::UnivVar(uvar));
+            let term_body = term_body.as_ref().clone().open_ty(&univ);
+            check(ctx, &term_body, &ty_body.clone().open(&univ))?;
+            ctx.drop_after_uvar(uvar)
+                .ok_or_else(|| TypeError::new(TypeErrorKind::UnknownUvar(uvar), term.span))?;
+        }
+        // ∃E (not in paper)
+        //
+        // `ty` is well-formed outside the body, so it can't mention the
+        // abstract type.
+        (TermKind::Unpack(packed, body), _) => {
+            let uvar = ctx.fresh_uvar();
+            let body = open_unpack(ctx, uvar, packed, body)?;
+            check(ctx, &body, &ty.clone().subst_ctx(ctx))?;
+            ctx.drop_after_uvar(uvar)
+                .ok_or_else(|| TypeError::new(TypeErrorKind::UnknownUvar(uvar), term.span))?;
+        }
+        // MuI (not in paper)
+        (TermKind::Fold(term), TyKind::Mu(body)) => {
+            check(ctx, term, &body.as_ref().clone().open(ty))?;
+        }
         // ∀I
-        (_, Ty::Forall(body)) => {
-            ctx.add_uvar();
-            check(ctx, term, &*body)?;
-            ctx.drop_after_uvar(0);
+        (_, TyKind::Forall(kind, bound, body)) => {
+            let uvar = ctx.fresh_uvar();
+            ctx.add_uvar(uvar, kind.clone(), bound.as_deref().cloned());
+            check(
+                ctx,
+                term,
+                &body.clone().open(&ty.with_kind(TyKind::UnivVar(uvar))),
+            )?;
+            ctx.drop_after_uvar(uvar)
+                .ok_or_else(|| TypeError::new(TypeErrorKind::UnknownUvar(uvar), term.span))?;
+        }
+        // ∧I for lambdas (not in paper)
+        //
+        // Lambdas can't have their types inferred, so they are checked
+        // against each side of an intersection in turn instead.
+        (TermKind::Lambda(..), TyKind::Intersection(left, right)) => {
+            check(ctx, term, left)?;
+            check(ctx, term, &right.clone().subst_ctx(ctx))?;
         }
         // ->I
-        (Term::Lambda(body), Ty::Arrow(inp, out)) => {
+        (TermKind::Lambda(None, body), TyKind::Arrow(inp, out)) => {
             ctx.add_term_var(*inp.clone());
-            check(ctx, &*body, &*out)?;
+            check(ctx, body, out)?;
+            ctx.drop_after_term_var(0);
+        }
+        // ->I with an annotated binder (not in paper)
+        (TermKind::Lambda(Some(ann), body), TyKind::Arrow(inp, out)) => {
+            check_wellformed(ctx, ann)?;
+            // The lambda must accept every argument the expected type allows.
+            let inp = inp.clone().subst_ctx(ctx);
+            subtype(ctx, &inp, ann).map_err(|err| TypeError::new(err.kind, ann.span))?;
+            ctx.add_term_var(ann.clone());
+            let out = out.clone().subst_ctx(ctx);
+            check(ctx, body, &out)?;
             ctx.drop_after_term_var(0);
         }
+        // Let (not in paper)
+        (TermKind::Let(ann, val, body), _) => {
+            let bound = let_binding_ty(ctx, ann.as_ref(), val)?;
+            ctx.add_term_var(bound);
+            check(ctx, body, ty)?;
+            ctx.drop_after_term_var(0);
+        }
+        // ProdI (not in paper)
+        (TermKind::Tuple(terms), TyKind::Product(tys)) if terms.len() == tys.len() => {
+            for (term, ty) in terms.iter().zip(tys) {
+                check(ctx, term, &ty.clone().subst_ctx(ctx))?;
+            }
+        }
+        // LetProd (not in paper)
+        (TermKind::LetTuple(len, val, body), _) => {
+            for ty in split_product(ctx, val, *len)? {
+                ctx.add_term_var(ty);
+            }
+            check(ctx, body, &ty.clone().subst_ctx(ctx))?;
+            ctx.drop_after_term_var(len - 1);
+        }
+        // RecordI (not in paper)
+        //
+        // Fields the expected type doesn't mention are allowed, as with width
+        // subtyping, and are inferred.
+        (TermKind::Record(fields), TyKind::Record(row))
+            if row.tail.is_none()
+                && row
+                    .fields
+                    .iter()
+                    .all(|(label, _)| fields.iter().any(|(l, _)| l == label)) =>
+        {
+            for (label, term) in fields {
+                match row.get(label) {
+                    Some(ty) => check(ctx, term, &ty.clone().subst_ctx(ctx))?,
+                    None => {
+                        infer(ctx, term)?;
+                    }
+                }
+            }
+        }
+        // SumIL; SumIR (not in paper)
+        (TermKind::Inl(inj), TyKind::Sum(left, _)) => check(ctx, inj, left)?,
+        (TermKind::Inr(inj), TyKind::Sum(_, right)) => check(ctx, inj, right)?,
+        // âSumIL; âSumIR (not in paper)
+        //
+        // Splitting the existential up front means the injected term is
+        // checked rather than inferred.
+        (TermKind::Inl(inj) | TermKind::Inr(inj), TyKind::ExstVar(evar))
+            if ctx.contains_evar(*evar) =>
+        {
+            let (left, right) = split_sum_evar(ctx, *evar, ty)?;
+            match &term.kind {
+                TermKind::Inl(_) => check(ctx, inj, &left)?,
+                _ => check(ctx, inj, &right)?,
+            }
+        }
+        // VariantI (not in paper)
+        (TermKind::Variant(label, inj), TyKind::Variant(row)) if row.get(label).is_some() => {
+            check(ctx, inj, row.get(label).unwrap())?;
+        }
+        // VariantE (not in paper)
+        (TermKind::VariantCase(scrut, arms), _) => check_variant_case(ctx, scrut, arms, ty)?,
+        // SumE (not in paper)
+        (TermKind::Case(scrut, left, right), _) => check_case(ctx, scrut, left, right, ty)?,
+        // Match (not in paper)
+        (TermKind::Match(scrut, arms), _) => check_match(ctx, scrut, arms, ty)?,
+        // Fix (not in paper)
+        (TermKind::Fix(None, body), _) => {
+            ctx.add_term_var(ty.clone());
+            check(ctx, body, ty)?;
+            ctx.drop_after_term_var(0);
+        }
+        // If (not in paper)
+        (TermKind::If(cond, then, els), _) => {
+            check(ctx, cond, &Ty::new(TyKind::Bool, cond.span))?;
+            check(ctx, then, ty)?;
+            check(ctx, els, &ty.clone().subst_ctx(ctx))?;
+        }
         // Sub
         _ => {
             let tya = infer(ctx, term)?;
             let tya = tya.subst_ctx(ctx);
             let tyb = ty.clone().subst_ctx(ctx);
-            subtype(ctx, &tya, &tyb)?;
+            // Subtyping only knows about types, so point any errors at the
+            // term being checked.
+            subtype(ctx, &tya, &tyb).map_err(|err| TypeError::new(err.kind, term.span))?;
         }
     }
     trace!("check/leave: ok");
-    Some(())
+    Ok(())
+}
+
+/// The type a let-binding binds its variable at: the annotation, if there is
+/// one, so that polymorphic bindings stay polymorphic in the body; otherwise
+/// the inferred type of the bound value.
+fn let_binding_ty(ctx: &mut TyCtxt, ann: Option<&Ty>, val: &Term) -> Result<Ty, TypeError> {
+    match ann {
+        Some(ty) => {
+            check_wellformed(ctx, ty)?;
+            check(ctx, val, ty)?;
+            Ok(ty.clone())
+        }
+        None => Ok(infer(ctx, val)?.subst_ctx(ctx)),
+    }
+}
+
+/// Infer the type of a tuple being destructured into `len` components, and
+/// return the type of each component.
+fn split_product(ctx: &mut TyCtxt, val: &Term, len: usize) -> Result<Vec<Ty>, TypeError> {
+    let ty = infer(ctx, val)?.subst_ctx(ctx);
+    let ty = expose(ctx, ty);
+    match &ty.kind {
+        TyKind::Product(tys) if tys.len() == len => Ok(tys.clone()),
+        TyKind::ExstVar(evar) if ctx.contains_evar(*evar) => {
+            let tys = articulate(ctx, *evar, len, ty.span)?
+                .into_iter()
+                .map(|alpha| ty.with_kind(TyKind::ExstVar(alpha)))
+                .collect::<Vec<_>>();
+            ctx.solve_evar(*evar, ty.with_kind(TyKind::Product(tys.clone())))
+                .ok_or_else(|| TypeError::new(TypeErrorKind::UnknownEvar(*evar), ty.span))?;
+            Ok(tys)
+        }
+        _ => {
+            trace!("split_product: not a product of the right length");
+            Err(TypeError::new(TypeErrorKind::TupleArity(ty, len), val.span))
+        }
+    }
+}
+
+/// Check each branch of `case scrut of inl x -> left | inr y -> right`
+/// against `ty`.
+fn check_case(
+    ctx: &mut TyCtxt,
+    scrut: &Term,
+    left: &Term,
+    right: &Term,
+    ty: &Ty,
+) -> Result<(), TypeError> {
+    let scrut_ty = infer(ctx, scrut)?.subst_ctx(ctx);
+    let scrut_ty = expose(ctx, scrut_ty);
+    let (left_ty, right_ty) = match &scrut_ty.kind {
+        TyKind::Sum(left_ty, right_ty) => (*left_ty.clone(), *right_ty.clone()),
+        TyKind::ExstVar(evar) if ctx.contains_evar(*evar) => split_sum_evar(ctx, *evar, &scrut_ty)?,
+        _ => {
+            trace!("check_case: not a sum");
+            return Err(TypeError::new(TypeErrorKind::NotASum(scrut_ty), scrut.span));
+        }
+    };
+    ctx.add_term_var(left_ty);
+    check(ctx, left, &ty.clone().subst_ctx(ctx))?;
+    ctx.drop_after_term_var(0);
+    ctx.add_term_var(right_ty.subst_ctx(ctx));
+    check(ctx, right, &ty.clone().subst_ctx(ctx))?;
+    ctx.drop_after_term_var(0);
+    Ok(())
+}
+
+/// Check each arm of `case scrut of { <x = y> -> e | ... }` against `ty`.
+///
+/// The scrutinee must be a variant with no cases other than the arms'.
+fn check_variant_case(
+    ctx: &mut TyCtxt,
+    scrut: &Term,
+    arms: &[(String, Term)],
+    ty: &Ty,
+) -> Result<(), TypeError> {
+    let scrut_ty = infer(ctx, scrut)?.subst_ctx(ctx);
+    let mut arm_tys = Vec::new();
+    for _ in arms {
+        let alpha = ctx.fresh_evar();
+        ctx.add_unsolved(alpha);
+        arm_tys.push(Ty::new(TyKind::ExstVar(alpha), scrut.span));
+    }
+    let fields = arms
+        .iter()
+        .map(|(label, _)| label.clone())
+        .zip(arm_tys.iter().cloned())
+        .collect();
+    let expected = Ty::new(TyKind::Variant(Row::closed(fields)), scrut.span);
+    subtype(ctx, &scrut_ty, &expected).map_err(|err| TypeError::new(err.kind, scrut.span))?;
+    for ((_, body), arm_ty) in arms.iter().zip(arm_tys) {
+        ctx.add_term_var(arm_ty.subst_ctx(ctx));
+        check(ctx, body, &ty.clone().subst_ctx(ctx))?;
+        ctx.drop_after_term_var(0);
+    }
+    Ok(())
+}
+
+/// Check each arm of `match scrut with { p -> e | ... }` against `ty`, and
+/// that together the arms match every value of the scrutinee, each matching
+/// some value the arms before it don't.
+fn check_match(
+    ctx: &mut TyCtxt,
+    scrut: &Term,
+    arms: &[(Pattern, Term)],
+    ty: &Ty,
+) -> Result<(), TypeError> {
+    let scrut_ty = infer(ctx, scrut)?.subst_ctx(ctx);
+    let scrut_ty = expose(ctx, scrut_ty);
+    for (pat, body) in arms {
+        let mut bound = Vec::new();
+        bind_pattern(ctx, pat, &scrut_ty, &mut bound)?;
+        let len = bound.len();
+        for var_ty in bound {
+            ctx.add_term_var(var_ty.subst_ctx(ctx));
+        }
+        check(ctx, body, &ty.clone().subst_ctx(ctx))?;
+        if len > 0 {
+            ctx.drop_after_term_var(len - 1);
+        }
+    }
+    let scrut_ty = scrut_ty.subst_ctx(ctx);
+    let pats = arms.iter().map(|(pat, _)| pat).collect::<Vec<_>>();
+    exhaustiveness::check_arms(ctx, &scrut_ty, &pats, scrut.span)
+}
+
+/// Check that `pat` can match a term of type `ty`, and push the types of the
+/// variables it binds onto `bound`, in order.
+fn bind_pattern(
+    ctx: &mut TyCtxt,
+    pat: &Pattern,
+    ty: &Ty,
+    bound: &mut Vec<Ty>,
+) -> Result<(), TypeError> {
+    let (name, pats) = match &pat.kind {
+        PatternKind::Wildcard => return Ok(()),
+        PatternKind::Var => {
+            bound.push(ty.clone());
+            return Ok(());
+        }
+        PatternKind::Ctor(name, pats) => (name, pats),
+    };
+    let (data, idx) = match ctx.get_ctor(name) {
+        Some((data, idx)) => (data.clone(), idx),
+        None => {
+            trace!("bind_pattern: unknown constructor");
+            return Err(TypeError::new(
+                TypeErrorKind::UnknownCtor(name.clone()),
+                pat.span,
+            ));
+        }
+    };
+    let ty = expose(ctx, ty.clone().subst_ctx(ctx));
+    let args = match &ty.kind {
+        TyKind::Data(data_name, args) if data_name == &data.name => args.clone(),
+        TyKind::ExstVar(evar) if ctx.contains_evar(*evar) => {
+            let args = articulate(ctx, *evar, data.params.len(), ty.span)?
+                .into_iter()
+                .map(|alpha| ty.with_kind(TyKind::ExstVar(alpha)))
+                .collect::<Vec<_>>();
+            ctx.solve_evar(*evar, ty.with_kind(TyKind::Data(data.name, args.clone())))
+                .ok_or_else(|| TypeError::new(TypeErrorKind::UnknownEvar(*evar), ty.span))?;
+            args
+        }
+        _ => {
+            trace!("bind_pattern: not the constructor's data type");
+            return Err(TypeError::new(
+                TypeErrorKind::CtorMismatch(name.clone(), ty),
+                pat.span,
+            ));
+        }
+    };
+    for (pat, field) in pats.iter().zip(&data.ctors[idx].1) {
+        bind_pattern(ctx, pat, &field.clone().instantiate(&args), bound)?;
+    }
+    Ok(())
+}
+
+/// The type of the field `label` of a term of type `ty`, if it has one.
+///
+/// If `ty` is not known to be a record with that field, but could be, it is
+/// made one, with a fresh existential for the field's type.
+fn field_ty(ctx: &mut TyCtxt, ty: &Ty, label: &str) -> Result<Option<Ty>, TypeError> {
+    match &ty.kind {
+        TyKind::Record(row) => {
+            if let Some(field) = row.get(label) {
+                return Ok(Some(field.clone()));
+            }
+            match row.tail.as_deref().map(|tail| &tail.kind) {
+                Some(TyKind::ExstVar(rho)) if ctx.evar_kind(*rho) == Some(Kind::Row) => {
+                    let more = extend_row(ctx, *rho, &[label.to_string()], ty.span)?;
+                    Ok(more.get(label).cloned())
+                }
+                _ => Ok(None),
+            }
+        }
+        TyKind::ExstVar(evar) if ctx.evar_kind(*evar) == Some(Kind::Type) => {
+            let field = ctx.fresh_evar();
+            let rho = ctx.fresh_evar();
+            ctx.insert_unsolved_before_evar(*evar, field)
+                .ok_or_else(|| TypeError::new(TypeErrorKind::UnknownEvar(*evar), ty.span))?;
+            ctx.insert_unsolved_row_before_evar(*evar, rho)
+                .ok_or_else(|| TypeError::new(TypeErrorKind::UnknownEvar(*evar), ty.span))?;
+            let field = ty.with_kind(TyKind::ExstVar(field));
+            let row = Row::new(
+                vec![(label.to_string(), field.clone())],
+                Some(ty.with_kind(TyKind::ExstVar(rho))),
+            );
+            ctx.solve_evar(*evar, ty.with_kind(TyKind::Record(row)))
+                .ok_or_else(|| TypeError::new(TypeErrorKind::UnknownEvar(*evar), ty.span))?;
+            Ok(Some(field))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Solve an unsolved existential with a sum of two fresh existentials, and
+/// return its sides.
+fn split_sum_evar(ctx: &mut TyCtxt, evar: usize, ty: &Ty) -> Result<(Ty, Ty), TypeError> {
+    let alphas = articulate(ctx, evar, 2, ty.span)?;
+    let left = ty.with_kind(TyKind::ExstVar(alphas[0]));
+    let right = ty.with_kind(TyKind::ExstVar(alphas[1]));
+    ctx.solve_evar(
+        evar,
+        ty.with_kind(TyKind::Sum(Box::new(left.clone()), Box::new(right.clone()))),
+    )
+    .ok_or_else(|| TypeError::new(TypeErrorKind::UnknownEvar(evar), ty.span))?;
+    Ok((left, right))
+}
+
+/// Check that an annotation is well-formed in the current context.
+fn check_wellformed(ctx: &TyCtxt, ty: &Ty) -> Result<(), TypeError> {
+    if ty.is_wellformed_in(ctx) {
+        Ok(())
+    } else {
+        trace!("check_wellformed: annotation is not well-formed");
+        Err(TypeError::new(
+            TypeErrorKind::IllFormedAnn(ty.clone().subst_ctx(ctx)),
+            ty.span,
+        ))
+    }
+}
+
+/// Replace a bounded universal variable with its bound, until the type is
+/// no longer one, so that a term of that type can be taken apart like a term
+/// of its bound. This is the exposure of algorithmic F<:.
+fn expose(ctx: &TyCtxt, ty: Ty) -> Ty {
+    match &ty.kind {
+        TyKind::UnivVar(uvar) => match ctx.uvar_bound(*uvar) {
+            Some(bound) => expose(ctx, bound.clone().subst_ctx(ctx)),
+            None => ty,
+        },
+        _ => ty,
+    }
+}
+
+/// Quantify a type inferred under a universal variable over it.
+fn generalize(ctx: &mut TyCtxt, uvar: usize, kind: Kind, ty: Ty) -> Result<Ty, TypeError> {
+    let span = ty.span;
+    let body = hoist_evars(ctx, uvar, ty)?.close(uvar);
+    Ok(Ty::new(TyKind::Forall(kind, None, Box::new(body)), span))
+}
+
+/// Move the existentials in a type inferred under a universal variable that
+/// were created after it, and are still unsolved, in front of it, so that
+/// they (and the type) are still well-formed once it is out of scope.
+fn hoist_evars(ctx: &mut TyCtxt, uvar: usize, ty: Ty) -> Result<Ty, TypeError> {
+    let span = ty.span;
+    let mut evars = Vec::new();
+    ty.evars(&mut evars);
+    for evar in evars {
+        let outer = ctx
+            .slice_until_uvar(uvar)
+            .is_some_and(|outer| outer.contains_evar(evar));
+        if !outer {
+            let hoisted = ctx.fresh_evar();
+            let evar_kind = ctx.evar_kind(evar).unwrap_or(Kind::Type);
+            ctx.insert_unsolved_before_uvar(uvar, hoisted, evar_kind);
+            ctx.solve_evar(evar, Ty::new(TyKind::ExstVar(hoisted), span))
+                .ok_or_else(|| TypeError::new(TypeErrorKind::UnknownEvar(evar), span))?;
+        }
+    }
+    Ok(ty.subst_ctx(ctx))
+}
+
+/// Open the existential type of the term unpacked by `unpack`, binding its
+/// variable to a new universal variable `uvar` and the term variable to the
+/// contents, and return the body with both opened.
+fn open_unpack(
+    ctx: &mut TyCtxt,
+    uvar: usize,
+    packed: &Term,
+    body: &Term,
+) -> Result<Term, TypeError> {
+    let ty = infer(ctx, packed)?.subst_ctx(ctx);
+    let ty = expose(ctx, ty);
+    let ex_body = match &ty.kind {
+        TyKind::Exists(ex_body) => ex_body.as_ref().clone(),
+        _ => {
+            trace!("open_unpack: not an existential");
+            return Err(TypeError::new(
+                TypeErrorKind::NotExistential(ty),
+                packed.span,
+            ));
+        }
+    };
+    ctx.add_uvar(uvar, Kind::Type, None);
+    let univ = Ty::new(TyKind::UnivVar(uvar), body.span);
+    ctx.add_term_var(ex_body.open(&univ));
+    Ok(body.clone().open_ty(&univ))
 }
 
 #[cfg_attr(feature = "trace", instrument(level = "trace", skip(ctx)))]
-pub fn infer_appl(ctx: &mut TyCtxt, ty: &Ty, term: &Term) -> Option<Ty> {
+pub fn infer_appl(ctx: &mut TyCtxt, ty: &Ty, term: &Term) -> Result<Ty, TypeError> {
     trace!("infer_appl/enter");
-    let res = match ty {
+    let ty = &ty.clone().normalize();
+    let res = match &ty.kind {
         // ∀App
-        Ty::Forall(body) => {
+        //
+        // Once the argument has been checked, the existential must be within
+        // the bound, if there is one.
+        TyKind::Forall(kind, bound, body) => {
             let evar = ctx.fresh_evar();
-            ctx.add_unsolved(evar);
-            let body = body.clone().subst_uvar0_bare(&Ty::ExstVar(evar));
-            infer_appl(ctx, &body, term)?
+            ctx.add_unsolved_of_kind(evar, kind);
+            let alpha = ty.with_kind(TyKind::ExstVar(evar));
+            let body = body.clone().open(&alpha);
+            let res = infer_appl(ctx, &ty.with_kind(body.kind), term)?;
+            if let Some(bound) = bound {
+                check_bound(ctx, &alpha, bound)?;
+            }
+            res
+        }
+        // ∧App (not in paper)
+        //
+        // The left side is tried first, and the right side only if applying
+        // the left fails, with anything solved while trying it undone.
+        TyKind::Intersection(left, right) => {
+            let mut attempt = ctx.clone();
+            match infer_appl(&mut attempt, left, term) {
+                Ok(res) => {
+                    *ctx = attempt;
+                    res
+                }
+                Err(_) => infer_appl(ctx, right, term)?,
+            }
         }
         // âApp
-        Ty::ExstVar(evar) if ctx.contains_evar(*evar) => {
+        TyKind::ExstVar(evar) if ctx.contains_evar(*evar) => {
             let alpha2 = ctx.fresh_evar();
             let alpha1 = ctx.fresh_evar();
             ctx.insert_unsolved_before_evar(*evar, alpha2);
             ctx.insert_unsolved_before_evar(*evar, alpha1);
             ctx.solve_evar(
                 *evar,
-                Ty::Arrow(Box::new(Ty::ExstVar(alpha1)), Box::new(Ty::ExstVar(alpha2))),
-            )?;
-            check(ctx, term, &Ty::ExstVar(alpha1))?;
-            Ty::ExstVar(alpha2)
+                ty.with_kind(TyKind::Arrow(
+                    Box::new(Ty::new(TyKind::ExstVar(alpha1), term.span)),
+                    Box::new(ty.with_kind(TyKind::ExstVar(alpha2))),
+                )),
+            )
+            .ok_or_else(|| TypeError::new(TypeErrorKind::UnknownEvar(*evar), ty.span))?;
+            check(ctx, term, &Ty::new(TyKind::ExstVar(alpha1), term.span))?;
+            ty.with_kind(TyKind::ExstVar(alpha2))
         }
         // ->App
-        Ty::Arrow(inp, out) => {
-            check(ctx, term, &*inp)?;
+        TyKind::Arrow(inp, out) => {
+            check(ctx, term, inp)?;
             *out.clone()
         }
         _ => {
             trace!("infer_appl/leave: not inferrable");
-            return None;
+            return Err(TypeError::new(
+                TypeErrorKind::NotAFunction(ty.clone().subst_ctx(ctx)),
+                ty.span,
+            ));
         }
     };
     trace!(?res, "infer_appl/leave: ok");
-    Some(res)
+    Ok(res)
+}
+
+/// What checking a definition found out about it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CheckedDef {
+    pub ty: Ty,
+    /// Whether the definition is known to be strongly normalising: it does
+    /// not use `fix`, directly or through the definitions it refers to.
+    pub normalising: bool,
+}
+
+/// Check each definition in a program against its signature, in order,
+/// returning what was found out about each. Data declarations bring their
+/// types and constructors into scope for the declarations after them.
+///
+/// A definition that fails to check is still assumed to have its signature
+/// while checking the ones after it, so that every error is reported.
+pub fn check_program(
+    ctx: &mut TyCtxt,
+    program: &Program,
+) -> Result<Vec<CheckedDef>, Vec<TypeError>> {
+    let mut defs: Vec<CheckedDef> = Vec::new();
+    let mut errors = Vec::new();
+    for decl in &program.decls {
+        match &decl.kind {
+            DeclKind::Def(_, ty, body) => {
+                if ty.is_wellformed_in(ctx) {
+                    // Don't let anything from checking one definition leak
+                    // into the next.
+                    let marker = ctx.fresh_evar();
+                    ctx.add_marker(marker);
+                    if let Err(err) = check(ctx, body, ty) {
+                        errors.push(err);
+                    }
+                    ctx.drop_after_marker(marker);
+                } else {
+                    errors.push(TypeError::new(
+                        TypeErrorKind::IllFormedAnn(ty.clone()),
+                        ty.span,
+                    ));
+                }
+                ctx.add_term_var(ty.clone());
+                // Free variables of the body are earlier definitions, the most
+                // recent at index 0, or term variables that were already in
+                // `ctx`, which aren't known to be normalising.
+                let normalising = !body.uses_fix()
+                    && body.free_vars().into_iter().all(|idx| {
+                        defs.len()
+                            .checked_sub(idx + 1)
+                            .is_some_and(|idx| defs[idx].normalising)
+                    });
+                defs.push(CheckedDef {
+                    ty: ty.clone(),
+                    normalising,
+                });
+            }
+            DeclKind::Data(data) => ctx.add_data(data.clone()),
+            // Synonyms were expanded during lowering.
+            DeclKind::Type(_) => (),
+        }
+    }
+    if errors.is_empty() {
+        Ok(defs)
+    } else {
+        Err(errors)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grammar::ProgramParser;
+    use crate::span::Span;
+
+    fn program(source: &str) -> Program {
+        ProgramParser::new()
+            .parse(source)
+            .unwrap()
+            .into_core()
+            .unwrap()
+    }
+
+    /// Check a program, returning the kinds of the errors it has, if any.
+    fn check(source: &str) -> Result<(), Vec<TypeErrorKind>> {
+        check_program(&mut TyCtxt::default(), &program(source))
+            .map(|_| ())
+            .map_err(|errs| errs.into_iter().map(|err| err.kind).collect())
+    }
+
+    fn accepts(source: &str) -> bool {
+        check(source).is_ok()
+    }
+
+    #[test]
+    fn unbound_variables_are_reported_at_their_span() {
+        let term = Term::new(TermKind::Var(0), Span::new(3, 4));
+        assert_eq!(
+            infer(&mut TyCtxt::default(), &term),
+            Err(TypeError::new(TypeErrorKind::UnboundVar, Span::new(3, 4)))
+        );
+    }
+
+    #[test]
+    fn definitions_are_found_past_variables_already_in_context() {
+        // A variable bound outside the program, as by a lambda or a `let`,
+        // comes before every definition in the context.
+        let mut ctx = TyCtxt::default();
+        ctx.add_term_var(Ty::new(TyKind::Unit, Span::default()));
+        let program = program(
+            "def id : bool -> bool = \\b. b;
+             def t : bool = id true;",
+        );
+        assert!(check_program(&mut ctx, &program).is_ok());
+    }
+
+    #[test]
+    fn definitions_are_checked_against_their_signatures() {
+        assert!(accepts(
+            "def id : bool -> bool = \\x. x; def t : bool = id true;"
+        ));
+        assert!(!accepts(
+            "def id : bool -> bool = \\x. x; def t : unit = id true;"
+        ));
+        assert!(!accepts("def t : bool = ();"));
+    }
+
+    #[test]
+    fn conditionals_need_booleans() {
+        assert!(accepts(
+            "def f : bool -> bool = \\b. if b then false else true;"
+        ));
+        assert!(!accepts("def f : bool = if () then false else true;"));
+        assert!(!accepts("def f : bool = if true then false else ();"));
+    }
+
+    #[test]
+    fn recursive_definitions_are_not_normalising() {
+        let program = program(
+            "def loop : bool -> bool = fix f. \\b. f b;
+             def id : bool -> bool = \\b. b;
+             def uses : bool = loop (id true);",
+        );
+        let defs = check_program(&mut TyCtxt::default(), &program).unwrap();
+        let normalising = defs.iter().map(|def| def.normalising).collect::<Vec<_>>();
+        assert_eq!(normalising, [false, true, false]);
+        assert_eq!(
+            check("def f : bool = (fix g. \\b. b) true;"),
+            Err(vec![TypeErrorKind::UnannotatedFix])
+        );
+    }
+
+    #[test]
+    fn normalising_ignores_term_variables_already_in_context() {
+        let mut ctx = TyCtxt::default();
+        ctx.add_term_var(Ty::new(TyKind::Bool, Span::default()));
+        let defs = check_program(&mut ctx, &program("def f : bool = true;")).unwrap();
+        assert!(defs[0].normalising);
+    }
+
+    #[test]
+    fn tuples_are_projected_and_destructured() {
+        assert!(accepts(
+            "def f : bool * unit -> unit * bool = \\p. (p.1, p.0);"
+        ));
+        assert!(accepts(
+            "def f : bool * unit -> bool = \\p. let (x, y) = p in x;"
+        ));
+        assert!(matches!(
+            check("def f : bool * unit -> bool = \\p. p.2;").unwrap_err()[..],
+            [TypeErrorKind::NoSuchComponent(..)]
+        ));
+        assert!(matches!(
+            check("def f : bool * unit -> bool = \\p. let (x, y, z) = p in x;").unwrap_err()[..],
+            [TypeErrorKind::TupleArity(..)]
+        ));
+    }
+
+    #[test]
+    fn sums_are_taken_apart_by_case() {
+        assert!(accepts(
+            "def f : bool + unit -> bool = \\s. case s of inl b -> b | inr u -> false;"
+        ));
+        assert!(matches!(
+            check("def f : bool -> bool = \\s. case s of inl b -> b | inr u -> false;")
+                .unwrap_err()[..],
+            [TypeErrorKind::NotASum(..)]
+        ));
+    }
+
+    #[test]
+    fn records_have_the_fields_they_are_built_with() {
+        assert!(accepts(
+            "def f : { x : bool, y : unit } -> bool = \\r. r.x;
+             def t : bool = f { y = (), x = true };"
+        ));
+        assert!(matches!(
+            check("def f : { x : bool } -> bool = \\r. r.y;").unwrap_err()[..],
+            [TypeErrorKind::NoSuchField(..)]
+        ));
+    }
+
+    #[test]
+    fn row_variables_and_variants() {
+        assert!(accepts(
+            "def f : 'r => { x : bool | 'r } -> bool = \\r. r.x;
+             def g : bool = f { x = true, y = () };"
+        ));
+        assert!(accepts(
+            "def f : < a : bool, b : unit > -> bool = \\v. case v of { <a = x> -> x | <b = y> -> false };"
+        ));
+        assert!(!accepts(
+            "def f : < a : bool, b : unit > -> bool = \\v. case v of { <a = x> -> x };"
+        ));
+    }
+
+    #[test]
+    fn recursive_types_are_folded_and_unfolded() {
+        assert!(accepts(
+            "def one : mu 'l. unit + bool * 'l = fold (inr (true, fold (inl ())));
+             def head : (mu 'l. unit + bool * 'l) -> bool =
+                 \\xs. case unfold xs of inl u -> false | inr p -> p.0;"
+        ));
+        assert!(matches!(
+            check("def f : bool = unfold true;").unwrap_err()[..],
+            [TypeErrorKind::NotRecursive(..)]
+        ));
+    }
+
+    #[test]
+    fn existentials_hide_their_witness() {
+        assert!(accepts(
+            "def c : exists 'c. 'c * ('c -> 'c) * ('c -> bool) =
+                 pack [bool, (false, \\b. true, \\b. b)] as exists 'c. 'c * ('c -> 'c) * ('c -> bool);
+             def done : bool = unpack c as ['c, x] in x.2 (x.1 x.0);"
+        ));
+        assert!(matches!(
+            check(
+                "def c : exists 'c. 'c = pack [bool, true] as exists 'c. 'c;
+                 def leak : bool = (unpack c as ['c, x] in x, true).1;"
+            )
+            .unwrap_err()[..],
+            [TypeErrorKind::EscapingTyVar(..)]
+        ));
+    }
+
+    #[test]
+    fn constructors_are_polymorphic_functions() {
+        assert!(accepts(
+            "data List 'a = Nil | Cons 'a (List 'a);
+             def l : List bool = Cons true Nil;"
+        ));
+        assert!(!accepts(
+            "data List 'a = Nil | Cons 'a (List 'a);
+             def l : List bool = Cons () Nil;"
+        ));
+        assert!(matches!(
+            check(
+                "data List 'a = Nil | Cons 'a (List 'a);
+                 data Nat = Z | S Nat;
+                 def f : Nat -> bool = \\n. match n with { Nil -> true | _ -> false };"
+            )
+            .unwrap_err()[..],
+            [TypeErrorKind::CtorMismatch(..)]
+        ));
+    }
+
+    #[test]
+    fn operators_are_inferred_from_data_types() {
+        assert!(accepts(
+            "data List 'a = Nil | Cons 'a (List 'a);
+             data Wrap ('f : * -> *) 'a = MkWrap ('f 'a);
+             def w : Wrap List bool = MkWrap (Cons true Nil);"
+        ));
+        assert!(!accepts(
+            "data List 'a = Nil | Cons 'a (List 'a);
+             data Wrap ('f : * -> *) 'a = MkWrap ('f 'a);
+             def w : Wrap List unit = MkWrap (Cons true Nil);"
+        ));
+    }
+
+    #[test]
+    fn type_arguments_must_be_within_bounds() {
+        assert!(accepts(
+            "def getx : 'a <: { x : bool } => 'a -> 'a * bool = \\r. (r, r.x);
+             def p : { x : bool, y : unit } * bool = getx [{ x : bool, y : unit }] { x = true, y = () };"
+        ));
+        assert!(matches!(
+            check(
+                "def getx : 'a <: { x : bool } => 'a -> 'a * bool = \\r. (r, r.x);
+                 def p : unit * bool = getx [unit] ();"
+            )
+            .unwrap_err()[..],
+            [TypeErrorKind::OutOfBound(..)]
+        ));
+    }
+
+    #[test]
+    fn lambdas_are_checked_against_each_side_of_an_intersection() {
+        assert!(accepts(
+            "def id : (bool -> bool) & (unit -> unit) = \\x. x;
+             def u : unit = id ();"
+        ));
+        assert!(!accepts(
+            "def f : (bool -> bool) & (unit -> unit) = \\x. true;"
+        ));
+    }
+
+    #[test]
+    fn absurd_eliminates_never() {
+        assert!(accepts(
+            "def f : never -> bool = absurd;
+             def g : 'a => never -> 'a = \\n. absurd n;"
+        ));
+        assert!(!accepts("def f : bool = absurd true;"));
+    }
 }
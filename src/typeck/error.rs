@@ -0,0 +1,198 @@
+use std::fmt;
+
+use crate::ast::core::{Kind, Ty};
+use crate::pp::pp_core_ty;
+use crate::span::Span;
+
+/// An error produced by one of the typing judgements, along with the span of
+/// the term (or, failing that, the type) that it arose from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypeError {
+    pub kind: TypeErrorKind,
+    pub span: Span,
+}
+
+/// The kind of a [`TypeError`].
+///
+/// All types carried by an error have already had the typing context
+/// applied to them (see [`Ty::subst_ctx`]), so solved existentials are
+/// shown as their solutions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TypeErrorKind {
+    /// A term variable was not in the context.
+    UnboundVar,
+    /// The first type is not a subtype of the second.
+    NotSubtype(Box<Ty>, Box<Ty>),
+    /// Solving the existential with the type would create an infinite type.
+    Occurs(usize, Ty),
+    /// An annotation was not well-formed in the current context.
+    IllFormedAnn(Ty),
+    /// A type application was given a type that is not a well-formed monotype.
+    NotMonotype(Ty),
+    /// A term of this type was applied to an argument.
+    NotAFunction(Ty),
+    /// A term of this type was applied to a type argument.
+    NotPolymorphic(Ty),
+    /// A term of this type, which quantifies over a row, was applied to a
+    /// type argument.
+    RowQuantifier(Ty),
+    /// A quantifier bounded by the second type was instantiated with the
+    /// first, which is not a subtype of it.
+    OutOfBound(Box<Ty>, Box<Ty>),
+    /// A term quantified over a type variable of the first kind was applied
+    /// to a type argument of the second.
+    KindMismatch(Kind, Kind),
+    /// A term of this type was projected with an index it does not have.
+    NoSuchComponent(Ty, usize),
+    /// A term of this type was destructured as a tuple with this many
+    /// components.
+    TupleArity(Ty, usize),
+    /// A term of this type was scrutinised by a `case`.
+    NotASum(Ty),
+    /// A term of this type was accessed with a label it does not have.
+    NoSuchField(Ty, String),
+    /// A term of this type was `unfold`ed.
+    NotRecursive(Ty),
+    /// A term of this type was `unpack`ed, or this was the annotation on a
+    /// `pack`.
+    NotExistential(Ty),
+    /// The type of the body of an `unpack` mentioned the abstract type it
+    /// binds.
+    EscapingTyVar(Ty),
+    /// A constructor pattern was matched against a term of this type, which
+    /// is not the data type that declares it.
+    CtorMismatch(String, Ty),
+    /// The arms of a `match` did not cover the values matched by this
+    /// pattern.
+    NonExhaustive(String),
+    /// An arm of a `match` could never be reached, because the arms before it
+    /// match everything it does.
+    UnreachableArm,
+    /// The type of a `fix` without an annotation had to be inferred.
+    UnannotatedFix,
+    /// The type of a `fold` had to be inferred.
+    UnannotatedFold,
+    /// An existential was not in the context. This indicates a bug in the
+    /// typechecker rather than in the program being checked.
+    UnknownEvar(usize),
+    /// A universal variable was not in the context. This also indicates a
+    /// bug.
+    UnknownUvar(usize),
+    /// A constructor was not declared. Lowering only produces declared
+    /// constructors, so this also indicates a bug.
+    UnknownCtor(String),
+}
+
+impl TypeError {
+    pub fn new(kind: TypeErrorKind, span: Span) -> Self {
+        Self { kind, span }
+    }
+}
+
+impl fmt::Display for TypeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.kind.fmt(f)
+    }
+}
+
+impl fmt::Display for TypeErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let arena = pretty::Arena::new();
+        let ty = |ty: &Ty| {
+            pp_core_ty(ty.clone(), &arena)
+                .into_doc()
+                .pretty(80)
+                .to_string()
+        };
+        match self {
+            TypeErrorKind::UnboundVar => f.write_str("unbound variable"),
+            TypeErrorKind::NotSubtype(sub, sup) => {
+                write!(f, "`{}` is not a subtype of `{}`", ty(sub), ty(sup))
+            }
+            TypeErrorKind::Occurs(evar, t) => write!(
+                f,
+                "occurs check failed: `'__exst{}` occurs in `{}`",
+                evar,
+                ty(t)
+            ),
+            TypeErrorKind::IllFormedAnn(t) => write!(f, "ill-formed annotation `{}`", ty(t)),
+            TypeErrorKind::NotMonotype(t) => write!(
+                f,
+                "type application requires a well-formed monotype, found `{}`",
+                ty(t)
+            ),
+            TypeErrorKind::NotAFunction(t) => {
+                write!(f, "expected a function, found a term of type `{}`", ty(t))
+            }
+            TypeErrorKind::NotPolymorphic(t) => write!(
+                f,
+                "expected a polymorphic term, found a term of type `{}`",
+                ty(t)
+            ),
+            TypeErrorKind::RowQuantifier(t) => write!(
+                f,
+                "cannot apply a term of type `{}` to a type argument",
+                ty(t)
+            ),
+            TypeErrorKind::OutOfBound(arg, bound) => {
+                write!(f, "`{}` is not within its bound `{}`", ty(arg), ty(bound))
+            }
+            TypeErrorKind::KindMismatch(expected, found) => write!(
+                f,
+                "expected a type argument of kind `{}`, found one of kind `{}`",
+                expected, found
+            ),
+            TypeErrorKind::NoSuchComponent(t, idx) => {
+                write!(f, "no component {} on a term of type `{}`", idx, ty(t))
+            }
+            TypeErrorKind::TupleArity(t, len) => write!(
+                f,
+                "expected a tuple with {} components, found a term of type `{}`",
+                len,
+                ty(t)
+            ),
+            TypeErrorKind::NotASum(t) => {
+                write!(f, "expected a sum, found a term of type `{}`", ty(t))
+            }
+            TypeErrorKind::NoSuchField(t, label) => {
+                write!(f, "no field `{}` on a term of type `{}`", label, ty(t))
+            }
+            TypeErrorKind::NotRecursive(t) => write!(
+                f,
+                "expected a recursive type, found a term of type `{}`",
+                ty(t)
+            ),
+            TypeErrorKind::NotExistential(t) => {
+                write!(f, "expected an existential type, found `{}`", ty(t))
+            }
+            TypeErrorKind::EscapingTyVar(t) => write!(
+                f,
+                "the abstract type bound by `unpack` escapes its scope in `{}`",
+                ty(t)
+            ),
+            TypeErrorKind::CtorMismatch(ctor, t) => write!(
+                f,
+                "constructor `{}` cannot match a term of type `{}`",
+                ctor,
+                ty(t)
+            ),
+            TypeErrorKind::NonExhaustive(pat) => write!(f, "pattern `{}` not covered", pat),
+            TypeErrorKind::UnreachableArm => f.write_str("unreachable pattern"),
+            TypeErrorKind::UnannotatedFix => {
+                f.write_str("cannot infer the type of a recursive term")
+            }
+            TypeErrorKind::UnannotatedFold => f.write_str("cannot infer the type of a `fold`"),
+            TypeErrorKind::UnknownEvar(evar) => {
+                write!(f, "internal error: `'__exst{}` is not in context", evar)
+            }
+            TypeErrorKind::UnknownUvar(uvar) => {
+                write!(f, "internal error: `'__univ{}` is not in context", uvar)
+            }
+            TypeErrorKind::UnknownCtor(ctor) => {
+                write!(f, "internal error: constructor `{}` is not declared", ctor)
+            }
+        }
+    }
+}
+
+impl std::error::Error for TypeError {}
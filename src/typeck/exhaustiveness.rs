@@ -0,0 +1,275 @@
+//! Checking that the arms of a `match` are exhaustive and that none of them
+//! are unreachable, using the usefulness algorithm from Maranget's "Warnings
+//! for pattern matching".
+
+use std::fmt;
+
+use crate::ast::core::{Pattern, PatternKind, Ty, TyKind};
+use crate::ctx::TyCtxt;
+use crate::span::Span;
+
+use super::{TypeError, TypeErrorKind};
+
+/// A pattern with its variables forgotten, since they match anything just
+/// like wildcards do.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Pat {
+    Wild,
+    Ctor(String, Vec<Pat>),
+}
+
+impl From<&Pattern> for Pat {
+    fn from(pat: &Pattern) -> Self {
+        match &pat.kind {
+            PatternKind::Wildcard | PatternKind::Var => Pat::Wild,
+            PatternKind::Ctor(name, pats) => {
+                Pat::Ctor(name.clone(), pats.iter().map(Pat::from).collect())
+            }
+        }
+    }
+}
+
+impl fmt::Display for Pat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Pat::Wild => f.write_str("_"),
+            Pat::Ctor(name, pats) => {
+                f.write_str(name)?;
+                for pat in pats {
+                    match pat {
+                        Pat::Ctor(_, args) if !args.is_empty() => write!(f, " ({})", pat)?,
+                        _ => write!(f, " {}", pat)?,
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Check the patterns of a `match` whose scrutinee, at `span`, has type
+/// `ty`: each must match something the ones before it don't, and together
+/// they must match everything.
+pub fn check_arms(ctx: &TyCtxt, ty: &Ty, pats: &[&Pattern], span: Span) -> Result<(), TypeError> {
+    let tys = [ty.clone()];
+    let mut rows: Vec<Vec<Pat>> = Vec::new();
+    for pat in pats {
+        let row = vec![Pat::from(*pat)];
+        if useful(ctx, &rows, &tys, &row).is_none() {
+            return Err(TypeError::new(TypeErrorKind::UnreachableArm, pat.span));
+        }
+        rows.push(row);
+    }
+    match useful(ctx, &rows, &tys, &[Pat::Wild]) {
+        Some(witness) => Err(TypeError::new(
+            TypeErrorKind::NonExhaustive(witness[0].to_string()),
+            span,
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Whether the row of patterns `row` matches some values, of types `tys`,
+/// that none of `rows` do. If so, return such values, as patterns.
+fn useful(ctx: &TyCtxt, rows: &[Vec<Pat>], tys: &[Ty], row: &[Pat]) -> Option<Vec<Pat>> {
+    let (head, rest) = match row.split_first() {
+        Some(split) => split,
+        None => {
+            return if rows.is_empty() {
+                Some(Vec::new())
+            } else {
+                None
+            }
+        }
+    };
+    match head {
+        Pat::Ctor(name, args) => {
+            let row = args.iter().chain(rest).cloned().collect::<Vec<_>>();
+            let witness = useful(
+                ctx,
+                &specialize(rows, name, args.len()),
+                &field_tys(ctx, tys, name),
+                &row,
+            )?;
+            Some(rebuild(name, args.len(), witness))
+        }
+        Pat::Wild => {
+            let heads = rows
+                .iter()
+                .filter_map(|row| match &row[0] {
+                    Pat::Ctor(name, _) => Some(name),
+                    Pat::Wild => None,
+                })
+                .collect::<Vec<_>>();
+            let data = match &tys[0].kind {
+                TyKind::Data(name, _) => ctx.get_data(name),
+                _ => None,
+            };
+            match data {
+                // Every constructor is matched by some row, so the values
+                // `row` matches are those it matches for some constructor.
+                Some(data) if data.ctors.iter().all(|(name, _)| heads.contains(&name)) => {
+                    data.ctors.iter().find_map(|(name, fields)| {
+                        let row = vec![Pat::Wild; fields.len()]
+                            .into_iter()
+                            .chain(rest.iter().cloned())
+                            .collect::<Vec<_>>();
+                        let witness = useful(
+                            ctx,
+                            &specialize(rows, name, fields.len()),
+                            &field_tys(ctx, tys, name),
+                            &row,
+                        )?;
+                        Some(rebuild(name, fields.len(), witness))
+                    })
+                }
+                // Otherwise, the rows that start with a constructor can't
+                // match the values that start with one they don't mention.
+                _ => {
+                    let rows = rows
+                        .iter()
+                        .filter(|row| row[0] == Pat::Wild)
+                        .map(|row| row[1..].to_vec())
+                        .collect::<Vec<_>>();
+                    let mut witness = useful(ctx, &rows, &tys[1..], rest)?;
+                    let head = match data {
+                        Some(data) if !heads.is_empty() => {
+                            let (name, fields) = data
+                                .ctors
+                                .iter()
+                                .find(|(name, _)| !heads.contains(&name))
+                                .expect("some constructor is missing");
+                            Pat::Ctor(name.clone(), vec![Pat::Wild; fields.len()])
+                        }
+                        _ => Pat::Wild,
+                    };
+                    witness.insert(0, head);
+                    Some(witness)
+                }
+            }
+        }
+    }
+}
+
+/// The rows that match values starting with the constructor `name`, which
+/// has `arity` fields, with their first pattern replaced by patterns for
+/// those fields.
+fn specialize(rows: &[Vec<Pat>], name: &str, arity: usize) -> Vec<Vec<Pat>> {
+    rows.iter()
+        .filter_map(|row| {
+            let args = match &row[0] {
+                Pat::Ctor(head, args) if head == name => args.clone(),
+                Pat::Ctor(..) => return None,
+                Pat::Wild => vec![Pat::Wild; arity],
+            };
+            Some(args.into_iter().chain(row[1..].iter().cloned()).collect())
+        })
+        .collect()
+}
+
+/// The types of the columns after specializing the first one, of types
+/// `tys`, to the constructor `name`.
+fn field_tys(ctx: &TyCtxt, tys: &[Ty], name: &str) -> Vec<Ty> {
+    let fields = match (ctx.get_ctor(name), &tys[0].kind) {
+        (Some((data, idx)), TyKind::Data(_, args)) => data.ctors[idx]
+            .1
+            .iter()
+            .map(|field| field.clone().instantiate(args))
+            .collect(),
+        // Typechecking the patterns has made the type a data type, so this
+        // only happens if it failed; the fields' types then stay unknown.
+        (Some((data, idx)), _) => vec![tys[0].clone(); data.ctors[idx].1.len()],
+        (None, _) => Vec::new(),
+    };
+    fields.into_iter().chain(tys[1..].iter().cloned()).collect()
+}
+
+/// Undo [`specialize`] on a row of witnesses.
+fn rebuild(name: &str, arity: usize, mut witness: Vec<Pat>) -> Vec<Pat> {
+    let rest = witness.split_off(arity);
+    std::iter::once(Pat::Ctor(name.to_string(), witness))
+        .chain(rest)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ctx::TyCtxt;
+    use crate::grammar::ProgramParser;
+    use crate::typeck::{check_program, TypeErrorKind};
+
+    const DATA: &str = "
+        data Void;
+        data Nat = Z | S Nat;
+        data List 'a = Nil | Cons 'a (List 'a);
+    ";
+
+    /// Check a definition of `f : List bool -> bool`, whose body is a match
+    /// on its argument with the given arms.
+    fn check_arms(arms: &str) -> Result<(), Vec<TypeErrorKind>> {
+        check(&format!(
+            "def f : List bool -> bool = \\xs. match xs with {{ {} }};",
+            arms
+        ))
+    }
+
+    fn check(def: &str) -> Result<(), Vec<TypeErrorKind>> {
+        let source = format!("{}{}", DATA, def);
+        let program = ProgramParser::new()
+            .parse(&source)
+            .unwrap()
+            .into_core()
+            .unwrap();
+        check_program(&mut TyCtxt::default(), &program)
+            .map(|_| ())
+            .map_err(|errs| errs.into_iter().map(|err| err.kind).collect())
+    }
+
+    #[test]
+    fn exhaustive_matches_are_accepted() {
+        assert_eq!(check_arms("Nil -> true | Cons _ _ -> false"), Ok(()));
+        assert_eq!(
+            check_arms("Cons _ (Cons _ _) -> true | Cons x Nil -> x | Nil -> false"),
+            Ok(())
+        );
+        assert_eq!(check_arms("Nil -> true | xs -> false"), Ok(()));
+    }
+
+    #[test]
+    fn missing_constructors_are_reported() {
+        assert_eq!(
+            check_arms("Nil -> true"),
+            Err(vec![TypeErrorKind::NonExhaustive("Cons _ _".to_string())])
+        );
+        assert_eq!(
+            check_arms("Cons x Nil -> x | Nil -> false"),
+            Err(vec![TypeErrorKind::NonExhaustive(
+                "Cons _ (Cons _ _)".to_string()
+            )])
+        );
+    }
+
+    #[test]
+    fn unreachable_arms_are_reported() {
+        assert_eq!(
+            check_arms("_ -> true | Nil -> false"),
+            Err(vec![TypeErrorKind::UnreachableArm])
+        );
+        assert_eq!(
+            check_arms("Cons _ _ -> true | Nil -> false | Cons _ Nil -> false"),
+            Err(vec![TypeErrorKind::UnreachableArm])
+        );
+    }
+
+    #[test]
+    fn empty_matches_need_empty_types() {
+        assert_eq!(
+            check("def f : Void -> bool = \\v. match v with {};"),
+            Ok(())
+        );
+        assert_eq!(
+            check("def f : Nat -> bool = \\n. match n with {};"),
+            Err(vec![TypeErrorKind::NonExhaustive("_".to_string())])
+        );
+    }
+}
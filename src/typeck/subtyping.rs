@@ -1,175 +1,1249 @@
-use crate::ast::core::Ty;
+use crate::ast::core::{Kind, Row, Ty, TyKind};
 use crate::ctx::TyCtxt;
+use crate::span::Span;
+
+use super::{TypeError, TypeErrorKind};
 
 use crate::trace;
 #[cfg(feature = "trace")]
 use tracing::instrument;
 
 #[cfg_attr(feature = "trace", instrument(level = "trace", skip(ctx)))]
-pub fn subtype(ctx: &mut TyCtxt, ty1: &Ty, ty2: &Ty) -> Option<()> {
+pub fn subtype(ctx: &mut TyCtxt, ty1: &Ty, ty2: &Ty) -> Result<(), TypeError> {
     trace!("subtype/enter");
-    match (ty1, ty2) {
+    // Types are compared up to beta-reduction.
+    let ty1 = &ty1.clone().normalize();
+    let ty2 = &ty2.clone().normalize();
+    match (&ty1.kind, &ty2.kind) {
         // <:Var
-        (Ty::Var(idx1), Ty::Var(idx2)) if idx1 == idx2 && ctx.contains_uvar(*idx1) => (),
+        (TyKind::UnivVar(uvar1), TyKind::UnivVar(uvar2))
+            if uvar1 == uvar2 && ctx.contains_uvar(*uvar1) => {}
         // <:Unit; <:Bool (not in paper)
-        (Ty::Unit, Ty::Unit) | (Ty::Bool, Ty::Bool) => (),
+        (TyKind::Unit, TyKind::Unit) | (TyKind::Bool, TyKind::Bool) => (),
+        // <:Top; <:Never (not in paper)
+        //
+        // These come before the rules that instantiate existentials, so that
+        // `â <: top` and `never <: â` hold without solving `â`. Any solution
+        // would do, and committing to one here could rule out the one a
+        // later constraint needs.
+        (_, TyKind::Top) | (TyKind::Never, _) => (),
         // <:Exvar
-        (Ty::ExstVar(idx1), Ty::ExstVar(idx2)) if idx1 == idx2 && ctx.contains_evar(*idx1) => {}
+        (TyKind::ExstVar(idx1), TyKind::ExstVar(idx2))
+            if idx1 == idx2 && ctx.contains_evar(*idx1) => {}
         // <:->
-        (Ty::Arrow(a1, a2), Ty::Arrow(b1, b2)) => {
-            subtype(ctx, &*b1, &*a1)?;
+        (TyKind::Arrow(a1, a2), TyKind::Arrow(b1, b2)) => {
+            subtype(ctx, b1, a1)?;
+            let a2 = a2.clone().subst_ctx(ctx);
+            let b2 = b2.clone().subst_ctx(ctx);
+            subtype(ctx, &a2, &b2)?;
+        }
+        // <:Prod (not in paper)
+        (TyKind::Product(as_), TyKind::Product(bs)) if as_.len() == bs.len() => {
+            for (a, b) in as_.iter().zip(bs) {
+                let a = a.clone().subst_ctx(ctx);
+                let b = b.clone().subst_ctx(ctx);
+                subtype(ctx, &a, &b)?;
+            }
+        }
+        // <:Sum (not in paper)
+        (TyKind::Sum(a1, a2), TyKind::Sum(b1, b2)) => {
+            subtype(ctx, a1, b1)?;
             let a2 = a2.clone().subst_ctx(ctx);
             let b2 = b2.clone().subst_ctx(ctx);
             subtype(ctx, &a2, &b2)?;
         }
+        // <:Record (not in paper)
+        //
+        // Width and depth subtyping: the subtype must have every field of the
+        // supertype, at a subtype of the supertype's field, and may have more.
+        (TyKind::Record(a), TyKind::Record(b)) => subtype_rows(ctx, a, b, true, false, ty1, ty2)?,
+        // <:Variant (not in paper)
+        //
+        // The other way round: the supertype must have every case of the
+        // subtype.
+        (TyKind::Variant(a), TyKind::Variant(b)) => {
+            subtype_rows(ctx, b, a, false, false, ty1, ty2)?
+        }
+        // <:Mu (not in paper)
+        //
+        // Recursive types are only related when they are equal: the bodies,
+        // opened with the same universal variable, must be subtypes of each
+        // other both ways. This is sound, but weaker than the Amber rule, which
+        // would relate the bodies covariantly under the assumption that the
+        // bound variables are too.
+        (TyKind::Mu(a), TyKind::Mu(b)) => {
+            let uvar = ctx.fresh_uvar();
+            ctx.add_uvar(uvar, Kind::Type, None);
+            let a = a.clone().open(&ty1.with_kind(TyKind::UnivVar(uvar)));
+            let b = b.clone().open(&ty2.with_kind(TyKind::UnivVar(uvar)));
+            subtype(ctx, &a, &b).map_err(|_| not_subtype(ctx, ty1, ty2))?;
+            let a = a.subst_ctx(ctx);
+            let b = b.subst_ctx(ctx);
+            subtype(ctx, &b, &a).map_err(|_| not_subtype(ctx, ty1, ty2))?;
+            ctx.drop_after_uvar(uvar)
+                .ok_or_else(|| unknown_uvar(uvar, ty1.span))?;
+        }
+        // <:Data (not in paper)
+        //
+        // Data types are nominal, and invariant in their parameters.
+        (TyKind::Data(a, as_), TyKind::Data(b, bs)) if a == b && as_.len() == bs.len() => {
+            for (a, b) in as_.iter().zip(bs) {
+                let a = a.clone().subst_ctx(ctx);
+                let b = b.clone().subst_ctx(ctx);
+                equate(ctx, &a, &b).map_err(|_| not_subtype(ctx, ty1, ty2))?;
+            }
+        }
+        // <:App (not in paper)
+        //
+        // A normalised application is headed by a variable, so it is only
+        // equal to another application when their heads and arguments are.
+        (TyKind::App(f1, a1), TyKind::App(f2, a2)) => {
+            equate(ctx, f1, f2).map_err(|_| not_subtype(ctx, ty1, ty2))?;
+            let a1 = a1.clone().subst_ctx(ctx);
+            let a2 = a2.clone().subst_ctx(ctx);
+            equate(ctx, &a1, &a2).map_err(|_| not_subtype(ctx, ty1, ty2))?;
+        }
+        // <:AppData (not in paper)
+        //
+        // A data type applied to arguments is also the data type applied to
+        // all but its last argument, applied to that one. Only this way of
+        // splitting it is tried, as in Haskell.
+        (TyKind::App(func, arg), TyKind::Data(name, args))
+        | (TyKind::Data(name, args), TyKind::App(func, arg))
+            if !args.is_empty() =>
+        {
+            let (partial, last) = split_data(ctx, name, args, ty1, ty2)?;
+            equate(ctx, func, &partial).map_err(|_| not_subtype(ctx, ty1, ty2))?;
+            let arg = arg.clone().subst_ctx(ctx);
+            let last = last.subst_ctx(ctx);
+            equate(ctx, &arg, &last).map_err(|_| not_subtype(ctx, ty1, ty2))?;
+        }
+        // <:Lam (not in paper)
+        //
+        // Type operators are related when their bodies are equal, opened with
+        // the same universal variable.
+        (TyKind::Lam(k1, a), TyKind::Lam(k2, b)) if k1 == k2 => {
+            let uvar = ctx.fresh_uvar();
+            ctx.add_uvar(uvar, k1.clone(), None);
+            let a = a.clone().open(&ty1.with_kind(TyKind::UnivVar(uvar)));
+            let b = b.clone().open(&ty2.with_kind(TyKind::UnivVar(uvar)));
+            equate(ctx, &a, &b).map_err(|_| not_subtype(ctx, ty1, ty2))?;
+            ctx.drop_after_uvar(uvar)
+                .ok_or_else(|| unknown_uvar(uvar, ty1.span))?;
+        }
+        // <:∧R (not in paper)
+        //
+        // This and <:∨L are invertible, so they are tried before the rules
+        // for quantifiers. An existential is instead solved with the whole
+        // intersection (or union) by <:InstantiateL (or R).
+        (_, TyKind::Intersection(b1, b2)) if !matches!(ty1.kind, TyKind::ExstVar(_)) => {
+            subtype(ctx, ty1, b1).map_err(|_| not_subtype(ctx, ty1, ty2))?;
+            let a = ty1.clone().subst_ctx(ctx);
+            let b2 = b2.clone().subst_ctx(ctx);
+            subtype(ctx, &a, &b2).map_err(|_| not_subtype(ctx, ty1, ty2))?;
+        }
+        // <:∨L (not in paper)
+        (TyKind::Union(a1, a2), _) if !matches!(ty2.kind, TyKind::ExstVar(_)) => {
+            subtype(ctx, a1, ty2).map_err(|_| not_subtype(ctx, ty1, ty2))?;
+            let a2 = a2.clone().subst_ctx(ctx);
+            let b = ty2.clone().subst_ctx(ctx);
+            subtype(ctx, &a2, &b).map_err(|_| not_subtype(ctx, ty1, ty2))?;
+        }
+        // <:∀∀Bounded (kernel F<:)
+        //
+        // Two bounded quantifiers are only related when their bounds are
+        // equal, and their bodies are compared under that bound. Full F<:
+        // would allow the right bound to be a subtype of the left one, but
+        // that makes subtyping undecidable, and going through <:∀R and <:∀L
+        // as for other foralls would amount to that rule.
+        (TyKind::Forall(k1, Some(bound1), a), TyKind::Forall(k2, Some(bound2), b)) if k1 == k2 => {
+            equate(ctx, bound1, bound2).map_err(|_| not_subtype(ctx, ty1, ty2))?;
+            let bound = bound1.as_ref().clone().subst_ctx(ctx);
+            let uvar = ctx.fresh_uvar();
+            ctx.add_uvar(uvar, k1.clone(), Some(bound));
+            let a = a.clone().open(&ty1.with_kind(TyKind::UnivVar(uvar)));
+            let b = b.clone().open(&ty2.with_kind(TyKind::UnivVar(uvar)));
+            let a = a.subst_ctx(ctx);
+            let b = b.subst_ctx(ctx);
+            subtype(ctx, &a, &b)?;
+            ctx.drop_after_uvar(uvar)
+                .ok_or_else(|| unknown_uvar(uvar, ty1.span))?;
+        }
+        // Kernel F<: has no rule relating a bounded quantifier to an
+        // unbounded one. Going through <:∀R and <:∀L would instantiate one
+        // with the variable of the other, or with some type within its bound,
+        // and so accept more than kernel F<: does, so they are never related.
+        (TyKind::Forall(_, bound1, _), TyKind::Forall(_, bound2, _))
+            if bound1.is_some() != bound2.is_some() =>
+        {
+            return Err(not_subtype(ctx, ty1, ty2));
+        }
+        // <:∀R
+        //
+        // This overlaps with <:∀L when both sides are foralls. <:∀R is
+        // invertible, so it has to be tried first: otherwise the left forall
+        // is instantiated with an existential that cannot later be solved
+        // with the right forall's universal variable.
+        (_, TyKind::Forall(kind, bound, b)) => {
+            // Put a new uvar in context
+            let uvar = ctx.fresh_uvar();
+            ctx.add_uvar(uvar, kind.clone(), bound.as_deref().cloned());
+            let b = b.clone().open(&ty2.with_kind(TyKind::UnivVar(uvar)));
+            subtype(ctx, ty1, &b)?;
+            // Drop everything after that uvar at the end.
+            ctx.drop_after_uvar(uvar)
+                .ok_or_else(|| unknown_uvar(uvar, ty2.span))?;
+        }
+        // <:∃L (from the 2019 paper)
+        //
+        // Like <:∀R, this is invertible, so it is tried before <:∀L.
+        (TyKind::Exists(a), _) => {
+            let uvar = ctx.fresh_uvar();
+            ctx.add_uvar(uvar, Kind::Type, None);
+            let a = a.clone().open(&ty1.with_kind(TyKind::UnivVar(uvar)));
+            subtype(ctx, &a, ty2)?;
+            ctx.drop_after_uvar(uvar)
+                .ok_or_else(|| unknown_uvar(uvar, ty1.span))?;
+        }
         // <:∀L
-        (Ty::Forall(a), b) => {
+        //
+        // The existential must also end up within the bound, if there is one.
+        (TyKind::Forall(kind, bound, a), _) => {
             // Get a fresh evar index.
             let evar = ctx.fresh_evar();
             ctx.add_marker(evar);
-            ctx.add_unsolved(evar);
-            let a = a.clone().subst_uvar0_bare(&Ty::ExstVar(evar));
-            subtype(ctx, &a, b)?;
+            ctx.add_unsolved_of_kind(evar, kind);
+            let alpha = ty1.with_kind(TyKind::ExstVar(evar));
+            let a = a.clone().open(&alpha);
+            subtype(ctx, &a, ty2)?;
+            if let Some(bound) = bound {
+                check_bound(ctx, &alpha, bound)?;
+            }
             // Drop everything after the evar at the end.
-            ctx.drop_after_marker(evar)?;
+            ctx.drop_after_marker(evar)
+                .ok_or_else(|| unknown_evar(evar, ty1.span))?;
         }
-        // <:∀R
-        (a, Ty::Forall(b)) => {
-            // Put a new uvar in context
-            ctx.add_uvar();
-            subtype(ctx, a, b)?;
-            // Drop everything after that uvar at the end.
-            ctx.drop_after_uvar(0);
+        // <:∃R (from the 2019 paper)
+        (_, TyKind::Exists(b)) => {
+            let evar = ctx.fresh_evar();
+            ctx.add_marker(evar);
+            ctx.add_unsolved(evar);
+            let b = b.clone().open(&ty2.with_kind(TyKind::ExstVar(evar)));
+            subtype(ctx, ty1, &b)?;
+            ctx.drop_after_marker(evar)
+                .ok_or_else(|| unknown_evar(evar, ty2.span))?;
         }
         // <:InstantiateL
-        (Ty::ExstVar(evar), ty) => {
-            if ty.contains_evar(*evar) {
+        (TyKind::ExstVar(evar), _) => {
+            if ty2.contains_evar(*evar) {
                 trace!("subtype/leave: <:IL: occurs");
-                return None;
+                return Err(TypeError::new(
+                    TypeErrorKind::Occurs(*evar, ty2.clone().subst_ctx(ctx)),
+                    ty2.span,
+                ));
             }
-            inst_left(ctx, *evar, ty)?;
+            inst_left(ctx, *evar, ty2)?;
         }
         // <:InstantiateR
-        (ty, Ty::ExstVar(evar)) => {
-            if ty.contains_evar(*evar) {
+        (_, TyKind::ExstVar(evar)) => {
+            if ty1.contains_evar(*evar) {
                 trace!("subtype/leave: <:IR: occurs");
-                return None;
+                return Err(TypeError::new(
+                    TypeErrorKind::Occurs(*evar, ty1.clone().subst_ctx(ctx)),
+                    ty1.span,
+                ));
             }
-            inst_right(ctx, ty, *evar)?;
+            inst_right(ctx, ty1, *evar)?;
         }
+        // <:∧L; <:∨R; <:Var, through the bound (not in paper)
+        //
+        // None of these are invertible, so each way they could apply is
+        // tried in turn (see `first_subtype`). They are tried last, so that
+        // existentials are solved with whole intersections, unions and
+        // bounded variables, rather than with parts of them or their bounds.
         _ => {
-            trace!("subtype/leave: not a subtype");
-            return None;
+            if !first_subtype(ctx, alternatives(ctx, ty1, ty2)) {
+                trace!("subtype/leave: not a subtype");
+                return Err(not_subtype(ctx, ty1, ty2));
+            }
         }
     }
 
     trace!("subtype/leave: ok");
-    Some(())
+    Ok(())
+}
+
+/// The subtyping judgements that `ty1 <: ty2` follows from by <:∧L, <:∨R or
+/// a bounded variable's bound, in the order they should be tried.
+fn alternatives(ctx: &TyCtxt, ty1: &Ty, ty2: &Ty) -> Vec<(Ty, Ty)> {
+    let mut alternatives = Vec::new();
+    if let TyKind::Union(b1, b2) = &ty2.kind {
+        alternatives.push((ty1.clone(), *b1.clone()));
+        alternatives.push((ty1.clone(), *b2.clone()));
+    }
+    match &ty1.kind {
+        TyKind::Intersection(a1, a2) => {
+            alternatives.push((*a1.clone(), ty2.clone()));
+            alternatives.push((*a2.clone(), ty2.clone()));
+        }
+        TyKind::UnivVar(uvar) => {
+            if let Some(bound) = ctx.uvar_bound(*uvar) {
+                alternatives.push((bound.clone().subst_ctx(ctx), ty2.clone()));
+            }
+        }
+        _ => (),
+    }
+    alternatives
+}
+
+/// Check the first of `alternatives` that holds, and whether there was one.
+///
+/// Solving existentials is order-sensitive, so each alternative is checked
+/// against a copy of `ctx` that is thrown away if it fails. Once one
+/// succeeds, the existentials it solved stay solved, even if a later
+/// judgement would have needed them solved the way another alternative
+/// would have. Backtracking is local to each judgement, which keeps it from
+/// being exponential in the size of the whole program, at the cost of this
+/// incompleteness.
+fn first_subtype(ctx: &mut TyCtxt, alternatives: Vec<(Ty, Ty)>) -> bool {
+    alternatives.into_iter().any(|(sub, sup)| {
+        let mut attempt = ctx.clone();
+        let ok = subtype(&mut attempt, &sub, &sup).is_ok();
+        if ok {
+            *ctx = attempt;
+        }
+        ok
+    })
 }
 
 // evar :<= ty
 #[cfg_attr(feature = "trace", instrument(level = "trace", skip(ctx)))]
-pub fn inst_left(ctx: &mut TyCtxt, evar: usize, ty: &Ty) -> Option<()> {
+pub fn inst_left(ctx: &mut TyCtxt, evar: usize, ty: &Ty) -> Result<(), TypeError> {
     trace!("inst_left/enter");
     if !ctx.contains_evar(evar) {
         trace!("inst_left/leave: no evar in ctx");
-        return None;
+        return Err(unknown_evar(evar, ty.span));
     }
 
-    match ty {
+    match &ty.kind {
+        // InstLTop (not in paper)
+        //
+        // As with <:Top, this puts no constraint on the existential, so it is
+        // left unsolved. This is reached from InstLArr and InstRArr, as in
+        // `â <: bool -> top`.
+        TyKind::Top => (),
         // InstLSolve
-        ty if ty.is_mono_wellformed_in(ctx.slice_until_evar(evar)?) => {
-            ctx.solve_evar(evar, ty.clone())?;
+        //
+        // A type with `top` or `never` inside it is instead taken apart, so
+        // that the existentials standing for those parts are left unsolved.
+        _ if !has_extremes(ty)
+            && ty.is_mono_wellformed_in(
+                ctx.slice_until_evar(evar)
+                    .ok_or_else(|| unknown_evar(evar, ty.span))?,
+            ) =>
+        {
+            ctx.solve_evar(evar, ty.clone())
+                .ok_or_else(|| unknown_evar(evar, ty.span))?;
         }
         // InstLReach
-        Ty::ExstVar(beta) => {
+        TyKind::ExstVar(beta) => {
             if !ctx.contains_evar(*beta) {
                 trace!("inst_left/leave: InstLReach: no beta in ctx");
-                return None;
+                return Err(unknown_evar(*beta, ty.span));
             }
-            ctx.solve_evar(*beta, Ty::ExstVar(evar));
+            ctx.solve_evar(*beta, ty.with_kind(TyKind::ExstVar(evar)));
         }
         // InstLArr
-        Ty::Arrow(a1, a2) => {
+        TyKind::Arrow(a1, a2) => {
             let alpha2 = ctx.fresh_evar();
             let alpha1 = ctx.fresh_evar();
-            ctx.insert_unsolved_before_evar(evar, alpha2)?;
-            ctx.insert_unsolved_before_evar(evar, alpha1)?;
+            ctx.insert_unsolved_before_evar(evar, alpha2)
+                .ok_or_else(|| unknown_evar(evar, ty.span))?;
+            ctx.insert_unsolved_before_evar(evar, alpha1)
+                .ok_or_else(|| unknown_evar(evar, ty.span))?;
             ctx.solve_evar(
                 evar,
-                Ty::Arrow(Box::new(Ty::ExstVar(alpha1)), Box::new(Ty::ExstVar(alpha2))),
-            )?;
+                ty.with_kind(TyKind::Arrow(
+                    Box::new(a1.with_kind(TyKind::ExstVar(alpha1))),
+                    Box::new(a2.with_kind(TyKind::ExstVar(alpha2))),
+                )),
+            )
+            .ok_or_else(|| unknown_evar(evar, ty.span))?;
             inst_right(ctx, a1, alpha1)?;
             let a2 = a2.clone().subst_ctx(ctx);
             inst_left(ctx, alpha2, &a2)?;
         }
+        // InstLProd (not in paper)
+        TyKind::Product(tys) => {
+            let alphas = articulate_with(ctx, evar, ty, tys, TyKind::Product)?;
+            for (alpha, ty) in alphas.into_iter().zip(tys) {
+                let ty = ty.clone().subst_ctx(ctx);
+                inst_left(ctx, alpha, &ty)?;
+            }
+        }
+        // InstLRecord; InstLVariant (not in paper)
+        //
+        // This only tries types with exactly the fields of `ty`, although
+        // records with more fields, or variants with fewer, would also be
+        // subtypes.
+        TyKind::Record(row) | TyKind::Variant(row) => {
+            let alphas = articulate_row(ctx, evar, ty, row)?;
+            for (alpha, (_, ty)) in alphas.into_iter().zip(&row.fields) {
+                let ty = ty.clone().subst_ctx(ctx);
+                inst_left(ctx, alpha, &ty)?;
+            }
+        }
+        // InstLData (not in paper)
+        TyKind::Data(name, args) => {
+            let alphas =
+                articulate_with(ctx, evar, ty, args, |tys| TyKind::Data(name.clone(), tys))?;
+            for (alpha, arg) in alphas.into_iter().zip(args) {
+                let arg = arg.clone().subst_ctx(ctx);
+                equate(ctx, &arg.with_kind(TyKind::ExstVar(alpha)), &arg)?;
+            }
+        }
+        // InstLSum (not in paper)
+        TyKind::Sum(a1, a2) => {
+            let (alpha1, alpha2) = articulate_sum(ctx, evar, ty, a1, a2)?;
+            inst_left(ctx, alpha1, a1)?;
+            let a2 = a2.clone().subst_ctx(ctx);
+            inst_left(ctx, alpha2, &a2)?;
+        }
+        // InstLAnd (not in paper)
+        TyKind::Intersection(a1, a2) => {
+            inst_left(ctx, evar, a1)?;
+            let alpha = ty.with_kind(TyKind::ExstVar(evar)).subst_ctx(ctx);
+            let a2 = a2.clone().subst_ctx(ctx);
+            subtype(ctx, &alpha, &a2)?;
+        }
+        // InstLOr (not in paper)
+        TyKind::Union(a1, a2) => {
+            let alpha = ty.with_kind(TyKind::ExstVar(evar));
+            let alternatives = vec![(alpha.clone(), *a1.clone()), (alpha, *a2.clone())];
+            if !first_subtype(ctx, alternatives) {
+                trace!("inst_left/leave: InstLOr: neither side");
+                return Err(not_subtype(ctx, &ty.with_kind(TyKind::ExstVar(evar)), ty));
+            }
+        }
         // InstLAllR
-        Ty::Forall(body) => {
-            ctx.add_uvar();
-            inst_left(ctx, evar, body)?;
-            ctx.drop_after_uvar(0)?;
+        TyKind::Forall(kind, bound, body) => {
+            let uvar = ctx.fresh_uvar();
+            ctx.add_uvar(uvar, kind.clone(), bound.as_deref().cloned());
+            let body = body.clone().open(&ty.with_kind(TyKind::UnivVar(uvar)));
+            inst_left(ctx, evar, &body)?;
+            ctx.drop_after_uvar(uvar)
+                .ok_or_else(|| unknown_uvar(uvar, ty.span))?;
+        }
+        // InstLExistsR (dual to InstRAllL)
+        TyKind::Exists(body) => {
+            let beta = ctx.fresh_evar();
+            ctx.add_marker(beta);
+            ctx.add_unsolved(beta);
+            let body = body.clone().open(&ty.with_kind(TyKind::ExstVar(beta)));
+            inst_left(ctx, evar, &body)?;
+            ctx.drop_after_marker(beta)
+                .ok_or_else(|| unknown_evar(beta, ty.span))?;
         }
         _ => {
             trace!("inst_left/leave: not inst'able");
-            return None;
+            return Err(not_subtype(ctx, &ty.with_kind(TyKind::ExstVar(evar)), ty));
         }
     }
 
     trace!("inst_left/leave: ok");
-    Some(())
+    Ok(())
 }
 
 // ty =<: evar
 #[cfg_attr(feature = "trace", instrument(level = "trace"))]
-pub fn inst_right(ctx: &mut TyCtxt, ty: &Ty, evar: usize) -> Option<()> {
+pub fn inst_right(ctx: &mut TyCtxt, ty: &Ty, evar: usize) -> Result<(), TypeError> {
     trace!("inst_right/enter");
     if !ctx.contains_evar(evar) {
         trace!("inst_right/leave: no evar in ctx");
-        return None;
+        return Err(unknown_evar(evar, ty.span));
     }
 
-    match ty {
+    match &ty.kind {
+        // InstRNever (not in paper)
+        //
+        // The same, for <:Never.
+        TyKind::Never => (),
         // InstRSolve
-        ty if ty.is_mono_wellformed_in(ctx.slice_until_evar(evar)?) => {
-            ctx.solve_evar(evar, ty.clone())?;
+        //
+        // A type with `top` or `never` inside it is instead taken apart, so
+        // that the existentials standing for those parts are left unsolved.
+        _ if !has_extremes(ty)
+            && ty.is_mono_wellformed_in(
+                ctx.slice_until_evar(evar)
+                    .ok_or_else(|| unknown_evar(evar, ty.span))?,
+            ) =>
+        {
+            ctx.solve_evar(evar, ty.clone())
+                .ok_or_else(|| unknown_evar(evar, ty.span))?;
         }
         // InstRReach
-        Ty::ExstVar(beta) => {
+        TyKind::ExstVar(beta) => {
             if !ctx.contains_evar(*beta) {
                 trace!("inst_right/leave: InstRReach: no beta in ctx");
-                return None;
+                return Err(unknown_evar(*beta, ty.span));
             }
-            ctx.solve_evar(*beta, Ty::ExstVar(evar));
+            ctx.solve_evar(*beta, ty.with_kind(TyKind::ExstVar(evar)));
         }
         // InstRArr
-        Ty::Arrow(a1, a2) => {
+        TyKind::Arrow(a1, a2) => {
             let alpha2 = ctx.fresh_evar();
             let alpha1 = ctx.fresh_evar();
-            ctx.insert_unsolved_before_evar(evar, alpha2)?;
-            ctx.insert_unsolved_before_evar(evar, alpha1)?;
+            ctx.insert_unsolved_before_evar(evar, alpha2)
+                .ok_or_else(|| unknown_evar(evar, ty.span))?;
+            ctx.insert_unsolved_before_evar(evar, alpha1)
+                .ok_or_else(|| unknown_evar(evar, ty.span))?;
             ctx.solve_evar(
                 evar,
-                Ty::Arrow(Box::new(Ty::ExstVar(alpha1)), Box::new(Ty::ExstVar(alpha2))),
-            )?;
+                ty.with_kind(TyKind::Arrow(
+                    Box::new(a1.with_kind(TyKind::ExstVar(alpha1))),
+                    Box::new(a2.with_kind(TyKind::ExstVar(alpha2))),
+                )),
+            )
+            .ok_or_else(|| unknown_evar(evar, ty.span))?;
             inst_left(ctx, alpha1, a1)?;
             let a2 = a2.clone().subst_ctx(ctx);
             inst_right(ctx, &a2, alpha2)?;
         }
+        // InstRProd (not in paper)
+        TyKind::Product(tys) => {
+            let alphas = articulate_with(ctx, evar, ty, tys, TyKind::Product)?;
+            for (alpha, ty) in alphas.into_iter().zip(tys) {
+                let ty = ty.clone().subst_ctx(ctx);
+                inst_right(ctx, &ty, alpha)?;
+            }
+        }
+        // InstRRecord; InstRVariant (not in paper)
+        //
+        // This only tries types with exactly the fields of `ty`, although
+        // records with fewer fields, or variants with more, would also be
+        // supertypes.
+        TyKind::Record(row) | TyKind::Variant(row) => {
+            let alphas = articulate_row(ctx, evar, ty, row)?;
+            for (alpha, (_, ty)) in alphas.into_iter().zip(&row.fields) {
+                let ty = ty.clone().subst_ctx(ctx);
+                inst_right(ctx, &ty, alpha)?;
+            }
+        }
+        // InstRData (not in paper)
+        TyKind::Data(name, args) => {
+            let alphas =
+                articulate_with(ctx, evar, ty, args, |tys| TyKind::Data(name.clone(), tys))?;
+            for (alpha, arg) in alphas.into_iter().zip(args) {
+                let arg = arg.clone().subst_ctx(ctx);
+                equate(ctx, &arg, &arg.with_kind(TyKind::ExstVar(alpha)))?;
+            }
+        }
+        // InstRSum (not in paper)
+        TyKind::Sum(a1, a2) => {
+            let (alpha1, alpha2) = articulate_sum(ctx, evar, ty, a1, a2)?;
+            inst_right(ctx, a1, alpha1)?;
+            let a2 = a2.clone().subst_ctx(ctx);
+            inst_right(ctx, &a2, alpha2)?;
+        }
+        // InstROr (not in paper)
+        TyKind::Union(a1, a2) => {
+            inst_right(ctx, a1, evar)?;
+            let a2 = a2.clone().subst_ctx(ctx);
+            let alpha = ty.with_kind(TyKind::ExstVar(evar)).subst_ctx(ctx);
+            subtype(ctx, &a2, &alpha)?;
+        }
+        // InstRAnd (not in paper)
+        TyKind::Intersection(a1, a2) => {
+            let alpha = ty.with_kind(TyKind::ExstVar(evar));
+            let alternatives = vec![(*a1.clone(), alpha.clone()), (*a2.clone(), alpha)];
+            if !first_subtype(ctx, alternatives) {
+                trace!("inst_right/leave: InstRAnd: neither side");
+                return Err(not_subtype(ctx, ty, &ty.with_kind(TyKind::ExstVar(evar))));
+            }
+        }
         // InstRAllL
-        Ty::Forall(body) => {
+        TyKind::Forall(kind, bound, body) => {
             let beta = ctx.fresh_evar();
             ctx.add_marker(beta);
-            ctx.add_unsolved(beta);
-            let body = body.clone().subst_uvar0_bare(&Ty::ExstVar(beta));
+            ctx.add_unsolved_of_kind(beta, kind);
+            let beta_ty = ty.with_kind(TyKind::ExstVar(beta));
+            let body = body.clone().open(&beta_ty);
+            inst_right(ctx, &body, evar)?;
+            if let Some(bound) = bound {
+                check_bound(ctx, &beta_ty, bound)?;
+            }
+            ctx.drop_after_marker(beta)
+                .ok_or_else(|| unknown_evar(beta, ty.span))?;
+        }
+        // InstRExistsL (dual to InstLAllR)
+        TyKind::Exists(body) => {
+            let uvar = ctx.fresh_uvar();
+            ctx.add_uvar(uvar, Kind::Type, None);
+            let body = body.clone().open(&ty.with_kind(TyKind::UnivVar(uvar)));
             inst_right(ctx, &body, evar)?;
-            ctx.drop_after_marker(beta)?;
+            ctx.drop_after_uvar(uvar)
+                .ok_or_else(|| unknown_uvar(uvar, ty.span))?;
         }
         _ => {
             trace!("inst_right/leave: not inst'able");
-            return None;
+            return Err(not_subtype(ctx, ty, &ty.with_kind(TyKind::ExstVar(evar))));
         }
     }
 
     trace!("inst_right/leave: ok");
-    Some(())
+    Ok(())
+}
+
+/// Check that `ty`, which instantiates a quantifier bounded by `bound`, is a
+/// subtype of that bound.
+pub fn check_bound(ctx: &mut TyCtxt, ty: &Ty, bound: &Ty) -> Result<(), TypeError> {
+    let ty = ty.clone().subst_ctx(ctx);
+    let bound = bound.clone().subst_ctx(ctx);
+    subtype(ctx, &ty, &bound).map_err(|_| {
+        TypeError::new(
+            TypeErrorKind::OutOfBound(
+                Box::new(ty.clone().subst_ctx(ctx)),
+                Box::new(bound.clone().subst_ctx(ctx)),
+            ),
+            ty.span,
+        )
+    })
+}
+
+/// Split the data type `name` applied to `args` into a type operator, the
+/// data type applied to all but the last argument, and that last argument.
+fn split_data(
+    ctx: &TyCtxt,
+    name: &str,
+    args: &[Ty],
+    ty1: &Ty,
+    ty2: &Ty,
+) -> Result<(Ty, Ty), TypeError> {
+    let kind = ctx
+        .get_data(name)
+        .and_then(|data| data.params.last())
+        .cloned()
+        .ok_or_else(|| not_subtype(ctx, ty1, ty2))?;
+    let (last, init) = args.split_last().expect("there is at least one argument");
+    let span = last.span;
+    let init = init
+        .iter()
+        .map(|arg| arg.clone().shift(1))
+        .chain(std::iter::once(Ty::new(TyKind::Var(0), span)))
+        .collect();
+    let partial = Ty::new(
+        TyKind::Lam(
+            kind,
+            Box::new(Ty::new(TyKind::Data(name.to_string(), init), span)),
+        ),
+        span,
+    );
+    Ok((partial, last.clone()))
+}
+
+/// Whether `ty` has `top` or `never` somewhere inside the arrows, products,
+/// sums, records and variants that it is built out of, which the
+/// instantiation rules can take apart.
+fn has_extremes(ty: &Ty) -> bool {
+    let inside = |ty: &Ty| matches!(ty.kind, TyKind::Top | TyKind::Never) || has_extremes(ty);
+    match &ty.kind {
+        TyKind::Arrow(a, b) | TyKind::Sum(a, b) => inside(a) || inside(b),
+        TyKind::Product(tys) => tys.iter().any(inside),
+        TyKind::Record(row) | TyKind::Variant(row) => row.fields.iter().any(|(_, ty)| inside(ty)),
+        _ => false,
+    }
+}
+
+/// Check that two types are subtypes of each other.
+fn equate(ctx: &mut TyCtxt, ty1: &Ty, ty2: &Ty) -> Result<(), TypeError> {
+    subtype(ctx, ty1, ty2)?;
+    let ty1 = ty1.clone().subst_ctx(ctx);
+    let ty2 = ty2.clone().subst_ctx(ctx);
+    subtype(ctx, &ty2, &ty1)
+}
+
+/// Make `n` fresh existentials just before `evar`, in order, so that `evar`
+/// can be solved with a type built out of them.
+pub fn articulate(
+    ctx: &mut TyCtxt,
+    evar: usize,
+    n: usize,
+    span: Span,
+) -> Result<Vec<usize>, TypeError> {
+    let mut alphas = Vec::new();
+    for _ in 0..n {
+        let alpha = ctx.fresh_evar();
+        ctx.insert_unsolved_before_evar(evar, alpha)
+            .ok_or_else(|| unknown_evar(evar, span))?;
+        alphas.push(alpha);
+    }
+    Ok(alphas)
+}
+
+/// Solve `evar` with a type of the same shape as `ty`, built by `mk` out of a
+/// fresh existential for each of the component types `tys`, returning those
+/// existentials.
+fn articulate_with(
+    ctx: &mut TyCtxt,
+    evar: usize,
+    ty: &Ty,
+    tys: &[Ty],
+    mk: impl FnOnce(Vec<Ty>) -> TyKind,
+) -> Result<Vec<usize>, TypeError> {
+    let alphas = articulate(ctx, evar, tys.len(), ty.span)?;
+    let components = alphas
+        .iter()
+        .zip(tys)
+        .map(|(alpha, ty)| ty.with_kind(TyKind::ExstVar(*alpha)))
+        .collect();
+    ctx.solve_evar(evar, ty.with_kind(mk(components)))
+        .ok_or_else(|| unknown_evar(evar, ty.span))?;
+    Ok(alphas)
+}
+
+/// Solve `evar` with a record or variant type like `ty`, whose row is `row`,
+/// but with a fresh existential for each field, returning those existentials.
+fn articulate_row(
+    ctx: &mut TyCtxt,
+    evar: usize,
+    ty: &Ty,
+    row: &Row,
+) -> Result<Vec<usize>, TypeError> {
+    let tail = match row.tail.as_deref() {
+        Some(tail) => Some(articulate_tail(ctx, evar, tail)?),
+        None => None,
+    };
+    let tys = row
+        .fields
+        .iter()
+        .map(|(_, ty)| ty.clone())
+        .collect::<Vec<_>>();
+    let is_record = matches!(ty.kind, TyKind::Record(_));
+    articulate_with(ctx, evar, ty, &tys, |tys| {
+        let fields = row
+            .fields
+            .iter()
+            .map(|(label, _)| label.clone())
+            .zip(tys)
+            .collect();
+        let row = Row::new(fields, tail);
+        if is_record {
+            TyKind::Record(row)
+        } else {
+            TyKind::Variant(row)
+        }
+    })
+}
+
+/// A row variable that is well-formed before `evar` and equal to `tail`. If
+/// `tail` is an existential that comes after `evar`, it is solved with a
+/// fresh one before it.
+fn articulate_tail(ctx: &mut TyCtxt, evar: usize, tail: &Ty) -> Result<Ty, TypeError> {
+    let before = ctx
+        .slice_until_evar(evar)
+        .ok_or_else(|| unknown_evar(evar, tail.span))?;
+    if tail.is_mono_wellformed_in(before) {
+        return Ok(tail.clone());
+    }
+    match &tail.kind {
+        TyKind::ExstVar(rho) if ctx.evar_kind(*rho) == Some(Kind::Row) => {
+            let fresh = ctx.fresh_evar();
+            ctx.insert_unsolved_row_before_evar(evar, fresh)
+                .ok_or_else(|| unknown_evar(evar, tail.span))?;
+            let fresh = tail.with_kind(TyKind::ExstVar(fresh));
+            ctx.solve_evar(*rho, fresh.clone())
+                .ok_or_else(|| unknown_evar(*rho, tail.span))?;
+            Ok(fresh)
+        }
+        _ => {
+            trace!("articulate_tail: tail is not in scope");
+            Err(TypeError::new(
+                TypeErrorKind::IllFormedAnn(tail.clone().subst_ctx(ctx)),
+                tail.span,
+            ))
+        }
+    }
+}
+
+/// Solve the row existential `rho` with the given labels, each with a fresh
+/// existential for its type, followed by a fresh row existential, returning
+/// that row.
+pub fn extend_row(
+    ctx: &mut TyCtxt,
+    rho: usize,
+    labels: &[String],
+    span: Span,
+) -> Result<Row, TypeError> {
+    let alphas = articulate(ctx, rho, labels.len(), span)?;
+    let tail = ctx.fresh_evar();
+    ctx.insert_unsolved_row_before_evar(rho, tail)
+        .ok_or_else(|| unknown_evar(rho, span))?;
+    let fields = labels
+        .iter()
+        .cloned()
+        .zip(
+            alphas
+                .into_iter()
+                .map(|alpha| Ty::new(TyKind::ExstVar(alpha), span)),
+        )
+        .collect();
+    let row = Row::new(fields, Some(Ty::new(TyKind::ExstVar(tail), span)));
+    ctx.solve_evar(rho, Ty::new(TyKind::Row(row.clone()), span))
+        .ok_or_else(|| unknown_evar(rho, span))?;
+    Ok(row)
+}
+
+/// Relate two rows, where `wide` must have every field of `narrow`: `wide` is
+/// the subtype's row for records and the supertype's for variants, as given
+/// by `wide_is_sub`. Fields are related in the same direction as the types.
+///
+/// If `wide` lacks some of `narrow`'s fields, they are added to its tail, if
+/// that is a row existential. If `narrow` has a tail, it must be the same
+/// row as the rest of `wide`; so must an empty row if `exact` is set.
+/// Row existentials are solved by unification: they are given exactly the
+/// fields that are left over, rather than any subset or superset of them.
+fn subtype_rows(
+    ctx: &mut TyCtxt,
+    wide: &Row,
+    narrow: &Row,
+    wide_is_sub: bool,
+    exact: bool,
+    ty1: &Ty,
+    ty2: &Ty,
+) -> Result<(), TypeError> {
+    let mut wide = wide.clone();
+    let missing = narrow
+        .fields
+        .iter()
+        .filter(|(label, _)| wide.get(label).is_none())
+        .map(|(label, _)| label.clone())
+        .collect::<Vec<_>>();
+    if !missing.is_empty() {
+        match wide.tail.as_deref().map(|tail| &tail.kind) {
+            Some(TyKind::ExstVar(rho)) if ctx.evar_kind(*rho) == Some(Kind::Row) => {
+                let more = extend_row(ctx, *rho, &missing, ty1.span)?;
+                let mut fields = wide.fields;
+                fields.extend(more.fields);
+                wide = Row::new(fields, more.tail.map(|tail| *tail));
+            }
+            _ => {
+                trace!("subtype_rows: missing fields");
+                return Err(not_subtype(ctx, ty1, ty2));
+            }
+        }
+    }
+
+    for (label, n) in &narrow.fields {
+        let w = wide.get(label).cloned().map(|w| w.subst_ctx(ctx));
+        let n = n.clone().subst_ctx(ctx);
+        match w {
+            Some(w) if wide_is_sub => subtype(ctx, &w, &n)?,
+            Some(w) => subtype(ctx, &n, &w)?,
+            None => return Err(not_subtype(ctx, ty1, ty2)),
+        }
+    }
+
+    let rest = Row::new(
+        wide.fields
+            .into_iter()
+            .filter(|(label, _)| narrow.get(label).is_none())
+            .collect(),
+        wide.tail.map(|tail| *tail),
+    )
+    .map(|ty| ty.subst_ctx(ctx));
+    match narrow.tail.as_deref() {
+        Some(tail) => {
+            let tail = tail.clone().subst_ctx(ctx);
+            equate_tail(ctx, rest, &tail, wide_is_sub, ty1, ty2)
+        }
+        None if exact => equate_tail(
+            ctx,
+            rest,
+            &ty2.with_kind(TyKind::Row(Row::closed(Vec::new()))),
+            wide_is_sub,
+            ty1,
+            ty2,
+        ),
+        None => Ok(()),
+    }
+}
+
+/// Make `rest` the same row as `tail`, which is a row variable or a row.
+fn equate_tail(
+    ctx: &mut TyCtxt,
+    rest: Row,
+    tail: &Ty,
+    wide_is_sub: bool,
+    ty1: &Ty,
+    ty2: &Ty,
+) -> Result<(), TypeError> {
+    if let TyKind::Row(row) = &tail.kind {
+        return match (row.fields.is_empty(), row.tail.as_deref()) {
+            (true, Some(tail)) => equate_tail(ctx, rest, tail, wide_is_sub, ty1, ty2),
+            // The empty row.
+            (true, None) => match rest.tail.as_deref().map(|rest_tail| &rest_tail.kind) {
+                None if rest.fields.is_empty() => Ok(()),
+                Some(TyKind::ExstVar(rho))
+                    if rest.fields.is_empty() && ctx.evar_kind(*rho) == Some(Kind::Row) =>
+                {
+                    ctx.solve_evar(*rho, tail.clone())
+                        .ok_or_else(|| unknown_evar(*rho, tail.span))
+                }
+                _ => {
+                    trace!("equate_tail: extra fields");
+                    Err(not_subtype(ctx, ty1, ty2))
+                }
+            },
+            // The tail was solved while relating the fields.
+            (false, _) => subtype_rows(ctx, &rest, row, wide_is_sub, true, ty1, ty2),
+        };
+    }
+    if rest.fields.is_empty() && rest.tail.as_deref() == Some(tail) {
+        return Ok(());
+    }
+    match &tail.kind {
+        TyKind::ExstVar(rho)
+            if ctx.evar_kind(*rho) == Some(Kind::Row)
+                && !rest.tys().any(|ty| ty.contains_evar(*rho)) =>
+        {
+            let row = ty2.with_kind(TyKind::Row(rest.clone()));
+            if row.is_mono_wellformed_in(
+                ctx.slice_until_evar(*rho)
+                    .ok_or_else(|| unknown_evar(*rho, tail.span))?,
+            ) {
+                ctx.solve_evar(*rho, row)
+                    .ok_or_else(|| unknown_evar(*rho, tail.span))?;
+                return Ok(());
+            }
+            // Some of the rest only comes into scope after `rho`, so solve it
+            // with fresh existentials and relate those to the rest instead.
+            let labels = rest
+                .fields
+                .iter()
+                .map(|(label, _)| label.clone())
+                .collect::<Vec<_>>();
+            let alphas = articulate(ctx, *rho, labels.len(), tail.span)?;
+            let rest_tail = match rest.tail.as_deref() {
+                Some(rest_tail) => Some(articulate_tail(ctx, *rho, rest_tail)?),
+                None => None,
+            };
+            let fields = labels
+                .into_iter()
+                .zip(
+                    alphas
+                        .iter()
+                        .map(|alpha| tail.with_kind(TyKind::ExstVar(*alpha))),
+                )
+                .collect();
+            ctx.solve_evar(
+                *rho,
+                tail.with_kind(TyKind::Row(Row::new(fields, rest_tail))),
+            )
+            .ok_or_else(|| unknown_evar(*rho, tail.span))?;
+            for (alpha, (_, ty)) in alphas.into_iter().zip(rest.fields) {
+                let alpha = tail.with_kind(TyKind::ExstVar(alpha));
+                let ty = ty.subst_ctx(ctx);
+                if wide_is_sub {
+                    subtype(ctx, &ty, &alpha)?;
+                } else {
+                    subtype(ctx, &alpha, &ty)?;
+                }
+            }
+            Ok(())
+        }
+        _ => match rest.tail.as_deref().map(|rest_tail| &rest_tail.kind) {
+            // The rest is just a row existential, so solve it with the tail.
+            Some(TyKind::ExstVar(rho))
+                if rest.fields.is_empty()
+                    && ctx.evar_kind(*rho) == Some(Kind::Row)
+                    && !tail.contains_evar(*rho) =>
+            {
+                let before = ctx
+                    .slice_until_evar(*rho)
+                    .ok_or_else(|| unknown_evar(*rho, tail.span))?;
+                if !tail.is_mono_wellformed_in(before) {
+                    trace!("equate_tail: tail is not in scope");
+                    return Err(not_subtype(ctx, ty1, ty2));
+                }
+                ctx.solve_evar(*rho, tail.clone())
+                    .ok_or_else(|| unknown_evar(*rho, tail.span))
+            }
+            _ => {
+                trace!("equate_tail: rows differ");
+                Err(not_subtype(ctx, ty1, ty2))
+            }
+        },
+    }
+}
+
+/// Solve `evar` with a sum of two fresh existentials, returning them.
+fn articulate_sum(
+    ctx: &mut TyCtxt,
+    evar: usize,
+    ty: &Ty,
+    a1: &Ty,
+    a2: &Ty,
+) -> Result<(usize, usize), TypeError> {
+    let alphas = articulate(ctx, evar, 2, ty.span)?;
+    ctx.solve_evar(
+        evar,
+        ty.with_kind(TyKind::Sum(
+            Box::new(a1.with_kind(TyKind::ExstVar(alphas[0]))),
+            Box::new(a2.with_kind(TyKind::ExstVar(alphas[1]))),
+        )),
+    )
+    .ok_or_else(|| unknown_evar(evar, ty.span))?;
+    Ok((alphas[0], alphas[1]))
+}
+
+fn not_subtype(ctx: &TyCtxt, ty1: &Ty, ty2: &Ty) -> TypeError {
+    TypeError::new(
+        TypeErrorKind::NotSubtype(
+            Box::new(ty1.clone().subst_ctx(ctx)),
+            Box::new(ty2.clone().subst_ctx(ctx)),
+        ),
+        ty1.span,
+    )
+}
+
+fn unknown_evar(evar: usize, span: Span) -> TypeError {
+    TypeError::new(TypeErrorKind::UnknownEvar(evar), span)
+}
+
+fn unknown_uvar(uvar: usize, span: Span) -> TypeError {
+    TypeError::new(TypeErrorKind::UnknownUvar(uvar), span)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grammar::TypeParser;
+
+    fn ty(source: &str) -> Ty {
+        TypeParser::new()
+            .parse(source)
+            .unwrap()
+            .into_core()
+            .unwrap()
+    }
+
+    fn is_subtype(sub: &str, sup: &str) -> bool {
+        subtype(&mut TyCtxt::default(), &ty(sub), &ty(sup)).is_ok()
+    }
+
+    /// Add a fresh unsolved existential of kind `*` to `ctx`.
+    fn evar(ctx: &mut TyCtxt) -> Ty {
+        let evar = ctx.fresh_evar();
+        ctx.add_unsolved(evar);
+        Ty::new(TyKind::ExstVar(evar), Span::default())
+    }
+
+    #[test]
+    fn products_are_compared_componentwise() {
+        assert!(is_subtype(
+            "('a => 'a -> 'a) * bool",
+            "(bool -> bool) * bool"
+        ));
+        assert!(!is_subtype("bool * unit", "bool * unit * bool"));
+        assert!(!is_subtype("bool * unit", "bool * bool"));
+    }
+
+    #[test]
+    fn sums_are_compared_side_by_side() {
+        assert!(is_subtype(
+            "('a => 'a -> 'a) + unit",
+            "(bool -> bool) + unit"
+        ));
+        assert!(!is_subtype("bool + unit", "unit + bool"));
+    }
+
+    #[test]
+    fn records_have_width_and_depth_subtyping() {
+        assert!(is_subtype("{ x : bool, y : unit }", "{ x : bool }"));
+        assert!(is_subtype("{ f : 'a => 'a -> 'a }", "{ f : bool -> bool }"));
+        assert!(!is_subtype("{ x : bool }", "{ x : bool, y : unit }"));
+        assert!(!is_subtype("{ x : bool }", "{ x : unit }"));
+    }
+
+    #[test]
+    fn variants_are_subtypes_of_those_with_more_cases() {
+        assert!(is_subtype("< a : bool >", "< a : bool, b : unit >"));
+        assert!(!is_subtype("< a : bool, b : unit >", "< a : bool >"));
+    }
+
+    #[test]
+    fn row_variables_are_solved_with_the_leftover_fields() {
+        assert!(is_subtype(
+            "'r => { x : bool | 'r } -> bool",
+            "{ x : bool, y : unit } -> bool"
+        ));
+        assert!(!is_subtype(
+            "'r => { x : bool | 'r } -> bool",
+            "{ y : unit } -> bool"
+        ));
+    }
+
+    #[test]
+    fn recursive_types_are_only_related_when_equal() {
+        assert!(is_subtype(
+            "mu 'a. unit + bool * 'a",
+            "mu 'b. unit + bool * 'b"
+        ));
+        assert!(!is_subtype(
+            "mu 'a. unit + bool * 'a",
+            "mu 'b. unit + unit * 'b"
+        ));
+    }
+
+    #[test]
+    fn existential_types_accept_any_instance() {
+        assert!(is_subtype(
+            "bool * (bool -> bool)",
+            "exists 'a. 'a * ('a -> bool)"
+        ));
+        assert!(!is_subtype(
+            "bool * (unit -> bool)",
+            "exists 'a. 'a * ('a -> bool)"
+        ));
+    }
+
+    #[test]
+    fn type_operators_are_compared_up_to_beta_reduction() {
+        assert!(is_subtype("(\\'a. 'a -> 'a) bool", "bool -> bool"));
+        assert!(is_subtype(
+            "('f : * -> *) => 'f bool -> 'f bool",
+            "('f : * -> *) => 'f bool -> 'f bool"
+        ));
+        assert!(!is_subtype(
+            "('f : * -> *) => 'f bool -> 'f unit",
+            "('f : * -> *) => 'f bool -> 'f bool"
+        ));
+    }
+
+    #[test]
+    fn bounded_quantifiers_need_equal_bounds() {
+        assert!(is_subtype(
+            "'a <: { x : bool } => 'a -> 'a",
+            "'b <: { x : bool } => 'b -> 'b"
+        ));
+        assert!(!is_subtype(
+            "'a <: { x : bool } => 'a -> 'a",
+            "'a <: { x : bool, y : unit } => 'a -> 'a"
+        ));
+    }
+
+    #[test]
+    fn bounded_and_unbounded_quantifiers_are_not_related() {
+        assert!(!is_subtype(
+            "'a <: { x : bool } => 'a -> bool",
+            "'b => { x : bool } -> bool"
+        ));
+        assert!(!is_subtype(
+            "'a => 'a -> 'a",
+            "'b <: { x : bool } => 'b -> 'b"
+        ));
+    }
+
+    #[test]
+    fn bounded_quantifiers_are_instantiated_within_their_bounds() {
+        assert!(is_subtype(
+            "'a <: { x : bool } => 'a -> 'a",
+            "{ x : bool, y : unit } -> { x : bool, y : unit }"
+        ));
+        let err = subtype(
+            &mut TyCtxt::default(),
+            &ty("'a <: { x : bool } => 'a -> 'a"),
+            &ty("unit -> unit"),
+        )
+        .unwrap_err();
+        assert!(matches!(err.kind, TypeErrorKind::OutOfBound(..)));
+    }
+
+    #[test]
+    fn bounded_variables_are_subtypes_of_their_bounds() {
+        let mut ctx = TyCtxt::default();
+        let uvar = ctx.fresh_uvar();
+        ctx.add_uvar(uvar, Kind::Type, Some(ty("{ x : bool, y : unit }")));
+        let var = Ty::new(TyKind::UnivVar(uvar), Span::default());
+        assert!(subtype(&mut ctx, &var, &ty("{ x : bool }")).is_ok());
+        assert!(check_bound(&mut ctx, &var, &ty("{ y : unit }")).is_ok());
+        assert!(subtype(&mut ctx, &var, &ty("{ z : bool }")).is_err());
+        assert!(check_bound(&mut ctx, &ty("unit"), &ty("{ y : unit }")).is_err());
+    }
+
+    #[test]
+    fn intersections_are_subtypes_of_each_side() {
+        assert!(is_subtype(
+            "{ x : bool, y : unit }",
+            "{ x : bool } & { y : unit }"
+        ));
+        assert!(!is_subtype("{ x : bool }", "{ x : bool } & { y : unit }"));
+        assert!(is_subtype(
+            "(bool -> bool) & (unit -> unit)",
+            "unit -> unit"
+        ));
+        assert!(!is_subtype(
+            "(bool -> bool) & (unit -> unit)",
+            "bool -> unit"
+        ));
+    }
+
+    #[test]
+    fn unions_are_supertypes_of_each_side() {
+        assert!(is_subtype("bool", "bool | unit"));
+        assert!(is_subtype("bool | unit", "unit | bool"));
+        assert!(!is_subtype("bool | unit", "bool"));
+    }
+
+    #[test]
+    fn failed_alternatives_leave_existentials_unsolved() {
+        let mut ctx = TyCtxt::default();
+        let alpha = evar(&mut ctx);
+        let sup = Ty::new(
+            TyKind::Arrow(Box::new(alpha.clone()), Box::new(ty("unit"))),
+            Span::default(),
+        );
+        // The left side solves `alpha` with `bool` before failing, so it
+        // must be undone before the right side is tried.
+        subtype(&mut ctx, &ty("(bool -> bool) & (unit -> unit)"), &sup).unwrap();
+        assert_eq!(alpha.subst_ctx(&ctx), ty("unit"));
+    }
+
+    #[test]
+    fn union_errors_name_the_whole_union() {
+        let err = subtype(&mut TyCtxt::default(), &ty("bool | unit"), &ty("bool")).unwrap_err();
+        assert_eq!(
+            err.kind,
+            TypeErrorKind::NotSubtype(Box::new(ty("bool | unit")), Box::new(ty("bool")))
+        );
+    }
+
+    #[test]
+    fn top_and_never_bound_every_type() {
+        assert!(is_subtype("bool -> bool", "top"));
+        assert!(is_subtype("never", "{ x : bool }"));
+        assert!(is_subtype("top -> never", "bool -> unit"));
+        assert!(!is_subtype("top", "bool"));
+        assert!(!is_subtype("bool", "never"));
+    }
+
+    #[test]
+    fn existentials_are_not_solved_by_top_or_never() {
+        let mut ctx = TyCtxt::default();
+        let alpha = evar(&mut ctx);
+        subtype(&mut ctx, &alpha, &ty("top")).unwrap();
+        subtype(&mut ctx, &ty("never"), &alpha).unwrap();
+        assert_eq!(alpha.clone().subst_ctx(&ctx), alpha);
+
+        // Only the part of an arrow that is `top` is left unsolved.
+        let beta = evar(&mut ctx);
+        subtype(&mut ctx, &beta, &ty("bool -> top")).unwrap();
+        match beta.subst_ctx(&ctx).kind {
+            TyKind::Arrow(inp, out) => {
+                assert_eq!(*inp, ty("bool"));
+                assert!(matches!(out.kind, TyKind::ExstVar(_)));
+            }
+            kind => panic!("solved with {:?}", kind),
+        }
+    }
 }